@@ -0,0 +1,521 @@
+use std::error::Error;
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::cost::{estimate_costs, min_logrows, CostError};
+use crate::fieldutils::{dequantize, i128_to_felt, quantize_float};
+use crate::forward::forward;
+use crate::graphwitness::GraphWitness;
+use crate::model::{Model, NodeType, ParsedNodes, SupportedOp};
+use crate::runargs::RunArgs;
+use crate::supportedop::PolyOp;
+use crate::tensor::Tensor;
+use crate::utils::{scale_to_multiplier, Scale};
+
+/// Sample data used to calibrate the run args.
+#[derive(Clone, Debug)]
+pub enum CalibrationData {
+    /// Witnesses generated with the base run args, their felts are dequantized at the base `input_scale`.
+    Witnesses(Vec<GraphWitness>),
+    /// Float inputs: one entry per sample, holding every input tensor of the model flattened.
+    Floats(Vec<Vec<Vec<f32>>>),
+}
+
+/// The accuracy target and the search space of a calibration.
+#[derive(Clone, Debug)]
+pub struct CalibrationTarget {
+    /// The maximum quantization error of inputs, params and outputs, relative to their largest magnitude.
+    pub max_relative_error: f64,
+    /// The candidate scales, searched independently for `input_scale` and `param_scale`.
+    pub scales: Vec<Scale>,
+    /// The candidate `num_inner_cols`.
+    pub num_inner_cols: Vec<usize>,
+}
+
+impl Default for CalibrationTarget {
+    fn default() -> Self {
+        CalibrationTarget {
+            max_relative_error: 0.01,
+            scales: (1..=12).collect(),
+            num_inner_cols: vec![1, 2, 4],
+        }
+    }
+}
+
+/// A candidate set of run args and how it fares.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// The run args, with `logrows` and `lookup_range` set to the smallest values that fit.
+    pub run_args: RunArgs,
+    /// The estimated number of rows used by the model.
+    pub total_rows: usize,
+    /// The worst quantization error of the inputs, relative to their largest magnitude.
+    pub input_error: f64,
+    /// The worst quantization error of the params, relative to their largest magnitude.
+    pub param_error: f64,
+    /// The worst error of the outputs, relative to their largest magnitude, or `None` if the model cannot be evaluated.
+    pub output_error: Option<f64>,
+    /// Whether the candidate meets the accuracy target.
+    pub meets_target: bool,
+}
+
+/// The outcome of a calibration.
+#[derive(Clone, Debug, Default)]
+pub struct CalibrationResult {
+    /// The candidate meeting the accuracy target with the fewest rows, if any does.
+    pub best: Option<Candidate>,
+    /// Every candidate considered.
+    pub candidates: Vec<Candidate>,
+}
+
+impl fmt::Display for CalibrationResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6} {:>6} {:>6} {:>8} {:>10} {:>24} {:>12} {:>12} {:>12}",
+            "input", "param", "cols", "logrows", "rows", "lookup_range", "input err", "param err", "output err"
+        )?;
+        for c in &self.candidates {
+            writeln!(
+                f,
+                "{:>6} {:>6} {:>6} {:>8} {:>10} {:>24} {:>12.6} {:>12.6} {:>12}{}",
+                c.run_args.input_scale,
+                c.run_args.param_scale,
+                c.run_args.num_inner_cols,
+                c.run_args.logrows,
+                c.total_rows,
+                format!("{:?}", c.run_args.lookup_range),
+                c.input_error,
+                c.param_error,
+                c.output_error.map(|e| format!("{:.6}", e)).unwrap_or_else(|| "-".to_string()),
+                if c.meets_target { "" } else { " (misses target)" }
+            )?;
+        }
+        match &self.best {
+            Some(best) => writeln!(
+                f,
+                "best: input_scale={} param_scale={} num_inner_cols={} logrows={} lookup_range={:?}",
+                best.run_args.input_scale,
+                best.run_args.param_scale,
+                best.run_args.num_inner_cols,
+                best.run_args.logrows,
+                best.run_args.lookup_range
+            ),
+            None => writeln!(f, "no candidate meets the accuracy target"),
+        }
+    }
+}
+
+/// Searches over `input_scale`, `param_scale` and `num_inner_cols` for the run args that minimize the circuit's rows,
+/// subject to the quantization error of the inputs, params and outputs staying under `target.max_relative_error`.
+/// `lookup_range` is extrapolated from the sample data to every candidate scale, and `logrows` is the smallest that fits
+/// both the estimated rows and a single lookup table column.
+///
+/// The output error of a candidate is measured by running the model, as quantized at the `base` scales, over the samples
+/// and params rounded to the candidate's scales, against a run over the unrounded ones. Rounding below the resolution of
+/// the base scales goes unseen, so the base scales should be at least as fine as the candidates'. Models that cannot be
+/// evaluated are judged on their input and param errors alone.
+pub fn calibrate(
+    model: &Model,
+    base: &RunArgs,
+    data: &CalibrationData,
+    target: &CalibrationTarget,
+) -> Result<CalibrationResult, Box<dyn Error>> {
+    let samples = samples(data, base.input_scale);
+    let inputs: Vec<f64> = samples.iter().flatten().flatten().copied().collect();
+    if inputs.is_empty() {
+        return Err("no calibration data provided".into());
+    }
+    let mut params = vec![];
+    collect_params(&model.graph, base.param_scale, &mut params);
+
+    let max_abs_input = max_abs(&inputs);
+    let max_abs_param = max_abs(&params);
+    let base_bound = match data {
+        CalibrationData::Witnesses(witnesses) => witnesses
+            .iter()
+            .map(|w| w.max_lookup_inputs.abs().max(w.min_lookup_inputs.abs()))
+            .max()
+            .unwrap_or(0) as f64,
+        CalibrationData::Floats(_) => max_abs_input.max(1.0) * max_abs_param.max(1.0),
+    };
+    let reference = run_samples(model, base, &samples, base.input_scale).ok();
+
+    let mut result = CalibrationResult::default();
+    for input_scale in &target.scales {
+        let input_error = relative_error(&inputs, *input_scale);
+        for param_scale in &target.scales {
+            let param_error = relative_error(&params, *param_scale);
+            let output_error = reference.as_ref().and_then(|reference| {
+                let rounded = params_on_grid(model, base.param_scale, *param_scale).ok()?;
+                let outputs = run_samples(&rounded, base, &samples, *input_scale).ok()?;
+                Some(output_error(reference, &outputs))
+            });
+            let lookup_bound = match data {
+                CalibrationData::Witnesses(_) => {
+                    base_bound
+                        * scale_to_multiplier(input_scale + param_scale - base.input_scale - base.param_scale)
+                }
+                CalibrationData::Floats(_) => base_bound * scale_to_multiplier(input_scale + param_scale),
+            };
+            let lookup_bound = lookup_bound.ceil().min(i128::MAX as f64) as i128;
+
+            for cols in &target.num_inner_cols {
+                let mut run_args = base.clone();
+                run_args.input_scale = *input_scale;
+                run_args.param_scale = *param_scale;
+                run_args.num_inner_cols = *cols;
+                run_args.lookup_range = (-lookup_bound, lookup_bound);
+
+                // candidates whose rows or lookup table don't fit in 2^MAX_LOGROWS rows are infeasible
+                let costs = match estimate_costs(model, &run_args) {
+                    Ok(costs) => costs,
+                    Err(CostError::TooManyRows(_)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let table_rows = lookup_bound
+                    .saturating_mul(2)
+                    .saturating_add(1)
+                    .min(usize::MAX as i128) as usize;
+                run_args.logrows = match min_logrows(table_rows) {
+                    Ok(table) => costs.min_logrows.max(table),
+                    Err(_) => continue,
+                };
+
+                result.candidates.push(Candidate {
+                    run_args,
+                    total_rows: costs.total_rows,
+                    input_error,
+                    param_error,
+                    output_error,
+                    meets_target: input_error <= target.max_relative_error
+                        && param_error <= target.max_relative_error
+                        && output_error.map_or(true, |e| e <= target.max_relative_error),
+                });
+            }
+        }
+    }
+
+    result.best = result
+        .candidates
+        .iter()
+        .filter(|c| c.meets_target)
+        .min_by(|a, b| {
+            a.run_args
+                .logrows
+                .cmp(&b.run_args.logrows)
+                .then(a.total_rows.cmp(&b.total_rows))
+                // at equal cost prefer the more accurate scales, then the narrower layout
+                .then(b.run_args.input_scale.cmp(&a.run_args.input_scale))
+                .then(b.run_args.param_scale.cmp(&a.run_args.param_scale))
+                .then(a.run_args.num_inner_cols.cmp(&b.run_args.num_inner_cols))
+        })
+        .cloned();
+
+    Ok(result)
+}
+
+/// Returns every sample as one flattened float vector per model input.
+fn samples(data: &CalibrationData, input_scale: Scale) -> Vec<Vec<Vec<f64>>> {
+    match data {
+        CalibrationData::Witnesses(witnesses) => witnesses
+            .iter()
+            .map(|w| {
+                w.inputs
+                    .iter()
+                    .map(|input| input.iter().map(|x| dequantize(*x, input_scale, 0.0)).collect())
+                    .collect()
+            })
+            .collect(),
+        CalibrationData::Floats(samples) => samples
+            .iter()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .map(|input| input.iter().map(|x| *x as f64).collect())
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// Rounds `x` to the nearest multiple of `2^-scale`.
+fn round_to_grid(x: f64, scale: Scale) -> f64 {
+    let mult = scale_to_multiplier(scale);
+    (x * mult).round() / mult
+}
+
+/// Runs the model over every sample, rounded to the grid of `grid_scale` and quantized at the base `input_scale`, and
+/// returns the dequantized outputs.
+fn run_samples(
+    model: &Model,
+    base: &RunArgs,
+    samples: &[Vec<Vec<f64>>],
+    grid_scale: Scale,
+) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let graph = &model.graph;
+    let output_scales: Vec<Scale> = graph
+        .outputs
+        .iter()
+        .map(|o| graph.outlet_scale(o).ok_or("model output does not exist"))
+        .collect::<Result<_, _>>()?;
+    let mut outputs = vec![];
+    for sample in samples {
+        let inputs = graph
+            .inputs
+            .iter()
+            .zip(sample)
+            .map(|(idx, values)| {
+                let felts = values
+                    .iter()
+                    .map(|x| quantize_float(&round_to_grid(*x, grid_scale), 0.0, base.input_scale).map(i128_to_felt))
+                    .collect::<Result<Vec<Fp>, _>>()?;
+                let dims = graph.outlet_dims(&(*idx, 0)).unwrap_or_else(|| vec![felts.len()]);
+                Ok(Tensor::new(Some(&felts), &dims)?)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let result = forward(model, &inputs, base)?;
+        for (output, scale) in result.outputs.iter().zip(&output_scales) {
+            outputs.push(output.iter().map(|x| dequantize(*x, *scale, 0.0)).collect());
+        }
+    }
+    Ok(outputs)
+}
+
+/// Returns a copy of the model whose params are rounded to the grid of `grid_scale`, then requantized at their own scale.
+fn params_on_grid(model: &Model, param_scale: Scale, grid_scale: Scale) -> Result<Model, Box<dyn Error>> {
+    let mut model = model.clone();
+    round_graph_params(&mut model.graph, param_scale, grid_scale)?;
+    Ok(model)
+}
+
+fn round_graph_params(graph: &mut ParsedNodes, param_scale: Scale, grid_scale: Scale) -> Result<(), Box<dyn Error>> {
+    for node in graph.nodes.values_mut() {
+        match node {
+            NodeType::Node(n) => {
+                let out_scale = n.out_scale;
+                round_op_params(&mut n.opkind, out_scale, param_scale, grid_scale)?
+            }
+            NodeType::SubGraph { model, .. } => round_graph_params(&mut model.graph, param_scale, grid_scale)?,
+        }
+    }
+    Ok(())
+}
+
+fn round_op_params(
+    op: &mut SupportedOp,
+    out_scale: Scale,
+    param_scale: Scale,
+    grid_scale: Scale,
+) -> Result<(), Box<dyn Error>> {
+    let requantize = |x: f64, scale: Scale| -> Result<Fp, Box<dyn Error>> {
+        Ok(i128_to_felt(quantize_float(&round_to_grid(x, grid_scale), 0.0, scale)?))
+    };
+    match op {
+        SupportedOp::Constant(c) => {
+            let stored = c.quantized_values.scale();
+            let mut values = c.raw_values.map_result(|x| requantize(x as f64, stored.unwrap_or(out_scale)))?;
+            if let Some(scale) = stored {
+                values.set_scale(scale);
+            }
+            c.quantized_values = values;
+        }
+        SupportedOp::Linear(PolyOp::Conv { kernel, .. }) | SupportedOp::Linear(PolyOp::DeConv { kernel, .. }) => {
+            let scale = kernel.scale().unwrap_or(param_scale);
+            *kernel = kernel.map_result(|x| requantize(dequantize(x, scale, 0.0), scale))?;
+        }
+        SupportedOp::Rescaled(op) => round_op_params(&mut op.inner, out_scale, param_scale, grid_scale)?,
+        SupportedOp::RebaseScale(op) => round_op_params(&mut op.inner, out_scale, param_scale, grid_scale)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The worst difference between `outputs` and `reference`, relative to the largest magnitude of the reference outputs.
+fn output_error(reference: &[Vec<f64>], outputs: &[Vec<f64>]) -> f64 {
+    let range = reference.iter().map(|r| max_abs(r)).fold(0.0, f64::max);
+    let worst = reference
+        .iter()
+        .zip(outputs)
+        .flat_map(|(r, o)| r.iter().zip(o).map(|(a, b)| (a - b).abs()))
+        .fold(0.0, f64::max);
+    if range == 0.0 {
+        worst
+    } else {
+        worst / range
+    }
+}
+
+fn collect_params(graph: &ParsedNodes, param_scale: Scale, params: &mut Vec<f64>) {
+    for node in graph.nodes.values() {
+        match node {
+            NodeType::Node(n) => collect_op_params(&n.opkind, param_scale, params),
+            NodeType::SubGraph { model, .. } => collect_params(&model.graph, param_scale, params),
+        }
+    }
+}
+
+fn collect_op_params(op: &SupportedOp, param_scale: Scale, params: &mut Vec<f64>) {
+    match op {
+        SupportedOp::Constant(c) => params.extend(c.raw_values.iter().map(|x| *x as f64)),
+        SupportedOp::Linear(PolyOp::Conv { kernel, .. })
+        | SupportedOp::Linear(PolyOp::DeConv { kernel, .. }) => {
+            let scale = kernel.scale().unwrap_or(param_scale);
+            params.extend(kernel.iter().map(|x| dequantize(*x, scale, 0.0)));
+        }
+        SupportedOp::Rescaled(op) => collect_op_params(&op.inner, param_scale, params),
+        SupportedOp::RebaseScale(op) => collect_op_params(&op.inner, param_scale, params),
+        _ => {}
+    }
+}
+
+fn max_abs(values: &[f64]) -> f64 {
+    values.iter().fold(0.0, |acc, x| acc.max(x.abs()))
+}
+
+/// The worst error of quantizing `values` at `scale`, relative to their largest magnitude.
+fn relative_error(values: &[f64], scale: Scale) -> f64 {
+    let range = max_abs(values);
+    if range == 0.0 {
+        return 0.0;
+    }
+    let mult = scale_to_multiplier(scale);
+    values
+        .iter()
+        .map(|x| match quantize_float(x, 0.0, scale) {
+            Ok(q) => (x - q as f64 / mult).abs() / range,
+            Err(_) => f64::INFINITY,
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::MAX_LOGROWS;
+    use crate::testutils::{constant, input, model, node};
+
+    fn base() -> RunArgs {
+        RunArgs {
+            input_scale: 12,
+            param_scale: 12,
+            ..RunArgs::default()
+        }
+    }
+
+    /// `input <op> weight`, with the weight holding `raw`.
+    fn binary(op: PolyOp<Fp>, raw: &[f32], out_scale: Scale) -> Model {
+        let dims = [raw.len()];
+        model(
+            vec![
+                input(0, &dims, 12),
+                constant(1, raw, &dims, 12),
+                node(2, SupportedOp::Linear(op), &[(0, 0), (1, 0)], &dims, out_scale),
+            ],
+            &[0],
+            &[(2, 0)],
+        )
+    }
+
+    fn floats(samples: &[&[f32]]) -> CalibrationData {
+        CalibrationData::Floats(samples.iter().map(|s| vec![s.to_vec()]).collect())
+    }
+
+    #[test]
+    fn scales_are_searched_independently() {
+        let m = binary(PolyOp::Mult, &[0.3, -0.7], 24);
+        let result = calibrate(&m, &base(), &floats(&[&[1.0, 2.0]]), &CalibrationTarget::default()).unwrap();
+        assert_eq!(result.candidates.len(), 12 * 12 * 3);
+        // integer inputs are exact at any scale but the params need a scale of 6
+        let best = result.best.unwrap();
+        assert_eq!(best.run_args.input_scale, 1);
+        assert_eq!(best.run_args.param_scale, 6);
+        assert_eq!(best.input_error, 0.0);
+        assert!(best.param_error <= 0.01);
+        assert!(best.output_error.unwrap() <= 0.01);
+        assert_eq!(best.run_args.logrows, 10);
+        assert_eq!(best.run_args.lookup_range, (-256, 256));
+    }
+
+    #[test]
+    fn output_error_is_part_of_the_target() {
+        // 1 - 0.99 is only known to within the rounding of the weight, which is large relative to the output
+        let m = binary(PolyOp::Sub, &[0.99], 12);
+        let target = CalibrationTarget {
+            scales: vec![7, 12],
+            num_inner_cols: vec![2],
+            ..CalibrationTarget::default()
+        };
+        let result = calibrate(&m, &base(), &floats(&[&[1.0]]), &target).unwrap();
+        let coarse = &result.candidates[0];
+        assert_eq!((coarse.run_args.input_scale, coarse.run_args.param_scale), (7, 7));
+        assert!(coarse.param_error <= 0.01);
+        assert!(coarse.output_error.unwrap() > 0.1);
+        assert!(!coarse.meets_target);
+
+        let best = result.best.unwrap();
+        assert_eq!(best.run_args.param_scale, 12);
+        assert_eq!(best.output_error, Some(0.0));
+    }
+
+    #[test]
+    fn unsupported_models_skip_the_output_error() {
+        let mut m = binary(PolyOp::Mult, &[0.5], 24);
+        if let Some(NodeType::Node(n)) = m.graph.nodes.get_mut(&2) {
+            n.opkind = SupportedOp::Unknown(Default::default());
+        }
+        let result = calibrate(&m, &base(), &floats(&[&[1.0]]), &CalibrationTarget::default()).unwrap();
+        assert!(result.candidates.iter().all(|c| c.output_error.is_none()));
+        assert!(result.best.is_some());
+    }
+
+    #[test]
+    fn witness_lookup_range_is_rescaled() {
+        // one sample of 2.0 at the base input scale, whose lookup inputs spanned [-8192, 4096] at the base scales
+        let witness = GraphWitness {
+            inputs: vec![vec![i128_to_felt(2 << 12)]],
+            max_lookup_inputs: 1 << 12,
+            min_lookup_inputs: -(2 << 12),
+            ..Default::default()
+        };
+        let m = binary(PolyOp::Mult, &[0.5], 24);
+        let target = CalibrationTarget {
+            scales: vec![11, 12],
+            num_inner_cols: vec![2],
+            ..CalibrationTarget::default()
+        };
+        let result = calibrate(&m, &base(), &CalibrationData::Witnesses(vec![witness]), &target).unwrap();
+        let ranges: Vec<_> = result.candidates.iter().map(|c| c.run_args.lookup_range).collect();
+        assert_eq!(ranges, vec![(-2048, 2048), (-4096, 4096), (-4096, 4096), (-8192, 8192)]);
+        // the lookup table, not the model, sets logrows
+        let best = result.best.unwrap();
+        assert_eq!((best.run_args.input_scale, best.run_args.param_scale), (11, 11));
+        assert_eq!(best.run_args.logrows, 13);
+    }
+
+    #[test]
+    fn oversized_lookup_tables_are_infeasible() {
+        let m = binary(PolyOp::Mult, &[0.5], 24);
+        let target = CalibrationTarget {
+            scales: vec![1, 12],
+            num_inner_cols: vec![2],
+            ..CalibrationTarget::default()
+        };
+        // at scales 12 and 12 the lookup range is 1000 * 2^24, beyond 2^MAX_LOGROWS rows
+        let result = calibrate(&m, &base(), &floats(&[&[1000.0]]), &target).unwrap();
+        let scales: Vec<_> = result
+            .candidates
+            .iter()
+            .map(|c| (c.run_args.input_scale, c.run_args.param_scale))
+            .collect();
+        assert_eq!(scales, vec![(1, 1), (1, 12), (12, 1)]);
+        assert!(result.candidates.iter().all(|c| c.run_args.logrows <= MAX_LOGROWS));
+    }
+
+    #[test]
+    fn no_samples() {
+        let m = binary(PolyOp::Mult, &[0.5], 24);
+        assert!(calibrate(&m, &base(), &floats(&[]), &CalibrationTarget::default()).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::graphsettings::ParseMode;
+use crate::schema::SchemaTarget;
+
+/// Command line interface of the verifier tooling.
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// The command to run, verifies the bundled proof if omitted
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+/// The available subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Prints statistics over a serialized model
+    Stats {
+        /// The path to the serialized model
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the circuit settings, to compare the model's lookups against
+        #[arg(short = 'S', long)]
+        settings: Option<PathBuf>,
+    },
+    /// Removes identities and dead nodes and folds constants in a serialized model
+    Optimize {
+        /// The path to the serialized model
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the circuit settings, whose run args are used to estimate the rows saved
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+        /// The path to write the optimized model to
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+    },
+    /// Compares two serialized models and reports whether the verifying key would change
+    Diff {
+        /// The path to the old model
+        #[arg(long)]
+        before: PathBuf,
+        /// The path to the new model
+        #[arg(long)]
+        after: PathBuf,
+        /// The path to the old circuit settings
+        #[arg(long, requires = "after_settings")]
+        before_settings: Option<PathBuf>,
+        /// The path to the new circuit settings
+        #[arg(long, requires = "before_settings")]
+        after_settings: Option<PathBuf>,
+    },
+    /// Validates a witness against its circuit settings and, optionally, the proof generated from it
+    ValidateWitness {
+        /// The path to the witness
+        #[arg(short = 'W', long)]
+        witness: PathBuf,
+        /// The path to the circuit settings
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+        /// The path to the proof, to compare the public outputs against
+        #[arg(short = 'P', long)]
+        proof: Option<PathBuf>,
+    },
+    /// Checks raw input data against the hashed public inputs of a proof
+    VerifyInputs {
+        /// The path to the input data, either `{"input_data": [[...]]}` or a list of flattened tensors
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the circuit settings
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+        /// The path to the proof
+        #[arg(short = 'P', long)]
+        proof: PathBuf,
+    },
+    /// Splits the instances of a proof into labeled public inputs, hashes and outputs
+    Instances {
+        /// The path to the proof
+        #[arg(short = 'P', long)]
+        proof: PathBuf,
+        /// The path to the circuit settings
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+    },
+    /// Checks the keys of circuit settings against the current layout, migrating older layouts
+    CheckSettings {
+        /// The path to the circuit settings
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+        /// Whether unknown and missing keys are errors or warnings
+        #[arg(long, value_enum, default_value_t = ParseMode::default())]
+        mode: ParseMode,
+    },
+    /// Prints the JSON schema of a file read by core-ezkl
+    Schema {
+        /// The file to print the schema of
+        #[arg(value_enum)]
+        target: SchemaTarget,
+        /// The path to write the schema to, printed if omitted
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+    /// Runs the consistency checks required by the settings' check mode before accepting a proof
+    CheckPolicy {
+        /// The path to the proof
+        #[arg(short = 'P', long)]
+        proof: PathBuf,
+        /// The path to the circuit settings
+        #[arg(short = 'S', long)]
+        settings: PathBuf,
+        /// The path to the witness the proof was generated from
+        #[arg(short = 'W', long)]
+        witness: Option<PathBuf>,
+        /// The path to the serialized model
+        #[arg(short = 'M', long)]
+        model: Option<PathBuf>,
+        /// The path to the verifying key, whose k is checked against the settings' logrows
+        #[arg(long)]
+        vk: Option<PathBuf>,
+        /// Reject proofs whose settings declare UNSAFE checks
+        #[arg(long)]
+        forbid_unsafe: bool,
+        /// Skip the checks whose witness, model or verifying key wasn't provided instead of rejecting SAFE proofs
+        #[arg(long)]
+        allow_partial: bool,
+    },
+}
@@ -0,0 +1,708 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::einsum::EinsumEquation;
+use crate::graphsettings::LookupOp;
+use crate::hybridop::HybridOp;
+use crate::model::{num_iterations, Model, NodeType, ParsedNodes, SupportedOp};
+use crate::runargs::{RunArgs, Visibility};
+use crate::supportedop::PolyOp;
+use crate::utils::F32;
+
+/// Rows at the bottom of the circuit that are reserved for blinding factors and cannot hold assignments.
+pub const RESERVED_BLINDING_ROWS: usize = 6;
+
+/// The largest `logrows` accepted when sizing a circuit, as BN254's evaluation domains top out at 2^28 rows.
+pub const MAX_LOGROWS: u32 = 28;
+
+/// Why the cost of a circuit could not be estimated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CostError {
+    /// The rows don't fit in `2^MAX_LOGROWS` rows.
+    TooManyRows(usize),
+    /// `logrows` exceeds [MAX_LOGROWS].
+    LogRowsTooLarge(u32),
+}
+
+impl fmt::Display for CostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostError::TooManyRows(rows) => write!(f, "{} rows don't fit in 2^{} rows", rows, MAX_LOGROWS),
+            CostError::LogRowsTooLarge(logrows) => {
+                write!(f, "logrows {} exceeds the maximum of {}", logrows, MAX_LOGROWS)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CostError {}
+
+/// The estimated circuit cost of a single node. The costs of the nodes of a subgraph body are summed over its iterations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeCost {
+    /// The indices of the enclosing subgraphs (outermost first) followed by the node's index.
+    pub path: Vec<usize>,
+    /// The name of the node's op.
+    pub op: String,
+    /// The number of rows the node takes up.
+    pub rows: usize,
+    /// How far the node advances the circuit's linear coordinate of assignments.
+    pub assignments: usize,
+    /// The number of constants the node assigns to fixed columns.
+    pub constants: usize,
+    /// The number of lookups the node performs.
+    pub lookups: usize,
+}
+
+/// The estimated cost of a lookup op, across every node that uses it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LookupCost {
+    /// The lookup op.
+    pub op: LookupOp,
+    /// The number of lookups into the op's table.
+    pub num_lookups: usize,
+    /// The number of rows in the op's table, ie. the size of `lookup_range`.
+    pub table_rows: usize,
+    /// The number of columns the table is split over at the current `logrows`.
+    pub table_columns: usize,
+}
+
+/// A breakdown of the estimated circuit cost of a model, per node and per lookup op.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CostReport {
+    /// The cost of every node, in graph order.
+    pub nodes: Vec<NodeCost>,
+    /// The cost of every lookup op used by the model.
+    pub lookups: Vec<LookupCost>,
+    /// The total number of rows used by the nodes.
+    pub total_rows: usize,
+    /// The total linear coordinate of assignments.
+    pub total_assignments: usize,
+    /// The total number of constants.
+    pub total_const_size: usize,
+    /// The `logrows` the estimate was made for.
+    pub logrows: u32,
+    /// The smallest `logrows` that fits the estimated rows.
+    pub min_logrows: u32,
+}
+
+impl CostReport {
+    /// Returns the node costs, most expensive (by rows) first.
+    pub fn ranked(&self) -> Vec<&NodeCost> {
+        let mut ranked: Vec<&NodeCost> = self.nodes.iter().collect();
+        ranked.sort_by(|a, b| b.rows.cmp(&a.rows).then(b.assignments.cmp(&a.assignments)));
+        ranked
+    }
+
+    /// Returns how many rows must be removed for the circuit to fit in `logrows - 1`, if it doesn't already.
+    pub fn rows_to_drop_logrow(&self) -> Option<usize> {
+        let target = (1usize << self.min_logrows.saturating_sub(1)).saturating_sub(RESERVED_BLINDING_ROWS);
+        self.total_rows.checked_sub(target).filter(|r| *r > 0)
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "total rows: {}, assignments: {}, constants: {}, logrows: {} (min {})",
+            self.total_rows, self.total_assignments, self.total_const_size, self.logrows, self.min_logrows
+        )?;
+        if let Some(rows) = self.rows_to_drop_logrow() {
+            writeln!(
+                f,
+                "remove {} rows to fit in logrows {}",
+                rows,
+                self.min_logrows.saturating_sub(1)
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<16} {:<40} {:>10} {:>12} {:>10} {:>10} {:>7}",
+            "node", "op", "rows", "assignments", "constants", "lookups", "share"
+        )?;
+        for node in self.ranked() {
+            let share = if self.total_rows > 0 {
+                100.0 * node.rows as f64 / self.total_rows as f64
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "{:<16} {:<40} {:>10} {:>12} {:>10} {:>10} {:>6.2}%",
+                format!("{:?}", node.path),
+                node.op,
+                node.rows,
+                node.assignments,
+                node.constants,
+                node.lookups,
+                share
+            )?;
+        }
+        for lookup in &self.lookups {
+            writeln!(
+                f,
+                "lookup {:<32} lookups: {:>10} table rows: {:>10} table columns: {:>4}",
+                format!("{:?}", lookup.op),
+                lookup.num_lookups,
+                lookup.table_rows,
+                lookup.table_columns
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Estimates the rows, assignments and constants contributed by every node of `model`, and the size of every lookup table,
+/// for the `num_inner_cols`, `logrows`, `lookup_range` and `param_visibility` in `run_args`.
+pub fn estimate_costs(model: &Model, run_args: &RunArgs) -> Result<CostReport, CostError> {
+    if run_args.logrows > MAX_LOGROWS {
+        return Err(CostError::LogRowsTooLarge(run_args.logrows));
+    }
+    let mut report = CostReport {
+        logrows: run_args.logrows,
+        ..Default::default()
+    };
+    let mut lookups: BTreeMap<LookupOp, usize> = BTreeMap::new();
+    estimate_graph(&model.graph, run_args, &[], &mut report.nodes, &mut lookups);
+
+    report.total_rows = report.nodes.iter().map(|n| n.rows).sum();
+    report.total_assignments = report.nodes.iter().map(|n| n.assignments).sum();
+    report.total_const_size = report.nodes.iter().map(|n| n.constants).sum();
+    report.min_logrows = min_logrows(report.total_rows)?;
+
+    let table_rows = (run_args.lookup_range.1 - run_args.lookup_range.0 + 1).max(0) as usize;
+    let usable_rows = (1usize << run_args.logrows).saturating_sub(RESERVED_BLINDING_ROWS).max(1);
+    report.lookups = lookups
+        .into_iter()
+        .map(|(op, num_lookups)| LookupCost {
+            op,
+            num_lookups,
+            table_rows,
+            table_columns: table_rows.div_ceil(usable_rows),
+        })
+        .collect();
+    Ok(report)
+}
+
+/// Returns the smallest `logrows` whose usable rows fit `rows`, up to [MAX_LOGROWS].
+pub fn min_logrows(rows: usize) -> Result<u32, CostError> {
+    (1..=MAX_LOGROWS)
+        .find(|k| (1usize << k).saturating_sub(RESERVED_BLINDING_ROWS) >= rows)
+        .ok_or(CostError::TooManyRows(rows))
+}
+
+fn estimate_graph(
+    graph: &ParsedNodes,
+    run_args: &RunArgs,
+    prefix: &[usize],
+    nodes: &mut Vec<NodeCost>,
+    lookups: &mut BTreeMap<LookupOp, usize>,
+) {
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+        match node {
+            NodeType::Node(n) => {
+                let input_dims: Vec<Vec<usize>> = n
+                    .inputs
+                    .iter()
+                    .map(|o| graph.outlet_dims(o).unwrap_or_default())
+                    .collect();
+                let mut tally = Tally::default();
+                estimate_op(&n.opkind, &input_dims, &n.out_dims, run_args, &mut tally, lookups);
+                let packed = tally.assignments - tally.dot_assignments;
+                nodes.push(NodeCost {
+                    path,
+                    op: n.opkind.as_string(),
+                    rows: tally.dot_rows + packed.div_ceil(run_args.num_inner_cols.max(1)),
+                    assignments: tally.assignments,
+                    constants: tally.constants,
+                    lookups: tally.lookups,
+                });
+            }
+            NodeType::SubGraph {
+                model,
+                inputs,
+                input_mappings,
+                ..
+            } => {
+                // the body is laid out once per iteration
+                let input_dims: Vec<Vec<usize>> = inputs
+                    .iter()
+                    .map(|o| graph.outlet_dims(o).unwrap_or_default())
+                    .collect();
+                let num_iter = num_iterations(input_mappings, &input_dims).unwrap_or(1);
+                let start = nodes.len();
+                let mut body_lookups = BTreeMap::new();
+                estimate_graph(&model.graph, run_args, &path, nodes, &mut body_lookups);
+                for cost in &mut nodes[start..] {
+                    cost.rows *= num_iter;
+                    cost.assignments *= num_iter;
+                    cost.constants *= num_iter;
+                    cost.lookups *= num_iter;
+                }
+                for (op, n) in body_lookups {
+                    *lookups.entry(op).or_insert(0) += n * num_iter;
+                }
+            }
+        }
+    }
+}
+
+fn len(dims: &[usize]) -> usize {
+    dims.iter().product()
+}
+
+fn record_lookup(op: LookupOp, n: usize, cost: &mut Tally, lookups: &mut BTreeMap<LookupOp, usize>) {
+    cost.assignments += n;
+    cost.lookups += n;
+    *lookups.entry(op).or_insert(0) += n;
+}
+
+/// The running cost of a node. Dot products each start on a fresh row so their rows are counted as they are laid out;
+/// every other assignment is packed `num_inner_cols` to a row once the node is done.
+#[derive(Debug, Default)]
+struct Tally {
+    assignments: usize,
+    dot_assignments: usize,
+    dot_rows: usize,
+    constants: usize,
+    lookups: usize,
+}
+
+/// Adds the cost of `op` to `cost`.
+fn estimate_op(
+    op: &SupportedOp,
+    input_dims: &[Vec<usize>],
+    out_dims: &[usize],
+    run_args: &RunArgs,
+    cost: &mut Tally,
+    lookups: &mut BTreeMap<LookupOp, usize>,
+) {
+    let out_len = len(out_dims);
+    let in_len = input_dims.first().map(|d| len(d)).unwrap_or(0);
+    let fixed_params = run_args.param_visibility == Visibility::Fixed;
+    let cols = run_args.num_inner_cols.max(1);
+    match op {
+        SupportedOp::Input(_) => cost.assignments += out_len,
+        SupportedOp::Constant(_) => {
+            if fixed_params {
+                cost.constants += out_len
+            } else {
+                cost.assignments += out_len
+            }
+        }
+        SupportedOp::Unknown(_) => {}
+        SupportedOp::Rescaled(rescaled) => {
+            // every rescaled input is multiplied by a constant
+            for (i, _) in &rescaled.scale {
+                cost.assignments += input_dims.get(*i).map(|d| len(d)).unwrap_or(0);
+            }
+            estimate_op(&rescaled.inner, input_dims, out_dims, run_args, cost, lookups);
+        }
+        SupportedOp::RebaseScale(rebase) => {
+            estimate_op(&rebase.inner, input_dims, out_dims, run_args, cost, lookups);
+            record_lookup(
+                LookupOp::Div {
+                    denom: (rebase.multiplier as f32).into(),
+                },
+                out_len,
+                cost,
+                lookups,
+            );
+        }
+        SupportedOp::Nonlinear(op) => record_lookup(op.clone(), out_len, cost, lookups),
+        SupportedOp::Linear(op) => estimate_poly(op, input_dims, out_len, in_len, fixed_params, cols, cost),
+        SupportedOp::Hybrid(op) => estimate_hybrid(op, input_dims, out_len, in_len, cols, cost, lookups),
+    }
+}
+
+fn add_dot_products(num: usize, dot_len: usize, cols: usize, cost: &mut Tally) {
+    cost.assignments += num * dot_len;
+    cost.dot_assignments += num * dot_len;
+    cost.dot_rows += num * dot_len.div_ceil(cols);
+}
+
+fn estimate_poly(
+    op: &PolyOp<Fp>,
+    input_dims: &[Vec<usize>],
+    out_len: usize,
+    in_len: usize,
+    fixed_params: bool,
+    cols: usize,
+    cost: &mut Tally,
+) {
+    match op {
+        PolyOp::Einsum { equation } => {
+            let shapes: Vec<&[usize]> = input_dims.iter().map(|d| d.as_slice()).collect();
+            match EinsumEquation::parse(equation).and_then(|eq| eq.cost(&shapes, cols)) {
+                Ok(einsum) => {
+                    cost.assignments += einsum.assignments;
+                    cost.dot_assignments += einsum.assignments;
+                    cost.dot_rows += einsum.rows;
+                }
+                Err(_) => cost.assignments += out_len,
+            }
+        }
+        PolyOp::Conv { kernel, bias, .. } | PolyOp::DeConv { kernel, bias, .. } => {
+            let dims = kernel.dims();
+            let dot_len = if let PolyOp::DeConv { .. } = op {
+                dims.first().copied().unwrap_or(1) * len(dims.get(2..).unwrap_or(&[]))
+            } else {
+                len(dims.get(1..).unwrap_or(&[]))
+            };
+            add_dot_products(out_len, dot_len, cols, cost);
+            let params = kernel.len() + bias.as_ref().map(|b| b.len()).unwrap_or(0);
+            if bias.is_some() {
+                cost.assignments += out_len;
+            }
+            if fixed_params {
+                cost.constants += params;
+            } else {
+                cost.assignments += params;
+            }
+        }
+        PolyOp::Add
+        | PolyOp::Sub
+        | PolyOp::Mult
+        | PolyOp::Neg
+        | PolyOp::Not
+        | PolyOp::And
+        | PolyOp::Or
+        | PolyOp::Xor => cost.assignments += out_len * input_dims.len().saturating_sub(1).max(1),
+        PolyOp::Iff => cost.assignments += 2 * out_len,
+        PolyOp::Pow(exp) => cost.assignments += out_len * (*exp as usize).saturating_sub(1).max(1),
+        PolyOp::Sum { .. } | PolyOp::Prod { .. } | PolyOp::GlobalSumPool | PolyOp::Pack(_, _) => {
+            cost.assignments += in_len
+        }
+        // these only rearrange already assigned cells
+        PolyOp::MultiBroadcastTo { .. }
+        | PolyOp::Downsample { .. }
+        | PolyOp::Identity
+        | PolyOp::Reshape(_)
+        | PolyOp::MoveAxis { .. }
+        | PolyOp::Flatten(_)
+        | PolyOp::Pad(_)
+        | PolyOp::Concat { .. }
+        | PolyOp::Slice { .. }
+        | PolyOp::Resize { .. } => {}
+    }
+}
+
+fn estimate_hybrid(
+    op: &HybridOp,
+    input_dims: &[Vec<usize>],
+    out_len: usize,
+    in_len: usize,
+    cols: usize,
+    cost: &mut Tally,
+    lookups: &mut BTreeMap<LookupOp, usize>,
+) {
+    match op {
+        HybridOp::Div {
+            denom,
+            use_range_check_for_int,
+        } => {
+            if *use_range_check_for_int {
+                cost.assignments += 3 * out_len;
+            } else {
+                record_lookup(LookupOp::Div { denom: *denom }, out_len, cost, lookups);
+            }
+        }
+        HybridOp::Recip {
+            use_range_check_for_int,
+            ..
+        } => {
+            if *use_range_check_for_int {
+                cost.assignments += 3 * out_len;
+            } else {
+                for lookup in op.required_lookups() {
+                    record_lookup(lookup, out_len, cost, lookups);
+                }
+            }
+        }
+        HybridOp::ReduceMax { .. } | HybridOp::ReduceMin { .. } => {
+            cost.assignments += 2 * in_len;
+            record_lookup(LookupOp::ReLU, in_len, cost, lookups);
+        }
+        HybridOp::ReduceArgMax { .. } | HybridOp::ReduceArgMin { .. } => {
+            cost.assignments += 3 * in_len;
+            record_lookup(LookupOp::ReLU, in_len, cost, lookups);
+        }
+        HybridOp::SumPool { kernel_shape, .. } => {
+            add_dot_products(out_len, kernel_shape.0 * kernel_shape.1, cols, cost)
+        }
+        HybridOp::MaxPool2d { pool_dims, .. } => {
+            let window = pool_dims.0 * pool_dims.1;
+            cost.assignments += out_len * window;
+            record_lookup(LookupOp::ReLU, out_len * window, cost, lookups);
+        }
+        HybridOp::Softmax { .. } => {
+            for lookup in op.required_lookups() {
+                record_lookup(lookup, in_len, cost, lookups);
+            }
+            cost.assignments += 2 * in_len;
+        }
+        HybridOp::RangeCheck(tol) => {
+            cost.assignments += out_len;
+            if tol.val > 0.0 {
+                // the difference, its product with the reciprocal and the negated product
+                cost.assignments += 3 * out_len;
+                for lookup in op.required_lookups() {
+                    let n = if matches!(lookup, LookupOp::GreaterThan { .. }) { 2 * out_len } else { out_len };
+                    record_lookup(lookup, n, cost, lookups);
+                }
+            }
+        }
+        HybridOp::Greater | HybridOp::Less => {
+            cost.assignments += out_len;
+            let op = if matches!(op, HybridOp::Greater) {
+                LookupOp::GreaterThan { a: F32(0.0) }
+            } else {
+                LookupOp::LessThan { a: F32(0.0) }
+            };
+            record_lookup(op, out_len, cost, lookups);
+        }
+        HybridOp::GreaterEqual | HybridOp::LessEqual => {
+            cost.assignments += out_len;
+            let op = if matches!(op, HybridOp::GreaterEqual) {
+                LookupOp::GreaterThanEqual { a: F32(0.0) }
+            } else {
+                LookupOp::LessThanEqual { a: F32(0.0) }
+            };
+            record_lookup(op, out_len, cost, lookups);
+        }
+        HybridOp::Equals => {
+            cost.assignments += out_len;
+            record_lookup(LookupOp::KroneckerDelta, out_len, cost, lookups);
+        }
+        HybridOp::Gather { dim, .. }
+        | HybridOp::GatherElements { dim, .. }
+        | HybridOp::ScatterElements { dim, .. } => {
+            // every output element is selected by a one-hot dot product over the indexed axis
+            let axis_len = input_dims.first().and_then(|d| d.get(*dim)).copied().unwrap_or(1);
+            add_dot_products(out_len, axis_len, cols, cost);
+            cost.assignments += out_len * axis_len;
+        }
+        HybridOp::TopK { .. } => {
+            cost.assignments += 3 * in_len;
+            record_lookup(LookupOp::ReLU, in_len, cost, lookups);
+        }
+        HybridOp::OneHot { .. } => {
+            cost.assignments += out_len;
+            record_lookup(LookupOp::KroneckerDelta, out_len, cost, lookups);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::testutils::{felts, input, model, node, subgraph};
+
+    fn run_args() -> RunArgs {
+        RunArgs {
+            logrows: 10,
+            lookup_range: (-512, 511),
+            ..RunArgs::default()
+        }
+    }
+
+    /// Sums the rows of its input and applies a ReLU.
+    fn body() -> Model {
+        model(
+            vec![
+                input(0, &[1, 2], 0),
+                node(1, SupportedOp::Linear(PolyOp::Sum { axes: vec![1] }), &[(0, 0)], &[1, 1], 0),
+                node(2, SupportedOp::Nonlinear(LookupOp::ReLU), &[(1, 0)], &[1, 1], 0),
+            ],
+            &[0],
+            &[(2, 0)],
+        )
+    }
+
+    #[test]
+    fn einsum_cost() {
+        let m = model(
+            vec![
+                input(0, &[2, 3], 0),
+                input(1, &[3, 4], 0),
+                node(
+                    2,
+                    SupportedOp::Linear(PolyOp::Einsum {
+                        equation: "ij,jk->ik".to_string(),
+                    }),
+                    &[(0, 0), (1, 0)],
+                    &[2, 4],
+                    0,
+                ),
+            ],
+            &[0, 1],
+            &[(2, 0)],
+        );
+        let report = estimate_costs(&m, &run_args()).unwrap();
+        // 8 dot products of length 3, each starting on a fresh row of 2 columns
+        assert_eq!(report.nodes[2].assignments, 24);
+        assert_eq!(report.nodes[2].rows, 16);
+        // the inputs are packed 2 to a row
+        assert_eq!(report.nodes[0].rows, 3);
+        assert_eq!(report.total_rows, 3 + 6 + 16);
+    }
+
+    #[test]
+    fn subgraph_costed_per_iteration() {
+        let single = estimate_costs(&body(), &run_args()).unwrap();
+        let scan = model(
+            vec![
+                input(0, &[4, 2], 0),
+                subgraph(
+                    1,
+                    body(),
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![vec![OutputMapping::Stacked {
+                        outlet: 0,
+                        axis: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![4, 1]],
+                    vec![0],
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let report = estimate_costs(&scan, &run_args()).unwrap();
+
+        assert_eq!(report.nodes.len(), 1 + single.nodes.len());
+        for (cost, once) in report.nodes[1..].iter().zip(&single.nodes) {
+            assert_eq!(cost.path, [vec![1], once.path.clone()].concat());
+            assert_eq!(cost.rows, 4 * once.rows);
+            assert_eq!(cost.assignments, 4 * once.assignments);
+            assert_eq!(cost.lookups, 4 * once.lookups);
+        }
+        assert_eq!(report.lookups.len(), 1);
+        assert_eq!(report.lookups[0].num_lookups, 4);
+        assert_eq!(report.total_rows, report.nodes[0].rows + 4 * single.total_rows);
+    }
+
+    #[test]
+    fn lookup_table_columns() {
+        let run_args = RunArgs {
+            logrows: 8,
+            ..run_args()
+        };
+        let report = estimate_costs(&body(), &run_args).unwrap();
+        assert_eq!(report.lookups[0].table_rows, 1024);
+        // 250 usable rows per column
+        assert_eq!(report.lookups[0].table_columns, 5);
+
+        // an empty range has no table to lay out
+        let run_args = RunArgs {
+            lookup_range: (1, 0),
+            ..run_args
+        };
+        let report = estimate_costs(&body(), &run_args).unwrap();
+        assert_eq!((report.lookups[0].table_rows, report.lookups[0].table_columns), (0, 0));
+    }
+
+    #[test]
+    fn fixed_params_are_constants() {
+        let conv = model(
+            vec![
+                input(0, &[1, 1, 3, 3], 0),
+                node(
+                    1,
+                    SupportedOp::Linear(PolyOp::Conv {
+                        kernel: felts(&[1; 8], &[2, 1, 2, 2]),
+                        bias: Some(felts(&[1, 1], &[2])),
+                        padding: [(0, 0); 2],
+                        stride: (1, 1),
+                    }),
+                    &[(0, 0)],
+                    &[1, 2, 2, 2],
+                    0,
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let private = estimate_costs(&conv, &run_args()).unwrap();
+        // 8 dot products of length 4, the bias additions and the 10 params
+        assert_eq!(private.nodes[1].assignments, 32 + 8 + 10);
+        assert_eq!(private.nodes[1].constants, 0);
+        assert_eq!(private.nodes[1].rows, 8 * 2 + 9);
+
+        let run_args = RunArgs {
+            param_visibility: Visibility::Fixed,
+            ..run_args()
+        };
+        let fixed = estimate_costs(&conv, &run_args).unwrap();
+        assert_eq!(fixed.nodes[1].assignments, 32 + 8);
+        assert_eq!(fixed.nodes[1].constants, 10);
+        assert_eq!(fixed.nodes[1].rows, 8 * 2 + 4);
+        assert_eq!(fixed.total_const_size, 10);
+    }
+
+    #[test]
+    fn division_lookups() {
+        let div = |use_range_check_for_int| {
+            let op = SupportedOp::Hybrid(HybridOp::Div {
+                denom: F32(2.0),
+                use_range_check_for_int,
+            });
+            model(vec![input(0, &[4], 0), node(1, op, &[(0, 0)], &[4], 0)], &[0], &[(1, 0)])
+        };
+        let report = estimate_costs(&div(false), &run_args()).unwrap();
+        assert_eq!(report.nodes[1].lookups, 4);
+        assert_eq!(report.lookups[0].op, LookupOp::Div { denom: F32(2.0) });
+
+        // a range checked division uses no lookup table
+        let report = estimate_costs(&div(true), &run_args()).unwrap();
+        assert_eq!((report.nodes[1].assignments, report.nodes[1].lookups), (12, 0));
+        assert!(report.lookups.is_empty());
+    }
+
+    #[test]
+    fn rows_to_drop_a_logrow() {
+        let report = |total_rows| {
+            let min_logrows = min_logrows(total_rows).unwrap();
+            CostReport {
+                total_rows,
+                min_logrows,
+                ..Default::default()
+            }
+        };
+        // 1019 rows need logrows 11, of which 2^10 - 6 rows fit in 10
+        assert_eq!(report(1019).rows_to_drop_logrow(), Some(1));
+        assert_eq!(report(1018).rows_to_drop_logrow(), Some(512));
+        assert_eq!(report(0).rows_to_drop_logrow(), None);
+    }
+
+    #[test]
+    fn min_logrows_leaves_blinding_rows() {
+        assert_eq!(min_logrows(0), Ok(1));
+        assert_eq!(min_logrows(676), Ok(10));
+        assert_eq!(min_logrows(1018), Ok(10));
+        assert_eq!(min_logrows(1019), Ok(11));
+        assert_eq!(min_logrows((1 << 28) - 6), Ok(28));
+        assert_eq!(min_logrows((1 << 28) - 5), Err(CostError::TooManyRows((1 << 28) - 5)));
+    }
+
+    #[test]
+    fn logrows_are_capped() {
+        let max_rows = (1usize << MAX_LOGROWS) - RESERVED_BLINDING_ROWS;
+        assert_eq!(min_logrows(max_rows), Ok(MAX_LOGROWS));
+        assert_eq!(min_logrows(max_rows + 1), Err(CostError::TooManyRows(max_rows + 1)));
+        assert_eq!(min_logrows(usize::MAX), Err(CostError::TooManyRows(usize::MAX)));
+
+        for logrows in [MAX_LOGROWS + 1, 32, 64, u32::MAX] {
+            let run_args = RunArgs { logrows, ..run_args() };
+            assert_eq!(estimate_costs(&body(), &run_args), Err(CostError::LogRowsTooLarge(logrows)));
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::fieldutils::dequantize;
+use crate::graphsettings::GraphSettings;
+use crate::hybridop::HybridOp;
+use crate::model::{Model, NodeType, Outlet, SupportedOp, Visibility};
+use crate::supportedop::PolyOp;
+use crate::tensor::Tensor;
+use crate::utils::Scale;
+
+/// Settings fields that don't take part in the circuit.
+const NON_CIRCUIT_SETTINGS: [&str; 3] = ["version", "timestamp", "check_mode"];
+
+/// A difference between the two versions of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeChange {
+    /// The node only exists in the new model.
+    Added(String),
+    /// The node only exists in the old model.
+    Removed(String),
+    /// The node runs a different op.
+    OpChanged {
+        /// the old op
+        before: String,
+        /// the new op
+        after: String,
+    },
+    /// The op is the same but its parameters (other than constant values) differ.
+    ParamsChanged {
+        /// the old parameters, as JSON
+        before: String,
+        /// the new parameters, as JSON
+        after: String,
+    },
+    /// The constant values held by the node (constants, kernels, biases) differ.
+    ConstantsChanged {
+        /// The largest absolute difference between dequantized values, `None` if the number of values differs.
+        max_delta: Option<f64>,
+    },
+    /// The constant indices of a gather or scatter differ. These select the cells the op reads or writes, so they are
+    /// part of the circuit whatever the params visibility.
+    IndicesChanged {
+        /// the old indices
+        before: Option<Tensor<usize>>,
+        /// the new indices
+        after: Option<Tensor<usize>>,
+    },
+    /// The node reads from different outlets.
+    InputsChanged {
+        /// the old inputs
+        before: Vec<Outlet>,
+        /// the new inputs
+        after: Vec<Outlet>,
+    },
+    /// The output dims differ.
+    ShapeChanged {
+        /// the old out dims
+        before: Vec<Vec<usize>>,
+        /// the new out dims
+        after: Vec<Vec<usize>>,
+    },
+    /// The output scales differ.
+    ScaleChanged {
+        /// the old out scales
+        before: Vec<Scale>,
+        /// the new out scales
+        after: Vec<Scale>,
+    },
+}
+
+/// The changes at a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeDiff {
+    /// The indices of the enclosing subgraphs (outermost first) followed by the node's index.
+    pub path: Vec<usize>,
+    /// The changes found.
+    pub changes: Vec<NodeChange>,
+}
+
+/// A changed settings field, eg. `run_args.logrows`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingsChange {
+    /// The dotted path of the field.
+    pub field: String,
+    /// the old value, as JSON
+    pub before: String,
+    /// the new value, as JSON
+    pub after: String,
+}
+
+/// The differences between two models and their settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelDiff {
+    /// Changed nodes, in graph order.
+    pub nodes: Vec<NodeDiff>,
+    /// Changes to the graph inputs, outputs, visibility or subgraph mappings.
+    pub graph: Vec<String>,
+    /// Changed settings fields.
+    pub settings: Vec<SettingsChange>,
+    /// Why the verifying key would change, empty if it wouldn't.
+    pub vk_changes: Vec<String>,
+}
+
+impl ModelDiff {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.graph.is_empty() && self.settings.is_empty()
+    }
+
+    /// Returns true if the change would alter the verifying key.
+    pub fn alters_vk(&self) -> bool {
+        !self.vk_changes.is_empty()
+    }
+}
+
+impl fmt::Display for ModelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.graph {
+            writeln!(f, "graph: {}", change)?;
+        }
+        for node in &self.nodes {
+            for change in &node.changes {
+                writeln!(f, "node {:?}: {:?}", node.path, change)?;
+            }
+        }
+        for change in &self.settings {
+            writeln!(f, "settings {}: {} -> {}", change.field, change.before, change.after)?;
+        }
+        if self.alters_vk() {
+            writeln!(f, "the verifying key changes:")?;
+            for reason in &self.vk_changes {
+                writeln!(f, "  {}", reason)?;
+            }
+            Ok(())
+        } else {
+            writeln!(f, "the verifying key is unchanged")
+        }
+    }
+}
+
+/// Compares two models and, if provided, their settings. Constants only alter the verifying key when params are `Fixed`,
+/// since they are then laid out in fixed columns; every other node or settings change outside of `version`, `timestamp`
+/// and `check_mode` does, including the constant indices of gathers and scatters.
+pub fn diff_models(
+    before: &Model,
+    after: &Model,
+    settings: Option<(&GraphSettings, &GraphSettings)>,
+) -> ModelDiff {
+    let mut diff = ModelDiff::default();
+    diff_graph(before, after, &[], &mut diff);
+
+    if let Some((old, new)) = settings {
+        if let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) {
+            diff_values("", &old, &new, &mut diff.settings);
+        }
+    }
+
+    let fixed_params = after.visibility.params == Visibility::Fixed || before.visibility.params == Visibility::Fixed;
+    for node in &diff.nodes {
+        for change in &node.changes {
+            if fixed_params || !matches!(change, NodeChange::ConstantsChanged { .. }) {
+                diff.vk_changes.push(format!("node {:?}: {:?}", node.path, change));
+            }
+        }
+    }
+    for change in &diff.graph {
+        diff.vk_changes.push(format!("graph: {}", change));
+    }
+    for change in &diff.settings {
+        let top_level = change.field.split('.').next().unwrap_or_default();
+        if !NON_CIRCUIT_SETTINGS.contains(&top_level) {
+            diff.vk_changes.push(format!("settings {}", change.field));
+        }
+    }
+    diff
+}
+
+fn diff_graph(before: &Model, after: &Model, prefix: &[usize], diff: &mut ModelDiff) {
+    let (old, new) = (&before.graph, &after.graph);
+    let at = |what: &str| {
+        if prefix.is_empty() {
+            what.to_string()
+        } else {
+            format!("{} of subgraph {:?}", what, prefix)
+        }
+    };
+    if old.inputs != new.inputs {
+        diff.graph.push(format!("{}: {:?} -> {:?}", at("inputs"), old.inputs, new.inputs));
+    }
+    if old.outputs != new.outputs {
+        diff.graph.push(format!("{}: {:?} -> {:?}", at("outputs"), old.outputs, new.outputs));
+    }
+    if before.visibility != after.visibility {
+        diff.graph.push(format!(
+            "{}: {:?} -> {:?}",
+            at("visibility"),
+            before.visibility,
+            after.visibility
+        ));
+    }
+
+    let mut idxs: Vec<usize> = old.nodes.keys().chain(new.nodes.keys()).copied().collect();
+    idxs.sort();
+    idxs.dedup();
+    for idx in idxs {
+        let mut path = prefix.to_vec();
+        path.push(idx);
+        let mut changes = vec![];
+        match (old.nodes.get(&idx), new.nodes.get(&idx)) {
+            (Some(a), None) => changes.push(NodeChange::Removed(node_name(a))),
+            (None, Some(b)) => changes.push(NodeChange::Added(node_name(b))),
+            (Some(a), Some(b)) => {
+                diff_node(a, b, &path, &mut changes, diff);
+            }
+            (None, None) => {}
+        }
+        if !changes.is_empty() {
+            diff.nodes.push(NodeDiff { path, changes });
+        }
+    }
+}
+
+fn node_name(node: &NodeType) -> String {
+    match node {
+        NodeType::Node(n) => n.opkind.as_string(),
+        NodeType::SubGraph { .. } => "SUBGRAPH".to_string(),
+    }
+}
+
+fn diff_node(a: &NodeType, b: &NodeType, path: &[usize], changes: &mut Vec<NodeChange>, diff: &mut ModelDiff) {
+    if a.inputs() != b.inputs() {
+        changes.push(NodeChange::InputsChanged {
+            before: a.inputs(),
+            after: b.inputs(),
+        });
+    }
+    if a.out_dims() != b.out_dims() {
+        changes.push(NodeChange::ShapeChanged {
+            before: a.out_dims(),
+            after: b.out_dims(),
+        });
+    }
+    if a.out_scales() != b.out_scales() {
+        changes.push(NodeChange::ScaleChanged {
+            before: a.out_scales(),
+            after: b.out_scales(),
+        });
+    }
+
+    match (a, b) {
+        (NodeType::Node(a), NodeType::Node(b)) => {
+            if a.opkind.as_string() != b.opkind.as_string() {
+                changes.push(NodeChange::OpChanged {
+                    before: a.opkind.as_string(),
+                    after: b.opkind.as_string(),
+                });
+                return;
+            }
+            let (params_a, params_b) = (op_params(&a.opkind), op_params(&b.opkind));
+            if params_a != params_b {
+                changes.push(NodeChange::ParamsChanged {
+                    before: params_a.to_string(),
+                    after: params_b.to_string(),
+                });
+            }
+            let (values_a, values_b) = (
+                constant_values(&a.opkind, a.out_scale),
+                constant_values(&b.opkind, b.out_scale),
+            );
+            if values_a != values_b {
+                let max_delta = if values_a.len() == values_b.len() {
+                    Some(
+                        values_a
+                            .iter()
+                            .zip(&values_b)
+                            .map(|(x, y)| (x - y).abs())
+                            .fold(0.0, f64::max),
+                    )
+                } else {
+                    None
+                };
+                changes.push(NodeChange::ConstantsChanged { max_delta });
+            }
+            let (indices_a, indices_b) = (constant_indices(&a.opkind), constant_indices(&b.opkind));
+            if indices_a != indices_b {
+                changes.push(NodeChange::IndicesChanged {
+                    before: indices_a,
+                    after: indices_b,
+                });
+            }
+        }
+        (
+            NodeType::SubGraph {
+                model: model_a,
+                input_mappings: inputs_a,
+                output_mappings: outputs_a,
+                ..
+            },
+            NodeType::SubGraph {
+                model: model_b,
+                input_mappings: inputs_b,
+                output_mappings: outputs_b,
+                ..
+            },
+        ) => {
+            if inputs_a != inputs_b {
+                diff.graph.push(format!(
+                    "input mappings of subgraph {:?}: {:?} -> {:?}",
+                    path, inputs_a, inputs_b
+                ));
+            }
+            if outputs_a != outputs_b {
+                diff.graph.push(format!(
+                    "output mappings of subgraph {:?}: {:?} -> {:?}",
+                    path, outputs_a, outputs_b
+                ));
+            }
+            diff_graph(model_a, model_b, path, diff);
+        }
+        _ => changes.push(NodeChange::OpChanged {
+            before: node_name(a),
+            after: node_name(b),
+        }),
+    }
+}
+
+/// The op serialized to JSON with its constant values stripped out, as these are compared separately.
+fn op_params(op: &SupportedOp) -> Value {
+    let mut value = serde_json::to_value(op).unwrap_or(Value::Null);
+    strip_constants(&mut value);
+    value
+}
+
+fn strip_constants(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in ["kernel", "bias", "quantized_values", "raw_values", "constant_idx"] {
+                map.remove(key);
+            }
+            map.values_mut().for_each(strip_constants);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_constants),
+        _ => {}
+    }
+}
+
+/// The dequantized constant values held by an op: constants, and conv/deconv kernels and biases.
+fn constant_values(op: &SupportedOp, out_scale: Scale) -> Vec<f64> {
+    match op {
+        SupportedOp::Constant(c) => c.raw_values.iter().map(|x| *x as f64).collect(),
+        SupportedOp::Linear(PolyOp::Conv { kernel, bias, .. })
+        | SupportedOp::Linear(PolyOp::DeConv { kernel, bias, .. }) => {
+            let kernel_scale = kernel.scale().unwrap_or(out_scale);
+            let mut values: Vec<f64> = kernel.iter().map(|x| dequantize(*x, kernel_scale, 0.0)).collect();
+            if let Some(bias) = bias {
+                let bias_scale = bias.scale().unwrap_or(out_scale);
+                values.extend(bias.iter().map(|x| dequantize(*x, bias_scale, 0.0)));
+            }
+            values
+        }
+        SupportedOp::Rescaled(op) => constant_values(&op.inner, out_scale),
+        SupportedOp::RebaseScale(op) => constant_values(&op.inner, op.original_scale),
+        _ => vec![],
+    }
+}
+
+/// The constant indices of a gather or scatter.
+fn constant_indices(op: &SupportedOp) -> Option<Tensor<usize>> {
+    match op {
+        SupportedOp::Hybrid(HybridOp::Gather { constant_idx, .. })
+        | SupportedOp::Hybrid(HybridOp::GatherElements { constant_idx, .. })
+        | SupportedOp::Hybrid(HybridOp::ScatterElements { constant_idx, .. }) => constant_idx.clone(),
+        SupportedOp::Rescaled(op) => constant_indices(&op.inner),
+        SupportedOp::RebaseScale(op) => constant_indices(&op.inner),
+        _ => None,
+    }
+}
+
+/// Records every leaf field that differs between two JSON values, by dotted path.
+fn diff_values(field: &str, old: &Value, new: &Value, changes: &mut Vec<SettingsChange>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let sub = if field.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", field, key)
+                };
+                diff_values(
+                    &sub,
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(SettingsChange {
+                    field: field.to_string(),
+                    before: old.to_string(),
+                    after: new.to_string(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphsettings::LookupOp;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::testutils::{constant, input, model, node, subgraph};
+
+    const SETTINGS_JSON: &str = include_str!("../../settings.json");
+
+    /// `gather(x, idx) + w`
+    fn base(weights: &[f32], idx: &[usize]) -> Model {
+        let gather = HybridOp::Gather {
+            dim: 0,
+            constant_idx: Some(Tensor::new(Some(idx), &[idx.len()]).unwrap()),
+        };
+        model(
+            vec![
+                input(0, &[4], 0),
+                constant(1, weights, &[weights.len()], 0),
+                node(2, SupportedOp::Hybrid(gather), &[(0, 0)], &[idx.len()], 0),
+                node(3, SupportedOp::Linear(PolyOp::Add), &[(2, 0), (1, 0)], &[idx.len()], 0),
+            ],
+            &[0],
+            &[(3, 0)],
+        )
+    }
+
+    fn changes(diff: &ModelDiff) -> Vec<(Vec<usize>, NodeChange)> {
+        diff.nodes
+            .iter()
+            .flat_map(|n| n.changes.iter().map(|c| (n.path.clone(), c.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn identical_models() {
+        let diff = diff_models(&base(&[1.0, 2.0], &[0, 2]), &base(&[1.0, 2.0], &[0, 2]), None);
+        assert!(diff.is_empty());
+        assert!(!diff.alters_vk());
+    }
+
+    #[test]
+    fn private_constants_keep_the_vk() {
+        let diff = diff_models(&base(&[1.0, 2.0], &[0, 2]), &base(&[1.0, 4.5], &[0, 2]), None);
+        assert_eq!(
+            changes(&diff),
+            vec![(vec![1], NodeChange::ConstantsChanged { max_delta: Some(2.5) })]
+        );
+        assert!(!diff.alters_vk());
+    }
+
+    #[test]
+    fn fixed_constants_alter_the_vk() {
+        let mut before = base(&[1.0, 2.0], &[0, 2]);
+        let mut after = base(&[1.0, 4.0], &[0, 2]);
+        before.visibility.params = Visibility::Fixed;
+        after.visibility.params = Visibility::Fixed;
+        let diff = diff_models(&before, &after, None);
+        assert!(diff.alters_vk());
+        assert_eq!(diff.vk_changes.len(), 1);
+    }
+
+    #[test]
+    fn gather_indices_always_alter_the_vk() {
+        let diff = diff_models(&base(&[1.0, 2.0], &[0, 2]), &base(&[1.0, 2.0], &[1, 3]), None);
+        assert_eq!(
+            changes(&diff),
+            vec![(
+                vec![2],
+                NodeChange::IndicesChanged {
+                    before: Some(Tensor::new(Some(&[0, 2]), &[2]).unwrap()),
+                    after: Some(Tensor::new(Some(&[1, 3]), &[2]).unwrap()),
+                }
+            )]
+        );
+        assert!(diff.alters_vk());
+    }
+
+    #[test]
+    fn added_removed_and_changed_ops() {
+        let before = base(&[1.0, 2.0], &[0, 2]);
+        let mut after = base(&[1.0, 2.0], &[0, 2]);
+        after
+            .graph
+            .nodes
+            .insert(3, node(3, SupportedOp::Linear(PolyOp::Sub), &[(2, 0), (1, 0)], &[2], 0));
+        after
+            .graph
+            .nodes
+            .insert(4, node(4, SupportedOp::Nonlinear(LookupOp::ReLU), &[(3, 0)], &[2], 0));
+        after.graph.outputs = vec![(4, 0)];
+        let diff = diff_models(&before, &after, None);
+        assert_eq!(
+            changes(&diff),
+            vec![
+                (
+                    vec![3],
+                    NodeChange::OpChanged {
+                        before: "ADD".to_string(),
+                        after: "SUB".to_string(),
+                    }
+                ),
+                (vec![4], NodeChange::Added("RELU".to_string())),
+            ]
+        );
+        assert_eq!(diff.graph.len(), 1);
+        assert!(diff.alters_vk());
+
+        let diff = diff_models(&after, &before, None);
+        assert_eq!(diff.nodes[1].changes, vec![NodeChange::Removed("RELU".to_string())]);
+    }
+
+    #[test]
+    fn settings_changes() {
+        let old = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        let m = base(&[1.0], &[0]);
+
+        let mut new = old.clone();
+        new.check_mode = crate::graphsettings::CheckMode::SAFE;
+        let diff = diff_models(&m, &m, Some((&old, &new)));
+        assert_eq!(diff.settings.len(), 1);
+        assert_eq!(diff.settings[0].field, "check_mode");
+        assert!(!diff.alters_vk());
+
+        new.run_args.logrows += 1;
+        let diff = diff_models(&m, &m, Some((&old, &new)));
+        assert_eq!(diff.settings.len(), 2);
+        assert_eq!(diff.vk_changes, vec!["settings run_args.logrows".to_string()]);
+    }
+
+    #[test]
+    fn subgraph_mappings_and_bodies() {
+        let scan = |chunk: usize, body_op: PolyOp<halo2curves::bn256::Fr>| {
+            let body = model(
+                vec![input(0, &[1], 0), node(1, SupportedOp::Linear(body_op), &[(0, 0)], &[1], 0)],
+                &[0],
+                &[(1, 0)],
+            );
+            model(
+                vec![
+                    input(0, &[4], 0),
+                    subgraph(
+                        1,
+                        body,
+                        &[(0, 0)],
+                        vec![InputMapping::Stacked { axis: 0, chunk }],
+                        vec![vec![OutputMapping::Stacked {
+                            outlet: 0,
+                            axis: 0,
+                            is_state: false,
+                        }]],
+                        vec![vec![4]],
+                        vec![0],
+                    ),
+                ],
+                &[0],
+                &[(1, 0)],
+            )
+        };
+        let diff = diff_models(&scan(1, PolyOp::Neg), &scan(2, PolyOp::Identity), None);
+        assert_eq!(diff.graph.len(), 1);
+        assert!(diff.graph[0].starts_with("input mappings of subgraph [1]"));
+        assert_eq!(diff.nodes.len(), 1);
+        assert_eq!(diff.nodes[0].path, vec![1, 1]);
+        assert!(diff.alters_vk());
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::ops::{Add, Mul};
+
+use crate::tensor::{index_iter, Tensor, TensorError, TensorType};
+
+/// A parsed einsum equation, eg. `ij,jk->ik`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EinsumEquation {
+    /// The indices of each input, in order.
+    pub inputs: Vec<Vec<char>>,
+    /// The indices of the output. For implicit equations (no `->`) these are the indices
+    /// appearing exactly once across the inputs, in alphabetical order.
+    pub output: Vec<char>,
+}
+
+/// The cost of laying out an einsum contraction in the circuit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EinsumCost {
+    /// The number of elements in the output.
+    pub output_len: usize,
+    /// The number of terms summed into every output element.
+    pub contraction_len: usize,
+    /// The number of cells assigned, ie. how far the contraction advances the circuit's linear coordinate.
+    pub assignments: usize,
+    /// The number of rows the contraction takes up.
+    pub rows: usize,
+}
+
+impl EinsumEquation {
+    /// Parses and validates an einsum equation, without reference to any input shapes.
+    /// ```
+    /// use core_ezkl::einsum::EinsumEquation;
+    /// let eq = EinsumEquation::parse("ij,jk").unwrap();
+    /// assert_eq!(eq.output, vec!['i', 'k']);
+    /// assert!(EinsumEquation::parse("ij,jk->iz").is_err());
+    /// ```
+    pub fn parse(equation: &str) -> Result<Self, TensorError> {
+        let equation: String = equation.chars().filter(|c| !c.is_whitespace()).collect();
+        let (lhs, rhs) = match equation.split_once("->") {
+            Some((lhs, rhs)) => (lhs, Some(rhs)),
+            None => (equation.as_str(), None),
+        };
+
+        let inputs: Vec<Vec<char>> = lhs.split(',').map(|s| s.chars().collect()).collect();
+        for c in inputs.iter().flatten() {
+            if !c.is_ascii_alphabetic() {
+                return Err(TensorError::DimError(format!(
+                    "invalid index '{}' in einsum equation {}",
+                    c, equation
+                )));
+            }
+        }
+
+        let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+        for c in inputs.iter().flatten() {
+            *counts.entry(*c).or_insert(0) += 1;
+        }
+
+        let output: Vec<char> = match rhs {
+            Some(rhs) => rhs.chars().collect(),
+            None => counts
+                .iter()
+                .filter(|(_, count)| **count == 1)
+                .map(|(c, _)| *c)
+                .collect(),
+        };
+        for (i, c) in output.iter().enumerate() {
+            if !counts.contains_key(c) {
+                return Err(TensorError::DimError(format!(
+                    "output index '{}' does not appear in any input of einsum equation {}",
+                    c, equation
+                )));
+            }
+            if output[..i].contains(c) {
+                return Err(TensorError::DimError(format!(
+                    "output index '{}' is repeated in einsum equation {}",
+                    c, equation
+                )));
+            }
+        }
+
+        Ok(EinsumEquation { inputs, output })
+    }
+
+    /// Returns the indices that are summed over, ie. that appear in the inputs but not in the output.
+    pub fn contracted(&self) -> Vec<char> {
+        let mut contracted = vec![];
+        for c in self.inputs.iter().flatten() {
+            if !self.output.contains(c) && !contracted.contains(c) {
+                contracted.push(*c);
+            }
+        }
+        contracted
+    }
+
+    /// Checks the input shapes against the equation and returns the size of every index.
+    pub fn index_sizes(&self, shapes: &[&[usize]]) -> Result<BTreeMap<char, usize>, TensorError> {
+        if shapes.len() != self.inputs.len() {
+            return Err(TensorError::DimMismatch(format!(
+                "einsum equation has {} inputs but {} were provided",
+                self.inputs.len(),
+                shapes.len()
+            )));
+        }
+        let mut sizes: BTreeMap<char, usize> = BTreeMap::new();
+        for (i, (indices, shape)) in self.inputs.iter().zip(shapes).enumerate() {
+            if indices.len() != shape.len() {
+                return Err(TensorError::DimMismatch(format!(
+                    "einsum input {} has indices {:?} but dims {:?}",
+                    i, indices, shape
+                )));
+            }
+            for (c, d) in indices.iter().zip(shape.iter()) {
+                match sizes.get(c) {
+                    Some(size) if size != d => {
+                        return Err(TensorError::DimMismatch(format!(
+                            "einsum operands could not be broadcast together: index '{}' has size {} but input {} has size {}",
+                            c, size, i, d
+                        )));
+                    }
+                    _ => {
+                        sizes.insert(*c, *d);
+                    }
+                }
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// Returns the output dims for the given input shapes.
+    pub fn output_dims(&self, shapes: &[&[usize]]) -> Result<Vec<usize>, TensorError> {
+        let sizes = self.index_sizes(shapes)?;
+        Ok(self.output.iter().map(|c| sizes[c]).collect())
+    }
+
+    /// Estimates the rows and assignments the circuit needs for this contraction.
+    /// Every output element is an accumulated dot product over the contracted indices,
+    /// laid out over `num_inner_cols` columns.
+    pub fn cost(&self, shapes: &[&[usize]], num_inner_cols: usize) -> Result<EinsumCost, TensorError> {
+        let sizes = self.index_sizes(shapes)?;
+        let output_len: usize = self.output.iter().map(|c| sizes[c]).product();
+        let contraction_len: usize = self.contracted().iter().map(|c| sizes[c]).product();
+        let num_inner_cols = num_inner_cols.max(1);
+        // each extra input adds one pairwise product per term
+        let num_products = self.inputs.len().saturating_sub(1).max(1);
+
+        let assignments = output_len * contraction_len.max(1) * num_products;
+        let rows = if contraction_len <= 1 {
+            (output_len * num_products).div_ceil(num_inner_cols)
+        } else {
+            output_len * num_products * contraction_len.div_ceil(num_inner_cols)
+        };
+
+        Ok(EinsumCost {
+            output_len,
+            contraction_len,
+            assignments,
+            rows,
+        })
+    }
+}
+
+/// Evaluates an einsum equation over tensors.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::einsum::einsum;
+/// let x = Tensor::<i128>::new(Some(&[2, 1, 2, 1, 1, 1]), &[2, 3]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[2, 3, 2, 1, 1, 1]), &[3, 2]).unwrap();
+/// let result = einsum("ij,jk->ik", &[&x, &k]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[8, 9, 5, 5]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn einsum<T: TensorType + Add<Output = T> + Mul<Output = T>>(
+    equation: &str,
+    inputs: &[&Tensor<T>],
+) -> Result<Tensor<T>, TensorError> {
+    let eq = EinsumEquation::parse(equation)?;
+    let shapes: Vec<&[usize]> = inputs.iter().map(|t| t.dims()).collect();
+    let sizes = eq.index_sizes(&shapes)?;
+
+    let zero = T::zero().ok_or_else(|| TensorError::Unsupported("element type has no zero value".to_string()))?;
+    let output_dims: Vec<usize> = eq.output.iter().map(|c| sizes[c]).collect();
+    let contracted = eq.contracted();
+    let contracted_dims: Vec<usize> = contracted.iter().map(|c| sizes[c]).collect();
+
+    let mut values = Vec::with_capacity(output_dims.iter().product());
+    let mut assignment: BTreeMap<char, usize> = BTreeMap::new();
+    for out_coord in index_iter(&output_dims) {
+        for (c, v) in eq.output.iter().zip(out_coord.iter()) {
+            assignment.insert(*c, *v);
+        }
+        let mut acc = zero.clone();
+        for inner_coord in index_iter(&contracted_dims) {
+            for (c, v) in contracted.iter().zip(inner_coord.iter()) {
+                assignment.insert(*c, *v);
+            }
+            let mut term: Option<T> = None;
+            for (indices, t) in eq.inputs.iter().zip(inputs) {
+                let coord: Vec<usize> = indices.iter().map(|c| assignment[c]).collect();
+                let x = t.get(&coord);
+                term = Some(match term {
+                    Some(prev) => prev * x,
+                    None => x,
+                });
+            }
+            if let Some(term) = term {
+                acc = acc + term;
+            }
+        }
+        values.push(acc);
+    }
+
+    Tensor::new(Some(&values), &output_dims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn einsum_implicit_output() {
+        let eq = EinsumEquation::parse("ba,cb").unwrap();
+        assert_eq!(eq.output, vec!['a', 'c']);
+        assert_eq!(eq.contracted(), vec!['b']);
+    }
+
+    #[test]
+    fn einsum_rejects_repeated_output_index() {
+        assert!(EinsumEquation::parse("ij->ii").is_err());
+    }
+
+    #[test]
+    fn einsum_rejects_mismatched_dims() {
+        let a = Tensor::<i128>::new(None, &[2, 3]).unwrap();
+        let b = Tensor::<i128>::new(None, &[4, 2]).unwrap();
+        assert!(matches!(
+            einsum("ij,jk->ik", &[&a, &b]),
+            Err(TensorError::DimMismatch(_))
+        ));
+        assert!(einsum("ij,jk->ik", &[&a]).is_err());
+    }
+
+    #[test]
+    fn einsum_trace_and_diagonal() {
+        let a = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+        assert_eq!(&einsum("ii->", &[&a]).unwrap()[..], &[5]);
+        assert_eq!(&einsum("ii->i", &[&a]).unwrap()[..], &[1, 4]);
+    }
+
+    #[test]
+    fn einsum_batched_matmul_cost() {
+        let eq = EinsumEquation::parse("bij,bjk->bik").unwrap();
+        let cost = eq.cost(&[&[2, 3, 4], &[2, 4, 5]], 2).unwrap();
+        assert_eq!(cost.output_len, 30);
+        assert_eq!(cost.contraction_len, 4);
+        assert_eq!(cost.rows, 60);
+    }
+}
@@ -0,0 +1,137 @@
+use halo2_gadgets::poseidon::primitives::{ConstantLength, Hash};
+use halo2curves::bn256::{Fq, Fr as Fp, G1Affine};
+use halo2curves::ff::{Field, FromUniformBytes, PrimeField};
+use halo2curves::group::prime::PrimeCurveAffine;
+use halo2curves::{Coordinates, CurveAffine};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::poseidon::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH};
+use crate::schema::{FeltSchema, G1AffineSchema};
+
+/// An ElGamal ciphertext over BN254: `c1 = g * r` and `c2_i = m_i + H(pk * r)` where `H` is Poseidon over the coordinates of
+/// the shared secret.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Ciphertext {
+    /// the ephemeral public key
+    #[schemars(with = "G1AffineSchema")]
+    pub c1: G1Affine,
+    /// the masked message
+    #[schemars(with = "Vec<FeltSchema>")]
+    pub c2: Vec<Fp>,
+}
+
+/// The result of encrypting tensors with the ElGamal module during a forward pass.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct ElGamalResult {
+    /// one ciphertext per encrypted tensor
+    pub ciphertexts: Vec<Ciphertext>,
+}
+
+/// Returns the public key of a secret key.
+pub fn public_key(sk: Fp) -> G1Affine {
+    (G1Affine::generator() * sk).into()
+}
+
+/// Reduces a base field element into the scalar field.
+fn fq_to_fr(x: Fq) -> Fp {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(x.to_repr().as_ref());
+    Fp::from_uniform_bytes(&bytes)
+}
+
+/// Returns the coordinates of a point reduced into the scalar field, zeros for the point at infinity.
+fn coordinates(point: G1Affine) -> (Fp, Fp) {
+    let coords: Option<Coordinates<G1Affine>> = point.coordinates().into();
+    match coords {
+        Some(coords) => (fq_to_fr(*coords.x()), fq_to_fr(*coords.y())),
+        None => (Fp::ZERO, Fp::ZERO),
+    }
+}
+
+/// Hashes the shared secret into the mask applied to every element of the message.
+fn mask(shared: G1Affine) -> Fp {
+    let (x, y) = coordinates(shared);
+    Hash::<_, PoseidonSpec, ConstantLength<2>, POSEIDON_WIDTH, POSEIDON_RATE>::init().hash([x, y])
+}
+
+/// Encrypts a message to `pk` with the randomness `r`.
+pub fn encrypt(pk: G1Affine, message: &[Fp], r: Fp) -> Ciphertext {
+    let c1 = (G1Affine::generator() * r).into();
+    let mask = mask((pk * r).into());
+    Ciphertext {
+        c1,
+        c2: message.iter().map(|m| *m + mask).collect(),
+    }
+}
+
+/// Decrypts a ciphertext with the secret key `sk`.
+pub fn decrypt(ciphertext: &Ciphertext, sk: Fp) -> Vec<Fp> {
+    let mask = mask((ciphertext.c1 * sk).into());
+    ciphertext.c2.iter().map(|c| *c - mask).collect()
+}
+
+/// Returns the instances exposing a ciphertext: the coordinates of `c1`, reduced into the scalar field, followed by the
+/// masked message.
+pub fn ciphertext_instances(ciphertext: &Ciphertext) -> Vec<Fp> {
+    let (x, y) = coordinates(ciphertext.c1);
+    let mut instances = vec![x, y];
+    instances.extend(&ciphertext.c2);
+    instances
+}
+
+/// Returns true if the whole ciphertext, as laid out by [ciphertext_instances], is found in `instances` starting at
+/// `offset`. Checking `c2` alone would accept a ciphertext whose ephemeral key was swapped.
+pub fn check_ciphertext_instances(ciphertext: &Ciphertext, instances: &[Fp], offset: usize) -> bool {
+    let expected = ciphertext_instances(ciphertext);
+    instances.get(offset..offset + expected.len()) == Some(expected.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn felts(values: &[u64]) -> Vec<Fp> {
+        values.iter().map(|x| Fp::from(*x)).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let (sk, r) = (Fp::from(42), Fp::from(7));
+        let message = felts(&[0, 28, 34]);
+        let ciphertext = encrypt(public_key(sk), &message, r);
+        assert_eq!(ciphertext.c1, public_key(r));
+        assert_ne!(ciphertext.c2, message);
+        assert_eq!(decrypt(&ciphertext, sk), message);
+        assert_ne!(decrypt(&ciphertext, Fp::from(43)), message);
+        // a fresh randomness yields a different ciphertext of the same message
+        let other = encrypt(public_key(sk), &message, Fp::from(8));
+        assert_ne!(other.c2, ciphertext.c2);
+        assert_eq!(decrypt(&other, sk), message);
+    }
+
+    #[test]
+    fn ciphertext_instances_hold_c1_and_c2() {
+        let pk = public_key(Fp::from(42));
+        let ciphertext = encrypt(pk, &felts(&[0, 28, 34]), Fp::from(7));
+        let exposed = ciphertext_instances(&ciphertext);
+        assert_eq!(exposed.len(), 5);
+        assert_eq!(exposed[2..], ciphertext.c2[..]);
+
+        let mut instances = felts(&[9]);
+        instances.extend(&exposed);
+        assert!(check_ciphertext_instances(&ciphertext, &instances, 1));
+        assert!(!check_ciphertext_instances(&ciphertext, &instances, 0));
+        assert!(!check_ciphertext_instances(&ciphertext, &instances, 2));
+
+        // the same masked message under another ephemeral key
+        let swapped = Ciphertext {
+            c1: public_key(Fp::from(8)),
+            c2: ciphertext.c2.clone(),
+        };
+        assert!(!check_ciphertext_instances(&swapped, &instances, 1));
+        let mut tampered = ciphertext.clone();
+        tampered.c2[2] += Fp::ONE;
+        assert!(!check_ciphertext_instances(&tampered, &instances, 1));
+    }
+}
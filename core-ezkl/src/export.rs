@@ -0,0 +1,382 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Model, NodeType, Outlet, OutputMapping, ParsedNodes, SupportedOp, Visibility};
+use crate::utils::Scale;
+
+/// A node of the model graph, flattened for export.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportedNode {
+    /// The indices of the enclosing subgraphs (outermost first) followed by the node's index.
+    pub path: Vec<usize>,
+    /// The name of the node's op, or `SUBGRAPH`.
+    pub op: String,
+    /// The outlets the node reads from, within its enclosing graph.
+    pub inputs: Vec<Outlet>,
+    /// The dims of each of the node's outputs.
+    pub out_dims: Vec<Vec<usize>>,
+    /// The scale of each of the node's outputs.
+    pub out_scales: Vec<Scale>,
+    /// The node's num of uses (not recorded for subgraphs).
+    pub num_uses: Option<usize>,
+    /// The visibility of the node's output, for inputs, constants and outputs of the graph.
+    pub visibility: Option<Visibility>,
+    /// For subgraphs, how the subgraph's inputs are fed on every iteration.
+    pub input_mappings: Option<Vec<String>>,
+    /// For subgraphs, how the subgraph's outputs are collected.
+    pub output_mappings: Option<Vec<Vec<String>>>,
+}
+
+/// Flattens the model graph, including the nodes of subgraphs, into a list of nodes in graph order.
+pub fn to_json_nodes(model: &Model) -> Vec<ExportedNode> {
+    let mut nodes = vec![];
+    collect_nodes(model, &[], &mut nodes);
+    nodes
+}
+
+/// Serializes the flattened model graph to a JSON string.
+pub fn to_json(model: &Model) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&to_json_nodes(model))
+}
+
+fn node_visibility(model: &Model, node: &NodeType) -> Option<Visibility> {
+    if model.graph.outputs.iter().any(|o| o.0 == node.idx()) {
+        return Some(model.visibility.output.clone());
+    }
+    match node {
+        NodeType::Node(n) => match &n.opkind {
+            SupportedOp::Input(_) => Some(model.visibility.input.clone()),
+            SupportedOp::Constant(_) => Some(model.visibility.params.clone()),
+            _ => None,
+        },
+        NodeType::SubGraph { .. } => None,
+    }
+}
+
+fn collect_nodes(model: &Model, prefix: &[usize], nodes: &mut Vec<ExportedNode>) {
+    for (idx, node) in model.graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+        let visibility = node_visibility(model, node);
+        match node {
+            NodeType::Node(n) => nodes.push(ExportedNode {
+                path,
+                op: n.opkind.as_string(),
+                inputs: n.inputs.clone(),
+                out_dims: vec![n.out_dims.clone()],
+                out_scales: vec![n.out_scale],
+                num_uses: Some(n.num_uses),
+                visibility,
+                input_mappings: None,
+                output_mappings: None,
+            }),
+            NodeType::SubGraph {
+                model: submodel,
+                inputs,
+                output_mappings,
+                input_mappings,
+                out_dims,
+                out_scales,
+                ..
+            } => {
+                nodes.push(ExportedNode {
+                    path: path.clone(),
+                    op: "SUBGRAPH".to_string(),
+                    inputs: inputs.clone(),
+                    out_dims: out_dims.clone(),
+                    out_scales: out_scales.clone(),
+                    num_uses: None,
+                    visibility,
+                    input_mappings: Some(input_mappings.iter().map(|m| format!("{:?}", m)).collect()),
+                    output_mappings: Some(
+                        output_mappings
+                            .iter()
+                            .map(|ms| ms.iter().map(|m| format!("{:?}", m)).collect())
+                            .collect(),
+                    ),
+                });
+                collect_nodes(submodel, &path, nodes);
+            }
+        }
+    }
+}
+
+fn dot_id(path: &[usize]) -> String {
+    let parts: Vec<String> = path.iter().map(|p| p.to_string()).collect();
+    format!("\"{}\"", parts.join("/"))
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Resolves an outlet to the path of the node that produces it, looking through subgraphs to the node producing their output.
+fn source_path(graph: &ParsedNodes, prefix: &[usize], outlet: &Outlet) -> Vec<usize> {
+    let mut path = prefix.to_vec();
+    path.push(outlet.0);
+    match graph.nodes.get(&outlet.0) {
+        Some(NodeType::SubGraph {
+            model, output_mappings, ..
+        }) => {
+            // the subgraph outlet is fed by the body output whose mappings target it
+            let body_output = output_mappings.iter().position(|mappings| {
+                mappings.iter().any(|m| match m {
+                    OutputMapping::Single { outlet: o, .. } | OutputMapping::Stacked { outlet: o, .. } => *o == outlet.1,
+                })
+            });
+            match body_output.and_then(|i| model.graph.outputs.get(i)) {
+                Some(inner) => source_path(&model.graph, &path, inner),
+                None => path,
+            }
+        }
+        _ => path,
+    }
+}
+
+/// Renders the model graph to Graphviz DOT. Subgraphs are drawn as clusters annotated with their input and output mappings,
+/// with the parent's edges routed to the subgraph's input nodes and from its output nodes.
+pub fn to_dot(model: &Model) -> String {
+    let mut out = String::new();
+    out.push_str("digraph model {\n  node [shape=box, fontname=\"monospace\"];\n");
+    write_graph(model, &[], 1, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+fn write_graph(model: &Model, prefix: &[usize], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let graph = &model.graph;
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+        let visibility = node_visibility(model, node)
+            .map(|v| format!("\\n{}", escape(&format!("{:?}", v))))
+            .unwrap_or_default();
+        match node {
+            NodeType::Node(n) => {
+                let label = format!(
+                    "{}: {}\\ndims {:?}\\nscale {}\\nuses {}",
+                    idx,
+                    escape(&n.opkind.as_string()),
+                    n.out_dims,
+                    n.out_scale,
+                    n.num_uses
+                );
+                let _ = writeln!(
+                    out,
+                    "{}{} [label=\"{}{}\"];",
+                    indent,
+                    dot_id(&path),
+                    label,
+                    visibility
+                );
+                for (i, input) in n.inputs.iter().enumerate() {
+                    let _ = writeln!(
+                        out,
+                        "{}{} -> {} [label=\"{}\"];",
+                        indent,
+                        dot_id(&source_path(graph, prefix, input)),
+                        dot_id(&path),
+                        i
+                    );
+                }
+            }
+            NodeType::SubGraph {
+                model: submodel,
+                inputs,
+                output_mappings,
+                input_mappings,
+                out_dims,
+                out_scales,
+                ..
+            } => {
+                let cluster: Vec<String> = path.iter().map(|p| p.to_string()).collect();
+                let _ = writeln!(out, "{}subgraph cluster_{} {{", indent, cluster.join("_"));
+                let _ = writeln!(
+                    out,
+                    "{}  label=\"{}: SUBGRAPH\\ndims {:?}\\nscales {:?}\\ninputs {}\\noutputs {}{}\";",
+                    indent,
+                    idx,
+                    out_dims,
+                    out_scales,
+                    escape(&format!("{:?}", input_mappings)),
+                    escape(&format!("{:?}", output_mappings)),
+                    visibility
+                );
+                write_graph(submodel, &path, depth + 1, out);
+                let _ = writeln!(out, "{}}}", indent);
+                for (i, (input, inner)) in inputs.iter().zip(submodel.graph.inputs.iter()).enumerate() {
+                    let mut inner_path = path.clone();
+                    inner_path.push(*inner);
+                    let mapping = input_mappings.get(i).map(|m| format!("{:?}", m)).unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "{}{} -> {} [label=\"{}\", style=dashed];",
+                        indent,
+                        dot_id(&source_path(graph, prefix, input)),
+                        dot_id(&inner_path),
+                        escape(&mapping)
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{InputMapping, VarVisibility};
+    use crate::supportedop::PolyOp;
+    use crate::testutils::{constant, input, model, node, subgraph};
+
+    /// A Scan whose body outputs are mapped to the subgraph outlets in reverse order: body output 0 (the sum, node 1)
+    /// feeds outlet 1 and body output 1 (the negation, node 2) feeds outlet 0.
+    fn scan() -> Model {
+        let body = model(
+            vec![
+                input(0, &[1, 2], 0),
+                node(1, SupportedOp::Linear(PolyOp::Sum { axes: vec![1] }), &[(0, 0)], &[1, 1], 0),
+                node(2, SupportedOp::Linear(PolyOp::Neg), &[(0, 0)], &[1, 2], 0),
+            ],
+            &[0],
+            &[(1, 0), (2, 0)],
+        );
+        model(
+            vec![
+                input(0, &[4, 2], 0),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![
+                        vec![OutputMapping::Stacked {
+                            outlet: 1,
+                            axis: 0,
+                            is_state: false,
+                        }],
+                        vec![OutputMapping::Single {
+                            outlet: 0,
+                            is_state: false,
+                        }],
+                    ],
+                    vec![vec![1, 2], vec![4, 1]],
+                    vec![0, 0],
+                ),
+                node(2, SupportedOp::Linear(PolyOp::Identity), &[(1, 0)], &[1, 2], 0),
+                node(3, SupportedOp::Linear(PolyOp::Identity), &[(1, 1)], &[4, 1], 0),
+            ],
+            &[0],
+            &[(2, 0), (3, 0)],
+        )
+    }
+
+    #[test]
+    fn source_path_follows_output_mappings() {
+        let m = scan();
+        assert_eq!(source_path(&m.graph, &[], &(1, 0)), vec![1, 2]);
+        assert_eq!(source_path(&m.graph, &[], &(1, 1)), vec![1, 1]);
+        assert_eq!(source_path(&m.graph, &[], &(0, 0)), vec![0]);
+        // unmapped outlets resolve to the subgraph itself
+        assert_eq!(source_path(&m.graph, &[], &(1, 2)), vec![1]);
+    }
+
+    #[test]
+    fn dot_routes_edges_through_subgraphs() {
+        let dot = to_dot(&scan());
+        assert!(dot.contains("subgraph cluster_1 {"));
+        assert!(dot.contains("\"1/2\" -> \"2\" [label=\"0\"];"));
+        assert!(dot.contains("\"1/1\" -> \"3\" [label=\"0\"];"));
+        assert!(dot.contains("\"0\" -> \"1/0\" [label=\"Stacked { axis: 0, chunk: 1 }\", style=dashed];"));
+    }
+
+    #[test]
+    fn json_nodes_include_subgraph_bodies() {
+        let nodes = to_json_nodes(&scan());
+        let paths: Vec<Vec<usize>> = nodes.iter().map(|n| n.path.clone()).collect();
+        assert_eq!(paths, vec![vec![0], vec![1], vec![1, 0], vec![1, 1], vec![1, 2], vec![2], vec![3]]);
+        assert_eq!(nodes[1].op, "SUBGRAPH");
+        assert_eq!(nodes[1].num_uses, None);
+        assert_eq!(nodes[1].output_mappings.as_ref().map(|m| m.len()), Some(2));
+        assert_eq!(nodes[5].visibility, Some(Visibility::Private));
+    }
+
+    #[test]
+    fn nested_subgraphs() {
+        let single = || {
+            vec![vec![OutputMapping::Single {
+                outlet: 0,
+                is_state: false,
+            }]]
+        };
+        let inner = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, SupportedOp::Linear(PolyOp::Neg), &[(0, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let middle = model(
+            vec![
+                input(0, &[2], 0),
+                subgraph(1, inner, &[(0, 0)], vec![InputMapping::Full], single(), vec![vec![2]], vec![0]),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                subgraph(1, middle, &[(0, 0)], vec![InputMapping::Full], single(), vec![vec![2]], vec![0]),
+                node(2, SupportedOp::Linear(PolyOp::Identity), &[(1, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(2, 0)],
+        );
+        assert_eq!(source_path(&m.graph, &[], &(1, 0)), vec![1, 1, 1]);
+
+        let dot = to_dot(&m);
+        assert!(dot.contains("    subgraph cluster_1_1 {"));
+        assert!(dot.contains("\"1/1/1\" -> \"2\" [label=\"0\"];"));
+        assert!(dot.contains("\"1/0\" -> \"1/1/0\" [label=\"Full\", style=dashed];"));
+        assert!(dot.contains("\"1/1/0\" -> \"1/1/1\" [label=\"0\"];"));
+    }
+
+    #[test]
+    fn visibility_of_inputs_params_and_outputs() {
+        // the constant is also a model output
+        let mut m = model(
+            vec![
+                input(0, &[2], 0),
+                constant(1, &[1.0, 2.0], &[2], 0),
+                node(2, SupportedOp::Linear(PolyOp::Add), &[(0, 0), (1, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(1, 0), (2, 0)],
+        );
+        m.visibility = VarVisibility {
+            input: Visibility::Public,
+            params: Visibility::Fixed,
+            output: Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![],
+            },
+        };
+        let nodes = to_json_nodes(&m);
+        let visibilities: Vec<_> = nodes.iter().map(|n| n.visibility.clone()).collect();
+        let output = Some(m.visibility.output.clone());
+        assert_eq!(visibilities, vec![Some(Visibility::Public), output.clone(), output]);
+        assert!(to_dot(&m).contains("\\nHashed { hash_is_public: true, outlets: [] }\"];"));
+
+        let json: Vec<ExportedNode> = serde_json::from_str(&to_json(&m).unwrap()).unwrap();
+        assert_eq!(json, nodes);
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(escape(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+    }
+}
@@ -0,0 +1,90 @@
+use halo2curves::ff::PrimeField;
+
+use crate::tensor::TensorError;
+use crate::utils::{scale_to_multiplier, Scale};
+
+/// Converts an i128 to a field element, mapping negative values to `p - |x|`.
+pub fn i128_to_felt<F: PrimeField>(x: i128) -> F {
+    if x >= 0 {
+        F::from_u128(x as u128)
+    } else {
+        -F::from_u128(x.unsigned_abs())
+    }
+}
+
+/// Converts a field element to an i128, interpreting elements above `i128::MAX` as negative.
+/// Elements outside of `[-i128::MAX, i128::MAX]` are truncated; use [felt_in_i128_range] to check beforehand.
+pub fn felt_to_i128<F: PrimeField + PartialOrd>(x: F) -> i128 {
+    if x > F::from_u128(i128::MAX as u128) {
+        let rep = (-x).to_repr();
+        let negtmp: &[u8] = rep.as_ref();
+        let lower_128: u128 = u128::from_le_bytes(negtmp[..16].try_into().unwrap());
+        return -(lower_128 as i128);
+    }
+    let rep = x.to_repr();
+    let tmp: &[u8] = rep.as_ref();
+    let lower_128: u128 = u128::from_le_bytes(tmp[..16].try_into().unwrap());
+    lower_128 as i128
+}
+
+/// Returns true if the field element round-trips through [felt_to_i128], ie. it represents a signed integer of at most 127 bits.
+pub fn felt_in_i128_range<F: PrimeField + PartialOrd>(x: F) -> bool {
+    i128_to_felt::<F>(felt_to_i128(x)) == x
+}
+
+/// Formats a field element as a big-endian 0x-prefixed hex string, as found in `PrettyElements`.
+pub fn felt_to_hex<F: PrimeField>(x: F) -> String {
+    let rep = x.to_repr();
+    let bytes: &[u8] = rep.as_ref();
+    let hex: String = bytes.iter().rev().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
+
+/// Quantizes a float to a fixed point integer at `scale`, adding `shift` after scaling.
+pub fn quantize_float(elem: &f64, shift: f64, scale: Scale) -> Result<i128, TensorError> {
+    let mult = scale_to_multiplier(scale);
+    let max_value = ((i128::MAX as f64 - shift) / mult).round();
+    if elem.abs() > max_value || elem.is_nan() {
+        return Err(TensorError::Unsupported(format!(
+            "value {} is too large to be quantized at scale {}",
+            elem, scale
+        )));
+    }
+    Ok((mult * *elem + shift).round() as i128)
+}
+
+/// Converts a fixed point field element at `scale` back to a float, removing `shift` first.
+pub fn dequantize<F: PrimeField + PartialOrd>(felt: F, scale: Scale, shift: f64) -> f64 {
+    (felt_to_i128(felt) as f64 - shift) / scale_to_multiplier(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::Fr as F;
+
+    #[test]
+    fn test_conv() {
+        for x in -(2_i128.pow(15))..(2_i128.pow(15)) {
+            let fieldx: F = i128_to_felt::<F>(x);
+            let xf: i128 = felt_to_i128::<F>(fieldx);
+            assert_eq!(x, xf);
+        }
+    }
+
+    #[test]
+    fn test_quantize_roundtrip() {
+        let q = quantize_float(&-1.3, 0.0, 3).unwrap();
+        assert_eq!(q, -10);
+        assert_eq!(dequantize(i128_to_felt::<F>(q), 3, 0.0), -1.25);
+        assert!(quantize_float(&f64::MAX, 0.0, 7).is_err());
+    }
+
+    #[test]
+    fn test_felt_to_hex() {
+        let hex = felt_to_hex(F::from(255));
+        assert_eq!(hex.len(), 66);
+        assert!(hex.ends_with("00ff"));
+        assert_eq!(hex, format!("{:?}", F::from(255)));
+    }
+}
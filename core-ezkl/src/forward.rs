@@ -0,0 +1,848 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::PrimeField;
+
+use crate::fieldutils::{felt_to_i128, i128_to_felt};
+use crate::graphsettings::LookupOp;
+use crate::hybridop::HybridOp;
+use crate::model::{num_iterations, InputMapping, Model, NodeType, OutputMapping, SupportedOp};
+use crate::runargs::RunArgs;
+use crate::scalecheck::expected_scale;
+use crate::tensor::{Tensor, TensorError};
+use crate::tensorops::{concat, reduce_axes, slice};
+use crate::utils::Scale;
+
+/// An error raised while evaluating a model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ForwardError {
+    /// The number of inputs provided doesn't match the graph's inputs.
+    InputCount {
+        /// The path of the graph (empty for the top level model).
+        path: Vec<usize>,
+        /// The number of inputs the graph has.
+        expected: usize,
+        /// The number of inputs provided.
+        provided: usize,
+    },
+    /// A node reads from an outlet that hasn't been computed.
+    MissingOutlet {
+        /// The path of the reading node.
+        path: Vec<usize>,
+        /// The missing outlet.
+        outlet: (usize, usize),
+    },
+    /// The node's op cannot be evaluated.
+    Unsupported {
+        /// The path of the node.
+        path: Vec<usize>,
+        /// The name of the op.
+        op: String,
+    },
+    /// A tensor operation failed.
+    Tensor {
+        /// The path of the node.
+        path: Vec<usize>,
+        /// The underlying error.
+        error: TensorError,
+    },
+    /// The dims of a node's output differ from its stored `out_dims`.
+    ShapeMismatch {
+        /// The path of the node.
+        path: Vec<usize>,
+        /// The dims of the computed output.
+        computed: Vec<usize>,
+        /// The dims stored in the serialized model.
+        stored: Vec<usize>,
+    },
+    /// The scale of a node's output differs from its stored `out_scale`, or a subgraph's from its stored `out_scales`.
+    ScaleMismatch {
+        /// The path of the node.
+        path: Vec<usize>,
+        /// The output of the node.
+        outlet: usize,
+        /// The scale implied by the node's inputs, or by the subgraph body's output.
+        computed: Scale,
+        /// The scale stored in the serialized model.
+        stored: Scale,
+    },
+}
+
+impl fmt::Display for ForwardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForwardError::InputCount {
+                path,
+                expected,
+                provided,
+            } => write!(f, "graph {:?} has {} inputs but {} were provided", path, expected, provided),
+            ForwardError::MissingOutlet { path, outlet } => {
+                write!(f, "node {:?} reads from outlet {:?} which was not computed", path, outlet)
+            }
+            ForwardError::Unsupported { path, op } => {
+                write!(f, "node {:?}: op {} cannot be evaluated", path, op)
+            }
+            ForwardError::Tensor { path, error } => write!(f, "node {:?}: {}", path, error),
+            ForwardError::ShapeMismatch {
+                path,
+                computed,
+                stored,
+            } => write!(
+                f,
+                "node {:?}: computed output dims {:?} but out_dims is {:?}",
+                path, computed, stored
+            ),
+            ForwardError::ScaleMismatch {
+                path,
+                outlet,
+                computed,
+                stored,
+            } => write!(
+                f,
+                "node {:?}: output {} has scale {} but {} is stored",
+                path, outlet, computed, stored
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ForwardError {}
+
+/// The result of a forward pass.
+#[derive(Clone, Debug, Default)]
+pub struct ForwardResult {
+    /// The outputs of the model.
+    pub outputs: Vec<Tensor<Fp>>,
+    /// The largest input to any lookup.
+    pub max_lookup_inputs: i128,
+    /// The smallest input to any lookup.
+    pub min_lookup_inputs: i128,
+}
+
+impl ForwardResult {
+    fn record_lookup_inputs(&mut self, t: &Tensor<i128>) {
+        for x in t.iter() {
+            self.max_lookup_inputs = self.max_lookup_inputs.max(*x);
+            self.min_lookup_inputs = self.min_lookup_inputs.min(*x);
+        }
+    }
+}
+
+/// Evaluates the model over quantized inputs, checking every node's output against its stored `out_dims` and its
+/// `out_scale` against the scale implied by its inputs and the params scale of `run_args`, and every subgraph's
+/// `out_scales` against the scales of its body's outputs.
+/// Subgraphs are run as ONNX Scan/Loop bodies: `Stacked` inputs are fed one chunk per iteration, `State` inputs are
+/// replaced by the body outputs marked `is_state` after each iteration, `Single` outputs keep the last iteration's value
+/// and `Stacked` outputs are concatenated along their axis.
+pub fn forward(model: &Model, inputs: &[Tensor<Fp>], run_args: &RunArgs) -> Result<ForwardResult, ForwardError> {
+    let mut result = ForwardResult::default();
+    let outputs = forward_graph(model, inputs, run_args, &[], &mut result)?;
+    Ok(ForwardResult { outputs, ..result })
+}
+
+fn forward_graph(
+    model: &Model,
+    inputs: &[Tensor<Fp>],
+    run_args: &RunArgs,
+    prefix: &[usize],
+    result: &mut ForwardResult,
+) -> Result<Vec<Tensor<Fp>>, ForwardError> {
+    let graph = &model.graph;
+    if inputs.len() != graph.inputs.len() {
+        return Err(ForwardError::InputCount {
+            path: prefix.to_vec(),
+            expected: graph.inputs.len(),
+            provided: inputs.len(),
+        });
+    }
+
+    let mut results: BTreeMap<usize, Vec<Tensor<Fp>>> = BTreeMap::new();
+    for (idx, input) in graph.inputs.iter().zip(inputs) {
+        results.insert(*idx, vec![input.clone()]);
+    }
+
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+        if results.contains_key(idx) {
+            continue;
+        }
+        let node_inputs = node
+            .inputs()
+            .iter()
+            .map(|outlet| {
+                results
+                    .get(&outlet.0)
+                    .and_then(|r| r.get(outlet.1))
+                    .cloned()
+                    .ok_or(ForwardError::MissingOutlet {
+                        path: path.clone(),
+                        outlet: *outlet,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let outputs = match node {
+            NodeType::Node(n) => {
+                let computed = n
+                    .inputs
+                    .iter()
+                    .map(|o| graph.outlet_scale(o))
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|scales| expected_scale(&n.opkind, &scales, run_args, &mut vec![]));
+                if let Some(computed) = computed {
+                    if computed != n.out_scale {
+                        return Err(ForwardError::ScaleMismatch {
+                            path,
+                            outlet: 0,
+                            computed,
+                            stored: n.out_scale,
+                        });
+                    }
+                }
+                let mut output = eval_op(&n.opkind, &node_inputs, &path, result)?;
+                output.set_scale(n.out_scale);
+                vec![output]
+            }
+            NodeType::SubGraph {
+                model: body,
+                input_mappings,
+                output_mappings,
+                out_dims,
+                out_scales,
+                ..
+            } => {
+                let lp = Loop {
+                    body,
+                    input_mappings,
+                    output_mappings,
+                    num_outlets: out_dims.len(),
+                };
+                let outputs = run_loop(&lp, node_inputs, run_args, &path, result)?;
+                check_subgraph_scales(body, output_mappings, out_scales, &path)?;
+                outputs
+            }
+        };
+
+        for (output, stored) in outputs.iter().zip(node.out_dims()) {
+            if output.dims() != stored.as_slice() {
+                return Err(ForwardError::ShapeMismatch {
+                    path,
+                    computed: output.dims().to_vec(),
+                    stored,
+                });
+            }
+        }
+        results.insert(*idx, outputs);
+    }
+
+    graph
+        .outputs
+        .iter()
+        .map(|outlet| {
+            results
+                .get(&outlet.0)
+                .and_then(|r| r.get(outlet.1))
+                .cloned()
+                .ok_or(ForwardError::MissingOutlet {
+                    path: prefix.to_vec(),
+                    outlet: *outlet,
+                })
+        })
+        .collect()
+}
+
+/// A subgraph run as a loop.
+struct Loop<'a> {
+    body: &'a Model,
+    input_mappings: &'a [InputMapping],
+    output_mappings: &'a [Vec<OutputMapping>],
+    num_outlets: usize,
+}
+
+fn run_loop(
+    lp: &Loop,
+    mut inputs: Vec<Tensor<Fp>>,
+    run_args: &RunArgs,
+    path: &[usize],
+    result: &mut ForwardResult,
+) -> Result<Vec<Tensor<Fp>>, ForwardError> {
+    let tensor_err = |error| ForwardError::Tensor {
+        path: path.to_vec(),
+        error,
+    };
+
+    let input_dims: Vec<Vec<usize>> = inputs.iter().map(|i| i.dims().to_vec()).collect();
+    let num_iter = num_iterations(lp.input_mappings, &input_dims).map_err(tensor_err)?;
+
+    // body inputs marked as state, matched in order with the body outputs marked as state
+    let input_states: Vec<usize> = lp
+        .input_mappings
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| matches!(m, InputMapping::State))
+        .map(|(i, _)| i)
+        .collect();
+    let output_states: Vec<usize> = lp
+        .output_mappings
+        .iter()
+        .enumerate()
+        .filter(|(_, ms)| {
+            ms.iter().any(|m| match m {
+                OutputMapping::Single { is_state, .. } | OutputMapping::Stacked { is_state, .. } => *is_state,
+            })
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    // the subgraph's outputs, indexed by the outlet their output mapping targets
+    let mut outlets: Vec<Option<Tensor<Fp>>> = vec![None; lp.num_outlets];
+    for i in 0..num_iter {
+        let iter_inputs = lp
+            .input_mappings
+            .iter()
+            .zip(&inputs)
+            .map(|(mapping, input)| match mapping {
+                InputMapping::Full | InputMapping::State => Ok(input.clone()),
+                InputMapping::Stacked { axis, chunk } => {
+                    let end = ((i + 1) * chunk).min(input.dims()[*axis]);
+                    slice(input, *axis, i * chunk, end).map_err(tensor_err)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let body_outputs = forward_graph(lp.body, &iter_inputs, run_args, path, result)?;
+
+        for (mappings, output) in lp.output_mappings.iter().zip(&body_outputs) {
+            for mapping in mappings {
+                let outlet = match mapping {
+                    OutputMapping::Single { outlet, .. } | OutputMapping::Stacked { outlet, .. } => *outlet,
+                };
+                let slot = outlets.get_mut(outlet).ok_or_else(|| {
+                    tensor_err(TensorError::DimMismatch(format!(
+                        "output mapping to outlet {} of a subgraph with {} outputs",
+                        outlet, lp.num_outlets
+                    )))
+                })?;
+                *slot = Some(match (mapping, slot.take()) {
+                    (OutputMapping::Stacked { axis, .. }, Some(prev)) => {
+                        concat(&[&prev, output], *axis).map_err(tensor_err)?
+                    }
+                    _ => output.clone(),
+                });
+            }
+        }
+
+        for (input_idx, output_idx) in input_states.iter().zip(&output_states) {
+            inputs[*input_idx] = body_outputs[*output_idx].clone();
+        }
+    }
+
+    outlets
+        .into_iter()
+        .enumerate()
+        .map(|(i, output)| {
+            output.ok_or(ForwardError::MissingOutlet {
+                path: path.to_vec(),
+                outlet: (path.last().copied().unwrap_or_default(), i),
+            })
+        })
+        .collect()
+}
+
+fn check_subgraph_scales(
+    body: &Model,
+    output_mappings: &[Vec<OutputMapping>],
+    out_scales: &[Scale],
+    path: &[usize],
+) -> Result<(), ForwardError> {
+    for (mappings, body_output) in output_mappings.iter().zip(&body.graph.outputs) {
+        let computed = match body.graph.nodes.get(&body_output.0) {
+            Some(node) => match node.out_scales().get(body_output.1) {
+                Some(scale) => *scale,
+                None => continue,
+            },
+            None => continue,
+        };
+        for mapping in mappings {
+            let outlet = match mapping {
+                OutputMapping::Single { outlet, .. } | OutputMapping::Stacked { outlet, .. } => *outlet,
+            };
+            if let Some(stored) = out_scales.get(outlet) {
+                if *stored != computed {
+                    return Err(ForwardError::ScaleMismatch {
+                        path: path.to_vec(),
+                        outlet,
+                        computed,
+                        stored: *stored,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn to_ints(t: &Tensor<Fp>) -> Tensor<i128> {
+    t.map(felt_to_i128)
+}
+
+fn to_felts(t: &Tensor<i128>) -> Tensor<Fp> {
+    t.map(i128_to_felt)
+}
+
+fn eval_op(
+    op: &SupportedOp,
+    inputs: &[Tensor<Fp>],
+    path: &[usize],
+    result: &mut ForwardResult,
+) -> Result<Tensor<Fp>, ForwardError> {
+    let tensor_err = |error| ForwardError::Tensor {
+        path: path.to_vec(),
+        error,
+    };
+    let unsupported = || ForwardError::Unsupported {
+        path: path.to_vec(),
+        op: op.as_string(),
+    };
+    let first = || inputs.first().ok_or_else(unsupported);
+
+    match op {
+        SupportedOp::Input(_) => first().cloned(),
+        SupportedOp::Constant(c) => Ok(c.quantized_values.clone()),
+        SupportedOp::Unknown(_) => Err(unsupported()),
+        SupportedOp::Linear(op) => op.f(inputs).map_err(tensor_err),
+        SupportedOp::Nonlinear(op) => {
+            let x = to_ints(first()?);
+            result.record_lookup_inputs(&x);
+            Ok(to_felts(&x.map(|x| lookup_f(op, x))))
+        }
+        SupportedOp::Hybrid(op) => eval_hybrid(op, inputs, result)
+            .map_err(tensor_err)?
+            .ok_or_else(unsupported),
+        SupportedOp::Rescaled(op) => {
+            let mut inputs = inputs.to_vec();
+            for (i, mult) in &op.scale {
+                if let Some(input) = inputs.get_mut(*i) {
+                    let mult = Fp::from_u128(*mult);
+                    *input = input.map(|x| x * mult);
+                }
+            }
+            eval_op(&op.inner, &inputs, path, result)
+        }
+        SupportedOp::RebaseScale(op) => {
+            let x = to_ints(&eval_op(&op.inner, inputs, path, result)?);
+            result.record_lookup_inputs(&x);
+            let denom = op.multiplier;
+            Ok(to_felts(&x.map(|x| (x as f64 / denom).round() as i128)))
+        }
+    }
+}
+
+/// Applies a lookup op to a single fixed point value.
+pub fn lookup_f(op: &LookupOp, x: i128) -> i128 {
+    let xf = x as f64;
+    // applies `f` to the value at `scale` and requantizes the result at the same scale
+    let scaled = |scale: f32, f: fn(f64) -> f64| (f(xf / scale as f64) * scale as f64).round() as i128;
+    let flag = |b: bool| b as i128;
+    match op {
+        LookupOp::Abs => x.abs(),
+        LookupOp::Div { denom } => (xf / denom.0 as f64).round() as i128,
+        LookupOp::ReLU => x.max(0),
+        LookupOp::Max { scale, a } => x.max((a.0 as f64 * scale.0 as f64).round() as i128),
+        LookupOp::Min { scale, a } => x.min((a.0 as f64 * scale.0 as f64).round() as i128),
+        LookupOp::Ceil { scale } => scaled(scale.0, f64::ceil),
+        LookupOp::Floor { scale } => scaled(scale.0, f64::floor),
+        LookupOp::Round { scale } => scaled(scale.0, f64::round),
+        LookupOp::RoundHalfToEven { scale } => scaled(scale.0, round_half_to_even),
+        LookupOp::Sqrt { scale } => scaled(scale.0, f64::sqrt),
+        LookupOp::Rsqrt { scale } => scaled(scale.0, |x| 1.0 / x.sqrt()),
+        // the table holds `scale / x`, so that a value at scale `s` is inverted to scale `scale / s`
+        LookupOp::Recip { scale } => recip(x, scale.0 as f64),
+        LookupOp::LeakyReLU { slope } => {
+            if x < 0 {
+                (xf * slope.0 as f64).round() as i128
+            } else {
+                x
+            }
+        }
+        LookupOp::Sigmoid { scale } => scaled(scale.0, |x| 1.0 / (1.0 + (-x).exp())),
+        LookupOp::Ln { scale } => scaled(scale.0, f64::ln),
+        LookupOp::Exp { scale } => scaled(scale.0, f64::exp),
+        LookupOp::Cos { scale } => scaled(scale.0, f64::cos),
+        LookupOp::ACos { scale } => scaled(scale.0, f64::acos),
+        LookupOp::Cosh { scale } => scaled(scale.0, f64::cosh),
+        LookupOp::ACosh { scale } => scaled(scale.0, f64::acosh),
+        LookupOp::Sin { scale } => scaled(scale.0, f64::sin),
+        LookupOp::ASin { scale } => scaled(scale.0, f64::asin),
+        LookupOp::Sinh { scale } => scaled(scale.0, f64::sinh),
+        LookupOp::ASinh { scale } => scaled(scale.0, f64::asinh),
+        LookupOp::Tan { scale } => scaled(scale.0, f64::tan),
+        LookupOp::ATan { scale } => scaled(scale.0, f64::atan),
+        LookupOp::Tanh { scale } => scaled(scale.0, f64::tanh),
+        LookupOp::ATanh { scale } => scaled(scale.0, f64::atanh),
+        LookupOp::Erf { scale } => scaled(scale.0, erf),
+        LookupOp::GreaterThan { a } => flag(xf > a.0 as f64),
+        LookupOp::LessThan { a } => flag(xf < a.0 as f64),
+        LookupOp::GreaterThanEqual { a } => flag(xf >= a.0 as f64),
+        LookupOp::LessThanEqual { a } => flag(xf <= a.0 as f64),
+        LookupOp::Sign => x.signum(),
+        LookupOp::KroneckerDelta => flag(x == 0),
+        LookupOp::Pow { scale, a } => {
+            let scale = scale.0 as f64;
+            ((xf / scale).powf(a.0 as f64) * scale).round() as i128
+        }
+    }
+}
+
+/// `scale / x`, with ezkl's epsilon guarding the division by zero.
+fn recip(x: i128, scale: f64) -> i128 {
+    (scale / (x as f64 + f64::EPSILON)).round() as i128
+}
+
+fn round_half_to_even(x: f64) -> f64 {
+    let r = x.round();
+    if (x - x.trunc()).abs() == 0.5 && r % 2.0 != 0.0 {
+        r - x.signum()
+    } else {
+        r
+    }
+}
+
+/// Abramowitz and Stegun 7.1.26, accurate to 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    x.signum() * (1.0 - poly * (-x * x).exp())
+}
+
+/// Evaluates the hybrid ops that don't need a table of their own, returns `None` for the others.
+fn eval_hybrid(
+    op: &HybridOp,
+    inputs: &[Tensor<Fp>],
+    result: &mut ForwardResult,
+) -> Result<Option<Tensor<Fp>>, TensorError> {
+    let ints: Vec<Tensor<i128>> = inputs.iter().map(to_ints).collect();
+    let Some(x) = ints.first() else {
+        return Ok(None);
+    };
+    let compare = |f: fn(i128, i128) -> bool| -> Result<Option<Tensor<Fp>>, TensorError> {
+        match ints.get(1) {
+            Some(y) => Ok(Some(to_felts(&x.zip_with(y, |a, b| f(a, b) as i128)?))),
+            None => Ok(None),
+        }
+    };
+
+    let output = match op {
+        HybridOp::Div { denom, .. } => {
+            result.record_lookup_inputs(x);
+            x.map(|x| (x as f64 / denom.0 as f64).round() as i128)
+        }
+        HybridOp::Recip {
+            input_scale,
+            output_scale,
+            ..
+        } => {
+            result.record_lookup_inputs(x);
+            let scale = input_scale.0 as f64 * output_scale.0 as f64;
+            x.map(|x| recip(x, scale))
+        }
+        HybridOp::ReduceMax { axes } => reduce_axes(x, axes, i128::max)?,
+        HybridOp::ReduceMin { axes } => reduce_axes(x, axes, i128::min)?,
+        HybridOp::ReduceArgMax { dim } => arg_reduce(x, *dim, |a, b| a > b)?,
+        HybridOp::ReduceArgMin { dim } => arg_reduce(x, *dim, |a, b| a < b)?,
+        HybridOp::RangeCheck(_) => x.clone(),
+        HybridOp::Greater => return compare(|a, b| a > b),
+        HybridOp::GreaterEqual => return compare(|a, b| a >= b),
+        HybridOp::Less => return compare(|a, b| a < b),
+        HybridOp::LessEqual => return compare(|a, b| a <= b),
+        HybridOp::Equals => return compare(|a, b| a == b),
+        _ => return Ok(None),
+    };
+    Ok(Some(to_felts(&output)))
+}
+
+/// Returns the index along `dim` of the first element preferred by `better`, keeping `dim` as a dimension of size 1.
+fn arg_reduce(x: &Tensor<i128>, dim: usize, better: fn(i128, i128) -> bool) -> Result<Tensor<i128>, TensorError> {
+    let len = *x.dims().get(dim).ok_or_else(|| {
+        TensorError::AxisError(format!("cannot reduce over axis {} of a tensor of dims {:?}", dim, x.dims()))
+    })?;
+    let mut best = slice(x, dim, 0, 1)?;
+    let mut idx = best.map(|_| 0_i128);
+    for i in 1..len {
+        let candidate = slice(x, dim, i, i + 1)?;
+        for ((b, c), j) in best.iter_mut().zip(candidate.iter()).zip(idx.iter_mut()) {
+            if better(*c, *b) {
+                *b = *c;
+                *j = i as i128;
+            }
+        }
+    }
+    Ok(idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supportedop::PolyOp;
+    use crate::testutils::{felts, input, model, node, subgraph};
+    use crate::utils::F32;
+
+    fn linear(op: PolyOp<Fp>) -> SupportedOp {
+        SupportedOp::Linear(op)
+    }
+
+    /// A cumulative sum over a [4] input: the state is carried in body input 0 and the running sums are stacked. The
+    /// body's first output (the state) feeds the subgraph's second outlet.
+    fn cumsum() -> Model {
+        let body = model(
+            vec![
+                input(0, &[1], 0),
+                input(1, &[1], 0),
+                node(2, linear(PolyOp::Add), &[(0, 0), (1, 0)], &[1], 0),
+            ],
+            &[0, 1],
+            &[(2, 0), (2, 0)],
+        );
+        model(
+            vec![
+                input(0, &[1], 0),
+                input(1, &[4], 0),
+                subgraph(
+                    2,
+                    body,
+                    &[(0, 0), (1, 0)],
+                    vec![InputMapping::State, InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![
+                        vec![OutputMapping::Single {
+                            outlet: 1,
+                            is_state: true,
+                        }],
+                        vec![OutputMapping::Stacked {
+                            outlet: 0,
+                            axis: 0,
+                            is_state: false,
+                        }],
+                    ],
+                    vec![vec![4], vec![1]],
+                    vec![0, 0],
+                ),
+            ],
+            &[0, 1],
+            &[(2, 0), (2, 1)],
+        )
+    }
+
+    #[test]
+    fn recip_table() {
+        let recip = |scale: f32, x| lookup_f(&LookupOp::Recip { scale: F32(scale) }, x);
+        // 2.0 at scale 4 is inverted to 0.5 at scale 16 / 4
+        assert_eq!(recip(16.0, 8), 2);
+        assert_eq!(recip(64.0, -16), -4);
+        assert_eq!(recip(10.0, 3), 3);
+        assert!(recip(1.0, 0) > 1 << 50);
+    }
+
+    #[test]
+    fn lookups() {
+        assert_eq!(lookup_f(&LookupOp::ReLU, -3), 0);
+        assert_eq!(lookup_f(&LookupOp::Div { denom: F32(4.0) }, 10), 3);
+        assert_eq!(lookup_f(&LookupOp::Div { denom: F32(4.0) }, -10), -3);
+        assert_eq!(lookup_f(&LookupOp::LeakyReLU { slope: F32(0.5) }, -5), -3);
+        assert_eq!(lookup_f(&LookupOp::Sign, -5), -1);
+        assert_eq!(lookup_f(&LookupOp::Sqrt { scale: F32(4.0) }, 16), 8);
+        assert_eq!(lookup_f(&LookupOp::RoundHalfToEven { scale: F32(2.0) }, 5), 4);
+        assert_eq!(lookup_f(&LookupOp::GreaterThan { a: F32(1.0) }, 2), 1);
+    }
+
+    #[test]
+    fn hybrid_recip_matches_its_table() {
+        let op = HybridOp::Recip {
+            input_scale: F32(4.0),
+            output_scale: F32(8.0),
+            use_range_check_for_int: false,
+        };
+        let x = felts(&[1, 2, -8, 5], &[4]);
+        let out = eval_hybrid(&op, &[x], &mut ForwardResult::default()).unwrap().unwrap();
+        let table: Vec<Fp> = [1, 2, -8, 5]
+            .iter()
+            .map(|x| i128_to_felt(lookup_f(&LookupOp::Recip { scale: F32(32.0) }, *x)))
+            .collect();
+        assert_eq!(out.iter().copied().collect::<Vec<_>>(), table);
+        assert_eq!(out, felts(&[32, 16, -4, 6], &[4]));
+    }
+
+    #[test]
+    fn scan_with_stacked_input_and_output() {
+        let body = model(
+            vec![
+                input(0, &[1, 2], 0),
+                node(1, linear(PolyOp::Sum { axes: vec![1] }), &[(0, 0)], &[1, 1], 0),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[3, 2], 0),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![vec![OutputMapping::Stacked {
+                        outlet: 0,
+                        axis: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![3, 1]],
+                    vec![0],
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let result = forward(&m, &[felts(&[1, 2, 3, 4, 5, -6], &[3, 2])], &RunArgs::default()).unwrap();
+        assert_eq!(result.outputs, vec![felts(&[3, 7, -1], &[3, 1])]);
+    }
+
+    #[test]
+    fn scan_with_state_orders_outputs_by_mapping() {
+        let inputs = [felts(&[0], &[1]), felts(&[1, 2, 3, 4], &[4])];
+        let result = forward(&cumsum(), &inputs, &RunArgs::default()).unwrap();
+        assert_eq!(result.outputs, vec![felts(&[1, 3, 6, 10], &[4]), felts(&[10], &[1])]);
+    }
+
+    #[test]
+    fn scan_with_unmapped_outlet() {
+        let mut m = cumsum();
+        if let Some(NodeType::SubGraph { out_dims, .. }) = m.graph.nodes.get_mut(&2) {
+            out_dims.push(vec![1]);
+        }
+        let inputs = [felts(&[0], &[1]), felts(&[1, 2, 3, 4], &[4])];
+        assert_eq!(
+            forward(&m, &inputs, &RunArgs::default()).unwrap_err(),
+            ForwardError::MissingOutlet {
+                path: vec![2],
+                outlet: (2, 2),
+            }
+        );
+    }
+
+    #[test]
+    fn lookup_inputs_are_recorded_across_iterations() {
+        let body = model(
+            vec![input(0, &[1], 0), node(1, SupportedOp::Nonlinear(LookupOp::ReLU), &[(0, 0)], &[1], 0)],
+            &[0],
+            &[(1, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[4], 0),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![vec![OutputMapping::Stacked {
+                        outlet: 0,
+                        axis: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![4]],
+                    vec![0],
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let result = forward(&m, &[felts(&[-3, 5, 2, -7], &[4])], &RunArgs::default()).unwrap();
+        assert_eq!(result.outputs, vec![felts(&[0, 5, 2, 0], &[4])]);
+        assert_eq!((result.min_lookup_inputs, result.max_lookup_inputs), (-7, 5));
+    }
+
+    #[test]
+    fn subgraph_scale_mismatch() {
+        let mut m = cumsum();
+        if let Some(NodeType::SubGraph { out_scales, .. }) = m.graph.nodes.get_mut(&2) {
+            out_scales[1] = 1;
+        }
+        let inputs = [felts(&[0], &[1]), felts(&[1, 2, 3, 4], &[4])];
+        assert_eq!(
+            forward(&m, &inputs, &RunArgs::default()).unwrap_err(),
+            ForwardError::ScaleMismatch {
+                path: vec![2],
+                outlet: 1,
+                computed: 0,
+                stored: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn input_count_and_unsupported_ops() {
+        assert_eq!(
+            forward(&cumsum(), &[felts(&[0], &[1])], &RunArgs::default()).unwrap_err(),
+            ForwardError::InputCount {
+                path: vec![],
+                expected: 2,
+                provided: 1,
+            }
+        );
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, SupportedOp::Unknown(Default::default()), &[(0, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        assert_eq!(
+            forward(&m, &[felts(&[1, 2], &[2])], &RunArgs::default()).unwrap_err(),
+            ForwardError::Unsupported {
+                path: vec![1],
+                op: "UNKNOWN".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn node_scale_mismatch() {
+        let m = model(
+            vec![
+                input(0, &[2], 1),
+                input(1, &[2], 1),
+                node(2, linear(PolyOp::Mult), &[(0, 0), (1, 0)], &[2], 1),
+            ],
+            &[0, 1],
+            &[(2, 0)],
+        );
+        let inputs = [felts(&[1, 2], &[2]), felts(&[3, 4], &[2])];
+        assert_eq!(
+            forward(&m, &inputs, &RunArgs::default()).unwrap_err(),
+            ForwardError::ScaleMismatch {
+                path: vec![2],
+                outlet: 0,
+                computed: 2,
+                stored: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn shape_mismatch() {
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, linear(PolyOp::Reshape(vec![1, 2])), &[(0, 0)], &[2, 1], 0),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        assert!(matches!(
+            forward(&m, &[felts(&[1, 2], &[2])], &RunArgs::default()),
+            Err(ForwardError::ShapeMismatch { .. })
+        ));
+    }
+}
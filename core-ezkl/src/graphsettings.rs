@@ -1,7 +1,19 @@
-use utils::{F32, Scale};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::runargs::RunArgs;
+use crate::schema::{self, SchemaTarget};
+use crate::utils::{self, Scale};
 
 /// model parameters
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct GraphSettings {
     /// run args
     pub run_args: RunArgs,
@@ -30,22 +42,333 @@ pub struct GraphSettings {
     /// unix time timestamp
     pub timestamp: Option<u128>,
 }
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
-///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+/// The rows and instances used by the hashing and commitment modules
 pub struct ModuleSizes {
-    kzg: Vec<usize>,
-    poseidon: (usize, Vec<usize>),
-} 
+    /// the number of rows used by each kzg commitment
+    pub kzg: Vec<usize>,
+    /// the number of rows used by poseidon hashing, and the number of instances it exposes
+    pub poseidon: (usize, Vec<usize>),
+    /// the number of rows used by elgamal encryption, and the number of instances it exposes
+    #[serde(default)]
+    pub elgamal: (usize, Vec<usize>),
+}
 
+/// Whether the extra consistency checks of the circuit are run
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum CheckMode {
+    /// run the checks
     #[default]
     SAFE,
+    /// skip the checks
     UNSAFE,
 }
 
+/// The newest ezkl release whose settings layout is understood, later patch releases share its layout.
+pub const LATEST_SETTINGS_VERSION: SettingsVersion = release(5, 0, 8);
+
+/// The version of ezkl that produced a settings file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SettingsVersion {
+    /// major version
+    pub major: u64,
+    /// minor version
+    pub minor: u64,
+    /// patch version
+    pub patch: u64,
+}
+
+impl fmt::Display for SettingsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for SettingsVersion {
+    type Err = SettingsError;
+
+    /// Parses `major.minor.patch`, ignoring a leading `v` and any pre-release or build suffix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SettingsError::InvalidVersion(s.to_string());
+        let core = s.trim().trim_start_matches('v');
+        let core = core.split(['-', '+']).next().unwrap_or_default();
+        let parts = core
+            .split('.')
+            .map(|p| p.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parts[..] {
+            [major, minor, patch] => Ok(SettingsVersion { major, minor, patch }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Why a settings file could not be loaded.
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The file is not valid JSON or doesn't match the settings layout.
+    Json(serde_json::Error),
+    /// The file doesn't say which release of ezkl produced it.
+    MissingVersion,
+    /// The version is not of the form `major.minor.patch`.
+    InvalidVersion(String),
+    /// The file was produced by a release newer than [LATEST_SETTINGS_VERSION].
+    UnsupportedVersion {
+        /// the version of the file
+        found: SettingsVersion,
+        /// the newest version understood
+        latest: SettingsVersion,
+    },
+    /// The file has unknown or missing keys, see [KeyIssue::is_error].
+    Keys(Vec<KeyIssue>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Json(e) => write!(f, "invalid settings: {}", e),
+            SettingsError::MissingVersion => write!(f, "the settings don't declare the ezkl version that produced them"),
+            SettingsError::InvalidVersion(v) => write!(f, "invalid settings version {:?}", v),
+            SettingsError::UnsupportedVersion { found, latest } => write!(
+                f,
+                "the settings were produced by ezkl {} but only releases up to {}.{}.x are supported",
+                found, latest.major, latest.minor
+            ),
+            SettingsError::Keys(issues) => {
+                write!(f, "invalid settings keys:")?;
+                for issue in issues {
+                    write!(f, "\n  {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+impl From<serde_json::Error> for SettingsError {
+    fn from(e: serde_json::Error) -> Self {
+        SettingsError::Json(e)
+    }
+}
+
+/// A field that was missing from a settings file and filled in while migrating it to the current layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    /// the path of the field, eg. `module_sizes.elgamal`
+    pub path: String,
+    /// the value it was given
+    pub value: Value,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} set to {}", self.path, self.value)
+    }
+}
+
+/// Shorthand for a [SettingsVersion] literal.
+const fn release(major: u64, minor: u64, patch: u64) -> SettingsVersion {
+    SettingsVersion { major, minor, patch }
+}
+
+/// The fields older releases didn't write, with the release that introduced them and the value that reproduces the
+/// behaviour of the releases before it. `timestamp` isn't written by any supported release and is left to serde's
+/// default like every other optional key.
+fn migrations() -> Vec<(SettingsVersion, &'static [&'static str], Value)> {
+    vec![
+        (release(3, 0, 0), &["check_mode"], json!("SAFE")),
+        (release(3, 0, 0), &["module_sizes", "elgamal"], json!([0, [0]])),
+        (release(4, 0, 0), &["run_args", "scale_rebase_multiplier"], json!(1)),
+        (release(5, 0, 0), &["run_args", "num_inner_cols"], json!(1)),
+        (release(5, 0, 0), &["num_blinding_factors"], Value::Null),
+    ]
+}
+
+/// Detects the release that produced a settings file, rejects releases newer than [LATEST_SETTINGS_VERSION] and fills
+/// in the fields that were introduced after it. Returns the fields that were filled in, keys missing from a release
+/// that should have written them are left for deserialization to report.
+pub fn migrate(settings: &mut Value) -> Result<Vec<Migration>, SettingsError> {
+    let version = settings
+        .get("version")
+        .ok_or(SettingsError::MissingVersion)?
+        .as_str()
+        .ok_or_else(|| SettingsError::InvalidVersion(settings["version"].to_string()))?
+        .parse::<SettingsVersion>()?;
+    let latest = LATEST_SETTINGS_VERSION;
+    if (version.major, version.minor) > (latest.major, latest.minor) {
+        return Err(SettingsError::UnsupportedVersion { found: version, latest });
+    }
+
+    let mut applied = vec![];
+    for (introduced, path, value) in migrations() {
+        if version >= introduced {
+            continue;
+        }
+        let (key, parents) = match path.split_last() {
+            Some(split) => split,
+            None => continue,
+        };
+        // a missing parent is left for deserialization to report
+        let parent = parents
+            .iter()
+            .try_fold(&mut *settings, |v, k| v.get_mut(*k))
+            .and_then(|v| v.as_object_mut());
+        if let Some(parent) = parent {
+            if !parent.contains_key(*key) {
+                parent.insert(key.to_string(), value.clone());
+                applied.push(Migration {
+                    path: path.join("."),
+                    value,
+                });
+            }
+        }
+    }
+    Ok(applied)
+}
+
+/// How strictly the keys of a settings file are checked against the current layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ParseMode {
+    /// The file as written, before any migration, must match the current layout: unknown keys and missing keys
+    /// without a default are errors.
+    #[default]
+    Strict,
+    /// Older layouts are migrated first and unknown keys are reported as warnings, missing keys without a default are
+    /// still errors.
+    Lenient,
+}
+
+/// A key of a settings file that doesn't match the current layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyIssue {
+    /// A key that isn't part of the layout, eg. `run_args.foo`.
+    Unknown(String),
+    /// A key of the layout without a default that the file doesn't have, eg. `run_args.logrows`.
+    Missing(String),
+    /// A key of the layout with a default that the file doesn't have, eg. `timestamp`.
+    Defaulted(String),
+}
+
+impl fmt::Display for KeyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyIssue::Unknown(path) => write!(f, "unknown key {}", path),
+            KeyIssue::Missing(path) => write!(f, "missing key {}", path),
+            KeyIssue::Defaulted(path) => write!(f, "missing key {} takes its default value", path),
+        }
+    }
+}
+
+impl KeyIssue {
+    /// Whether the issue stops the settings from loading in the given mode.
+    pub fn is_error(&self, mode: ParseMode) -> bool {
+        match self {
+            KeyIssue::Unknown(_) => mode == ParseMode::Strict,
+            KeyIssue::Missing(_) => true,
+            KeyIssue::Defaulted(_) => false,
+        }
+    }
+}
+
+/// Settings parsed with a [ParseMode], along with how the file was brought to the current layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedSettings {
+    /// the settings
+    pub settings: GraphSettings,
+    /// the fields filled in by migrating an older layout
+    pub migrations: Vec<Migration>,
+    /// the key issues that didn't stop the settings from loading
+    pub warnings: Vec<KeyIssue>,
+}
+
+/// Follows a `$ref` to `#/definitions/..`, possibly wrapped in a single `allOf`, to the schema it points to.
+fn resolve<'a>(schema: &'a Value, definitions: &'a Value) -> &'a Value {
+    if let Some(name) = schema["$ref"].as_str().and_then(|r| r.strip_prefix("#/definitions/")) {
+        return resolve(&definitions[name], definitions);
+    }
+    match schema["allOf"].as_array().map(|all| &all[..]) {
+        Some([inner]) => resolve(inner, definitions),
+        _ => schema,
+    }
+}
+
+/// Compares the keys of `found` against the properties of the object `schema`, recursing into the objects both share.
+/// Keys the schema doesn't require are those serde gives a default to.
+fn check_keys(found: &Value, schema: &Value, definitions: &Value, path: &str, issues: &mut Vec<KeyIssue>) {
+    let schema = resolve(schema, definitions);
+    let (found, properties) = match (found.as_object(), schema["properties"].as_object()) {
+        (Some(found), Some(properties)) => (found, properties),
+        _ => return,
+    };
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    for key in found.keys() {
+        if !properties.contains_key(key) {
+            issues.push(KeyIssue::Unknown(join(key)));
+        }
+    }
+    for (key, property) in properties {
+        match found.get(key) {
+            Some(value) => check_keys(value, property, definitions, &join(key), issues),
+            None if required.contains(&key.as_str()) => issues.push(KeyIssue::Missing(join(key))),
+            None => issues.push(KeyIssue::Defaulted(join(key))),
+        }
+    }
+}
+
+impl GraphSettings {
+    /// Parses settings with the given [ParseMode]. Keys are checked against the settings' JSON schema, on the file as
+    /// written in [ParseMode::Strict] and once older layouts are migrated as in [GraphSettings::from_json] in
+    /// [ParseMode::Lenient].
+    pub fn parse(json: &str, mode: ParseMode) -> Result<ParsedSettings, SettingsError> {
+        let raw: Value = serde_json::from_str(json)?;
+        let mut settings = raw.clone();
+        let migrations = migrate(&mut settings)?;
+        let schema = serde_json::to_value(schema::schema(SchemaTarget::Settings))?;
+        let checked = match mode {
+            ParseMode::Strict => &raw,
+            ParseMode::Lenient => &settings,
+        };
+        let mut issues = vec![];
+        check_keys(checked, &schema, &schema["definitions"], "", &mut issues);
+        let (errors, warnings): (Vec<_>, Vec<_>) = issues.into_iter().partition(|issue| issue.is_error(mode));
+        if !errors.is_empty() {
+            return Err(SettingsError::Keys(errors));
+        }
+        Ok(ParsedSettings {
+            settings: serde_json::from_value(settings)?,
+            migrations,
+            warnings,
+        })
+    }
+
+    /// Parses settings produced by any ezkl release up to [LATEST_SETTINGS_VERSION], migrating older layouts.
+    pub fn from_json(json: &str) -> Result<Self, SettingsError> {
+        let mut settings: Value = serde_json::from_str(json)?;
+        migrate(&mut settings)?;
+        Ok(serde_json::from_value(settings)?)
+    }
+
+    /// Loads settings from a JSON file, see [GraphSettings::from_json].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(Self::from_json(&json)?)
+    }
+
+    /// Returns the release of ezkl that produced the settings.
+    pub fn parsed_version(&self) -> Result<SettingsVersion, SettingsError> {
+        self.version.parse()
+    }
+}
+
 #[allow(missing_docs)]
 /// An enum representing the operations that can be used to express more complex operations via accumulation
-#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize, JsonSchema)]
 pub enum LookupOp {
     Abs,
     Div { denom: utils::F32 },
@@ -83,4 +406,165 @@ pub enum LookupOp {
     Sign,
     KroneckerDelta,
     Pow { scale: utils::F32, a: utils::F32 },
-}
\ No newline at end of file
+}
+
+impl LookupOp {
+    /// Returns the name of the operation.
+    pub fn as_string(&self) -> String {
+        match self {
+            LookupOp::Abs => "ABS",
+            LookupOp::Div { .. } => "DIV",
+            LookupOp::ReLU => "RELU",
+            LookupOp::Max { .. } => "MAX",
+            LookupOp::Min { .. } => "MIN",
+            LookupOp::Ceil { .. } => "CEIL",
+            LookupOp::Floor { .. } => "FLOOR",
+            LookupOp::Round { .. } => "ROUND",
+            LookupOp::RoundHalfToEven { .. } => "ROUNDHALFTOEVEN",
+            LookupOp::Sqrt { .. } => "SQRT",
+            LookupOp::Rsqrt { .. } => "RSQRT",
+            LookupOp::Recip { .. } => "RECIP",
+            LookupOp::LeakyReLU { .. } => "LEAKYRELU",
+            LookupOp::Sigmoid { .. } => "SIGMOID",
+            LookupOp::Ln { .. } => "LN",
+            LookupOp::Exp { .. } => "EXP",
+            LookupOp::Cos { .. } => "COS",
+            LookupOp::ACos { .. } => "ACOS",
+            LookupOp::Cosh { .. } => "COSH",
+            LookupOp::ACosh { .. } => "ACOSH",
+            LookupOp::Sin { .. } => "SIN",
+            LookupOp::ASin { .. } => "ASIN",
+            LookupOp::Sinh { .. } => "SINH",
+            LookupOp::ASinh { .. } => "ASINH",
+            LookupOp::Tan { .. } => "TAN",
+            LookupOp::ATan { .. } => "ATAN",
+            LookupOp::Tanh { .. } => "TANH",
+            LookupOp::ATanh { .. } => "ATANH",
+            LookupOp::Erf { .. } => "ERF",
+            LookupOp::GreaterThan { .. } => "GREATERTHAN",
+            LookupOp::LessThan { .. } => "LESSTHAN",
+            LookupOp::GreaterThanEqual { .. } => "GREATERTHANEQUAL",
+            LookupOp::LessThanEqual { .. } => "LESSTHANEQUAL",
+            LookupOp::Sign => "SIGN",
+            LookupOp::KroneckerDelta => "KRONECKERDELTA",
+            LookupOp::Pow { .. } => "POW",
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS_JSON: &str = include_str!("../../settings.json");
+
+    #[test]
+    fn parses_bundled_settings() {
+        let settings = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        assert_eq!(settings.run_args.logrows, 10);
+        assert_eq!(settings.check_mode, CheckMode::UNSAFE);
+        assert_eq!(settings.parsed_version().unwrap(), LATEST_SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn migrates_older_layouts() {
+        let mut settings: Value = serde_json::from_str(SETTINGS_JSON).unwrap();
+        settings["version"] = json!("2.5.0");
+        settings["module_sizes"].as_object_mut().unwrap().remove("elgamal");
+        settings.as_object_mut().unwrap().remove("num_blinding_factors");
+        let applied = migrate(&mut settings).unwrap();
+        let paths: Vec<&str> = applied.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["module_sizes.elgamal", "num_blinding_factors"]);
+
+        let settings: GraphSettings = serde_json::from_value(settings).unwrap();
+        assert_eq!(settings.module_sizes.elgamal, (0, vec![0]));
+        assert_eq!(settings.num_blinding_factors, None);
+        assert_eq!(settings.timestamp, None);
+    }
+
+    #[test]
+    fn migrations_only_apply_to_older_releases() {
+        let mut settings: Value = serde_json::from_str(SETTINGS_JSON).unwrap();
+        assert_eq!(migrate(&mut settings).unwrap(), vec![]);
+        assert_eq!(GraphSettings::parse(SETTINGS_JSON, ParseMode::Lenient).unwrap().migrations, vec![]);
+
+        // check_mode was introduced in 3.0.0, so a 4.x file without it is broken rather than old
+        settings["version"] = json!("4.1.0");
+        settings.as_object_mut().unwrap().remove("check_mode");
+        settings["run_args"].as_object_mut().unwrap().remove("num_inner_cols");
+        let applied = migrate(&mut settings).unwrap();
+        let paths: Vec<&str> = applied.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["run_args.num_inner_cols"]);
+        assert!(serde_json::from_value::<GraphSettings>(settings.clone()).is_err());
+
+        settings["version"] = json!(LATEST_SETTINGS_VERSION.to_string());
+        match GraphSettings::parse(&settings.to_string(), ParseMode::Lenient) {
+            Err(SettingsError::Keys(found)) => assert_eq!(found, vec![KeyIssue::Missing("check_mode".to_string())]),
+            other => panic!("expected a missing check_mode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_newer_and_invalid_versions() {
+        let mut settings: Value = serde_json::from_str(SETTINGS_JSON).unwrap();
+        settings["version"] = json!("5.1.0");
+        assert!(matches!(migrate(&mut settings), Err(SettingsError::UnsupportedVersion { .. })));
+        settings["version"] = json!("5.0.9");
+        assert!(migrate(&mut settings).is_ok());
+        settings["version"] = json!("five");
+        assert!(matches!(migrate(&mut settings), Err(SettingsError::InvalidVersion(_))));
+        settings.as_object_mut().unwrap().remove("version");
+        assert!(matches!(migrate(&mut settings), Err(SettingsError::MissingVersion)));
+    }
+
+    #[test]
+    fn strict_and_lenient_modes() {
+        let mut settings: Value = serde_json::from_str(SETTINGS_JSON).unwrap();
+        settings["run_args"]["foo"] = json!(1);
+        let unknown = settings.to_string();
+        settings["run_args"].as_object_mut().unwrap().remove("logrows");
+        let missing = settings.to_string();
+        let timestamp = KeyIssue::Defaulted("timestamp".to_string());
+
+        match GraphSettings::parse(&missing, ParseMode::Strict) {
+            Err(SettingsError::Keys(found)) => assert_eq!(
+                found,
+                vec![
+                    KeyIssue::Unknown("run_args.foo".to_string()),
+                    KeyIssue::Missing("run_args.logrows".to_string()),
+                ]
+            ),
+            other => panic!("expected key issues, got {:?}", other),
+        }
+        match GraphSettings::parse(&missing, ParseMode::Lenient) {
+            Err(SettingsError::Keys(found)) => assert_eq!(found, vec![KeyIssue::Missing("run_args.logrows".to_string())]),
+            other => panic!("expected a missing logrows, got {:?}", other),
+        }
+
+        assert!(GraphSettings::parse(&unknown, ParseMode::Strict).is_err());
+        let parsed = GraphSettings::parse(&unknown, ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.warnings, vec![KeyIssue::Unknown("run_args.foo".to_string()), timestamp.clone()]);
+        assert_eq!(parsed.settings.run_args.logrows, 10);
+
+        let parsed = GraphSettings::parse(SETTINGS_JSON, ParseMode::Strict).unwrap();
+        assert_eq!(parsed.warnings, vec![timestamp]);
+        assert_eq!(ParseMode::default(), ParseMode::Strict);
+    }
+
+    #[test]
+    fn strict_mode_checks_the_file_before_migrating() {
+        let mut settings: Value = serde_json::from_str(SETTINGS_JSON).unwrap();
+        settings["version"] = json!("2.5.0");
+        settings.as_object_mut().unwrap().remove("check_mode");
+        let json = settings.to_string();
+
+        match GraphSettings::parse(&json, ParseMode::Strict) {
+            Err(SettingsError::Keys(found)) => assert_eq!(found, vec![KeyIssue::Missing("check_mode".to_string())]),
+            other => panic!("expected a missing check_mode, got {:?}", other),
+        }
+        let parsed = GraphSettings::parse(&json, ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.settings.check_mode, CheckMode::SAFE);
+        assert!(parsed.migrations.iter().any(|m| m.path == "check_mode"));
+    }
+}
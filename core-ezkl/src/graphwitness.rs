@@ -1,12 +1,29 @@
-use halo2curves::bn256::{Bn256, Fr as Fp, G1Affine};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+use halo2curves::bn256::{Fr as Fp, G1Affine};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::elgamal::ElGamalResult;
+use crate::fieldutils::{dequantize, felt_in_i128_range, felt_to_hex, felt_to_i128, quantize_float};
+use crate::graphsettings::GraphSettings;
+use crate::instances::{InstanceKind, InstanceLayout};
+use crate::schema::{FeltSchema, G1AffineSchema};
+use crate::snark::Snark;
+use crate::utils::Scale;
+
+/// The result of a forward pass of the model, as produced by `ezkl gen-witness`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct GraphWitness {
     /// The inputs of the forward pass
+    #[schemars(with = "Vec<Vec<FeltSchema>>")]
     pub inputs: Vec<Vec<Fp>>,
     /// The prettified outputs of the forward pass, we use a String to maximize compatibility with Python and JS clients
     pub pretty_elements: Option<PrettyElements>,
     /// The output of the forward pass
+    #[schemars(with = "Vec<Vec<FeltSchema>>")]
     pub outputs: Vec<Vec<Fp>>,
     /// Any hashes of inputs generated during the forward pass
     pub processed_inputs: Option<ModuleForwardResult>,
@@ -21,15 +38,20 @@ pub struct GraphWitness {
 }
 
 /// Result from a forward pass
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct ModuleForwardResult {
     /// The inputs of the forward pass for poseidon
+    #[schemars(with = "Option<Vec<FeltSchema>>")]
     pub poseidon_hash: Option<Vec<Fp>>,
     /// The outputs of the forward pass for KZG
+    #[schemars(with = "Option<Vec<Vec<G1AffineSchema>>>")]
     pub kzg_commit: Option<Vec<Vec<G1Affine>>>,
+    /// The ciphertexts of the forward pass for ElGamal
+    #[serde(default)]
+    pub elgamal: Option<ElGamalResult>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, JsonSchema)]
 /// Contains the instances of the circuit in human readable form
 pub struct PrettyElements {
     /// the inputs as rescaled floats -- represented as a String for maximum compatibility with Python and JS
@@ -46,4 +68,441 @@ pub struct PrettyElements {
     pub rescaled_outputs: Vec<Vec<String>>,
     /// the outputs as felts but 0x strings (if any) -- represented as a String for maximum compatibility with Python and JS
     pub outputs: Vec<Vec<String>>,
-}
\ No newline at end of file
+}
+/// A problem found while validating a witness.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WitnessIssue {
+    /// The public inputs and outputs don't match `model_instance_shapes`.
+    InstanceShapes(String),
+    /// A felt doesn't represent a signed integer of at most 127 bits.
+    FeltOutOfRange {
+        /// The tensor holding the felt, eg. `output 0`.
+        tensor: String,
+        /// The index of the felt within the flattened tensor.
+        index: usize,
+    },
+    /// The lookup inputs seen during the forward pass fall outside of `lookup_range`.
+    LookupRange {
+        /// The smallest lookup input.
+        min: i128,
+        /// The largest lookup input.
+        max: i128,
+        /// The range of the lookup tables.
+        range: (i128, i128),
+    },
+    /// `pretty_elements` disagrees with the raw felts.
+    PrettyElements(String),
+    /// The instance layout can't be built from the settings, so the outputs can't be found in the proof.
+    InstanceLayout(String),
+    /// A public output differs from the corresponding instance of the proof.
+    InstanceMismatch {
+        /// The index of the output.
+        output: usize,
+        /// The index of the felt within the flattened output.
+        index: usize,
+    },
+}
+
+/// The outcome of validating a witness.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WitnessReport {
+    /// The issues found.
+    pub issues: Vec<WitnessIssue>,
+}
+
+impl WitnessReport {
+    /// Returns true if no issue was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for WitnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return writeln!(f, "witness is valid");
+        }
+        for issue in &self.issues {
+            match issue {
+                WitnessIssue::InstanceShapes(msg) => writeln!(f, "instance shapes: {}", msg)?,
+                WitnessIssue::FeltOutOfRange { tensor, index } => {
+                    writeln!(f, "{} element {} is out of the i128 range", tensor, index)?
+                }
+                WitnessIssue::LookupRange { min, max, range } => writeln!(
+                    f,
+                    "lookup inputs span ({}, {}) which exceeds lookup_range {:?}",
+                    min, max, range
+                )?,
+                WitnessIssue::PrettyElements(msg) => writeln!(f, "pretty elements: {}", msg)?,
+                WitnessIssue::InstanceLayout(msg) => writeln!(f, "instance layout: {}", msg)?,
+                WitnessIssue::InstanceMismatch { output, index } => {
+                    writeln!(f, "output {} element {} differs from the proof's instance", output, index)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GraphWitness {
+    /// Loads a witness from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Checks the witness against the settings it was generated with and, if provided, the proof generated from it.
+    pub fn validate(&self, settings: &GraphSettings, snark: Option<&Snark<Fp>>) -> WitnessReport {
+        let mut report = WitnessReport::default();
+        let run_args = &settings.run_args;
+
+        // public inputs then public outputs make up model_instance_shapes
+        let mut public: Vec<(String, &Vec<Fp>)> = vec![];
+        if run_args.input_visibility.is_public() {
+            public.extend(self.inputs.iter().enumerate().map(|(i, t)| (format!("input {}", i), t)));
+        }
+        if run_args.output_visibility.is_public() {
+            public.extend(self.outputs.iter().enumerate().map(|(i, t)| (format!("output {}", i), t)));
+        }
+        if public.len() != settings.model_instance_shapes.len() {
+            report.issues.push(WitnessIssue::InstanceShapes(format!(
+                "{} public tensors but {} instance shapes",
+                public.len(),
+                settings.model_instance_shapes.len()
+            )));
+        }
+        for ((name, tensor), shape) in public.iter().zip(&settings.model_instance_shapes) {
+            let len: usize = shape.iter().product();
+            if tensor.len() != len {
+                report.issues.push(WitnessIssue::InstanceShapes(format!(
+                    "{} has {} elements but its instance shape {:?} holds {}",
+                    name,
+                    tensor.len(),
+                    shape,
+                    len
+                )));
+            }
+        }
+
+        let tensors = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (format!("input {}", i), t))
+            .chain(self.outputs.iter().enumerate().map(|(i, t)| (format!("output {}", i), t)));
+        for (name, tensor) in tensors {
+            if let Some(index) = tensor.iter().position(|x| !felt_in_i128_range(*x)) {
+                report.issues.push(WitnessIssue::FeltOutOfRange { tensor: name, index });
+            }
+        }
+
+        let range = run_args.lookup_range;
+        if self.min_lookup_inputs < range.0 || self.max_lookup_inputs > range.1 {
+            report.issues.push(WitnessIssue::LookupRange {
+                min: self.min_lookup_inputs,
+                max: self.max_lookup_inputs,
+                range,
+            });
+        }
+
+        if let Some(pretty) = &self.pretty_elements {
+            self.check_pretty_elements(pretty, settings, &mut report);
+        }
+
+        if let Some(snark) = snark {
+            self.check_instances(settings, snark, &mut report);
+        }
+        report
+    }
+
+    fn check_pretty_elements(&self, pretty: &PrettyElements, settings: &GraphSettings, report: &mut WitnessReport) {
+        let mut check = |name: &str, felts: &[Vec<Fp>], pretty: &[Vec<String>], scales: Option<&[Scale]>| {
+            if felts.len() != pretty.len() {
+                report.issues.push(WitnessIssue::PrettyElements(format!(
+                    "{} holds {} tensors but the witness has {}",
+                    name,
+                    pretty.len(),
+                    felts.len()
+                )));
+                return;
+            }
+            for (i, (felts, pretty)) in felts.iter().zip(pretty).enumerate() {
+                if felts.len() != pretty.len() {
+                    report.issues.push(WitnessIssue::PrettyElements(format!(
+                        "{} {} holds {} elements but the witness has {}",
+                        name,
+                        i,
+                        pretty.len(),
+                        felts.len()
+                    )));
+                    continue;
+                }
+                let scale = scales.map(|s| s.get(i).copied().unwrap_or(0));
+                let mismatch = felts.iter().zip(pretty).position(|(felt, s)| match scale {
+                    // requantize rather than compare floats, so that the formatting of the floats doesn't matter
+                    Some(scale) => s
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|x| quantize_float(&x, 0.0, scale).ok())
+                        .map_or(true, |x| x != felt_to_i128(*felt)),
+                    None => !s.eq_ignore_ascii_case(&felt_to_hex(*felt)),
+                });
+                if let Some(index) = mismatch {
+                    report.issues.push(WitnessIssue::PrettyElements(format!(
+                        "{} {} element {} disagrees with the witness",
+                        name, i, index
+                    )));
+                }
+            }
+        };
+
+        check("inputs", &self.inputs, &pretty.inputs, None);
+        check("outputs", &self.outputs, &pretty.outputs, None);
+        check(
+            "rescaled_inputs",
+            &self.inputs,
+            &pretty.rescaled_inputs,
+            Some(settings.model_input_scales.as_slice()),
+        );
+        check(
+            "rescaled_outputs",
+            &self.outputs,
+            &pretty.rescaled_outputs,
+            Some(settings.model_output_scales.as_slice()),
+        );
+        let hashes = |processed: &Option<ModuleForwardResult>| -> Vec<Vec<Fp>> {
+            processed
+                .as_ref()
+                .and_then(|p| p.poseidon_hash.clone())
+                .map(|h| vec![h])
+                .unwrap_or_default()
+        };
+        check("processed_inputs", &hashes(&self.processed_inputs), &pretty.processed_inputs, None);
+        check("processed_params", &hashes(&self.processed_params), &pretty.processed_params, None);
+        check("processed_outputs", &hashes(&self.processed_outputs), &pretty.processed_outputs, None);
+    }
+
+    fn check_instances(&self, settings: &GraphSettings, snark: &Snark<Fp>, report: &mut WitnessReport) {
+        let layout = match InstanceLayout::from_settings(settings) {
+            Ok(layout) => layout,
+            Err(e) => {
+                report.issues.push(WitnessIssue::InstanceLayout(e.to_string()));
+                return;
+            }
+        };
+        let instances: &[Fp] = snark.instances.first().map(|i| i.as_slice()).unwrap_or(&[]);
+        for (output, (tensor, entry)) in self.outputs.iter().zip(layout.of_kind(InstanceKind::Output)).enumerate() {
+            let offset = entry.offset;
+            if let Some(index) = (0..tensor.len()).find(|i| instances.get(offset + i) != Some(&tensor[*i])) {
+                report.issues.push(WitnessIssue::InstanceMismatch { output, index });
+            }
+        }
+    }
+}
+
+impl PrettyElements {
+    /// Prettifies the felts of a witness, rescaling inputs and outputs with the scales in `settings`.
+    pub fn from_witness(witness: &GraphWitness, settings: &GraphSettings) -> Self {
+        let hashes = |processed: &Option<ModuleForwardResult>| {
+            processed
+                .as_ref()
+                .and_then(|p| p.poseidon_hash.as_ref())
+                .map(|h| vec![to_hex(h)])
+                .unwrap_or_default()
+        };
+        PrettyElements {
+            rescaled_inputs: rescale(&witness.inputs, &settings.model_input_scales),
+            inputs: witness.inputs.iter().map(|t| to_hex(t)).collect(),
+            processed_inputs: hashes(&witness.processed_inputs),
+            processed_params: hashes(&witness.processed_params),
+            processed_outputs: hashes(&witness.processed_outputs),
+            rescaled_outputs: rescale(&witness.outputs, &settings.model_output_scales),
+            outputs: witness.outputs.iter().map(|t| to_hex(t)).collect(),
+        }
+    }
+
+    /// Prettifies the instances of a proof, splitting them into public inputs, hashes and public outputs following the
+    /// [InstanceLayout] of `settings`.
+    pub fn from_snark(snark: &Snark<Fp>, settings: &GraphSettings) -> Result<Self, Box<dyn Error>> {
+        let layout = InstanceLayout::from_settings(settings)?;
+        let mut pretty = PrettyElements::default();
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        let hashes = |processed: &mut Vec<Vec<String>>, felts: &[Fp]| match processed.first_mut() {
+            Some(hashes) => hashes.extend(to_hex(felts)),
+            None => processed.push(to_hex(felts)),
+        };
+        for (entry, felts) in layout.split(snark)? {
+            match entry.kind {
+                InstanceKind::Input => inputs.push(felts),
+                InstanceKind::Output => outputs.push(felts),
+                InstanceKind::InputHash => hashes(&mut pretty.processed_inputs, &felts),
+                InstanceKind::ParamHash => hashes(&mut pretty.processed_params, &felts),
+                InstanceKind::OutputHash => hashes(&mut pretty.processed_outputs, &felts),
+                InstanceKind::Ciphertext => {}
+            }
+        }
+
+        pretty.rescaled_inputs = rescale(&inputs, &settings.model_input_scales);
+        pretty.inputs = inputs.iter().map(|t| to_hex(t)).collect();
+        pretty.rescaled_outputs = rescale(&outputs, &settings.model_output_scales);
+        pretty.outputs = outputs.iter().map(|t| to_hex(t)).collect();
+        Ok(pretty)
+    }
+}
+
+impl GraphWitness {
+    /// Fills `pretty_elements` from the witness' felts.
+    pub fn generate_pretty_elements(&mut self, settings: &GraphSettings) {
+        self.pretty_elements = Some(PrettyElements::from_witness(self, settings));
+    }
+}
+
+fn to_hex(felts: &[Fp]) -> Vec<String> {
+    felts.iter().map(|x| felt_to_hex(*x)).collect()
+}
+
+fn rescale(tensors: &[Vec<Fp>], scales: &[Scale]) -> Vec<Vec<String>> {
+    tensors
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let scale = scales.get(i).copied().unwrap_or(0);
+            t.iter().map(|x| dequantize(*x, scale, 0.0).to_string()).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+    use halo2curves::ff::PrimeField;
+
+    const SETTINGS_JSON: &str = include_str!("../../settings.json");
+    const PROOF_JSON: &str = include_str!("../../proof.json");
+
+    fn felts(values: &[i128]) -> Vec<Fp> {
+        values.iter().map(|x| i128_to_felt(*x)).collect()
+    }
+
+    fn settings() -> GraphSettings {
+        GraphSettings::from_json(SETTINGS_JSON).unwrap()
+    }
+
+    /// A witness whose outputs are the public instances of the bundled proof.
+    fn witness() -> GraphWitness {
+        let mut witness = GraphWitness {
+            inputs: vec![felts(&[4, -2, 7])],
+            outputs: vec![felts(&[0, 28, 34])],
+            min_lookup_inputs: -12,
+            max_lookup_inputs: 300,
+            ..Default::default()
+        };
+        witness.generate_pretty_elements(&settings());
+        witness
+    }
+
+    #[test]
+    fn bundled_proof() {
+        let snark: Snark<Fp> = serde_json::from_str(PROOF_JSON).unwrap();
+        let report = witness().validate(&settings(), Some(&snark));
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(
+            witness().pretty_elements.unwrap().rescaled_outputs,
+            vec![vec!["0".to_string(), "3.5".to_string(), "4.25".to_string()]]
+        );
+    }
+
+    #[test]
+    fn instance_mismatch() {
+        let snark: Snark<Fp> = serde_json::from_str(PROOF_JSON).unwrap();
+        let mut witness = witness();
+        witness.outputs = vec![felts(&[0, 28, 35])];
+        witness.pretty_elements = None;
+        let report = witness.validate(&settings(), Some(&snark));
+        assert_eq!(report.issues, vec![WitnessIssue::InstanceMismatch { output: 0, index: 2 }]);
+    }
+
+    #[test]
+    fn instance_shapes_and_ranges() {
+        let mut witness = witness();
+        witness.outputs = vec![felts(&[0, 28])];
+        witness.inputs[0][1] = -Fp::from_u128(u128::MAX);
+        witness.max_lookup_inputs = 571;
+        witness.pretty_elements = None;
+        let issues = witness.validate(&settings(), None).issues;
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(issues[0], WitnessIssue::InstanceShapes(_)));
+        assert_eq!(
+            issues[1],
+            WitnessIssue::FeltOutOfRange {
+                tensor: "input 0".to_string(),
+                index: 1,
+            }
+        );
+        assert_eq!(
+            issues[2],
+            WitnessIssue::LookupRange {
+                min: -12,
+                max: 571,
+                range: (-378, 570),
+            }
+        );
+    }
+
+    #[test]
+    fn unbuildable_instance_layout() {
+        let snark: Snark<Fp> = serde_json::from_str(PROOF_JSON).unwrap();
+        let mut settings = settings();
+        settings.run_args.input_visibility = crate::runargs::Visibility::Encrypted;
+        settings.module_sizes.elgamal.1.clear();
+        let issues = witness().validate(&settings, Some(&snark)).issues;
+        assert_eq!(
+            issues,
+            vec![WitnessIssue::InstanceLayout(
+                "module_sizes.elgamal is missing ciphertext sizes".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn pretty_elements_are_compared_as_quantized_values() {
+        let settings = settings();
+        let mut witness = witness();
+        let pretty = witness.pretty_elements.as_mut().unwrap();
+        pretty.rescaled_outputs[0] = vec!["0.0".to_string(), "3.50".to_string(), "4.25000001".to_string()];
+        pretty.outputs[0][1] = pretty.outputs[0][1].to_uppercase();
+        assert!(witness.validate(&settings, None).is_valid());
+
+        let pretty = witness.pretty_elements.as_mut().unwrap();
+        pretty.rescaled_inputs[0][2] = "1.5".to_string();
+        pretty.processed_inputs = vec![vec![]];
+        assert_eq!(
+            witness.validate(&settings, None).issues,
+            vec![
+                WitnessIssue::PrettyElements("rescaled_inputs 0 element 2 disagrees with the witness".to_string()),
+                WitnessIssue::PrettyElements("processed_inputs holds 1 tensors but the witness has 0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_elements_from_bundled_proof() {
+        let snark: Snark<Fp> = serde_json::from_str(PROOF_JSON).unwrap();
+        let pretty = PrettyElements::from_snark(&snark, &settings()).unwrap();
+        let hex = |x: u8| format!("0x{}{:02x}", "0".repeat(62), x);
+        assert_eq!(
+            pretty,
+            PrettyElements {
+                rescaled_outputs: vec![vec!["0".to_string(), "3.5".to_string(), "4.25".to_string()]],
+                outputs: vec![vec![hex(0), hex(28), hex(34)]],
+                ..Default::default()
+            }
+        );
+        // the witness behind the proof prettifies its outputs the same way
+        let from_witness = witness().pretty_elements.unwrap();
+        assert_eq!(from_witness.outputs, pretty.outputs);
+        assert_eq!(from_witness.rescaled_outputs, pretty.rescaled_outputs);
+    }
+}
@@ -0,0 +1,172 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::graphsettings::LookupOp;
+use crate::runargs::Tolerance;
+use crate::supportedop::Tensor;
+use crate::utils;
+
+#[allow(missing_docs)]
+/// An enum representing the operations that consist of both lookups and arithmetic operations.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum HybridOp {
+    Recip {
+        input_scale: utils::F32,
+        output_scale: utils::F32,
+        use_range_check_for_int: bool,
+    },
+    Div {
+        denom: utils::F32,
+        use_range_check_for_int: bool,
+    },
+    ReduceMax {
+        axes: Vec<usize>,
+    },
+    ReduceArgMax {
+        dim: usize,
+    },
+    SumPool {
+        padding: [(usize, usize); 2],
+        stride: (usize, usize),
+        kernel_shape: (usize, usize),
+    },
+    MaxPool2d {
+        padding: [(usize, usize); 2],
+        stride: (usize, usize),
+        pool_dims: (usize, usize),
+    },
+    ReduceMin {
+        axes: Vec<usize>,
+    },
+    ReduceArgMin {
+        dim: usize,
+    },
+    Softmax {
+        scale: utils::F32,
+        axes: Vec<usize>,
+    },
+    RangeCheck(Tolerance),
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Equals,
+    Gather {
+        dim: usize,
+        constant_idx: Option<Tensor<usize>>,
+    },
+    TopK {
+        dim: usize,
+        k: usize,
+        largest: bool,
+    },
+    OneHot {
+        dim: usize,
+        num_classes: usize,
+    },
+    GatherElements {
+        dim: usize,
+        constant_idx: Option<Tensor<usize>>,
+    },
+    ScatterElements {
+        dim: usize,
+        constant_idx: Option<Tensor<usize>>,
+    },
+}
+
+impl HybridOp {
+    /// Returns the name of the operation.
+    pub fn as_string(&self) -> String {
+        match self {
+            HybridOp::Recip { .. } => "RECIP",
+            HybridOp::Div { .. } => "DIV",
+            HybridOp::ReduceMax { .. } => "REDUCEMAX",
+            HybridOp::ReduceArgMax { .. } => "REDUCEARGMAX",
+            HybridOp::SumPool { .. } => "SUMPOOL",
+            HybridOp::MaxPool2d { .. } => "MAXPOOL2D",
+            HybridOp::ReduceMin { .. } => "REDUCEMIN",
+            HybridOp::ReduceArgMin { .. } => "REDUCEARGMIN",
+            HybridOp::Softmax { .. } => "SOFTMAX",
+            HybridOp::RangeCheck(_) => "RANGECHECK",
+            HybridOp::Greater => "GREATER",
+            HybridOp::GreaterEqual => "GREATEREQUAL",
+            HybridOp::Less => "LESS",
+            HybridOp::LessEqual => "LESSEQUAL",
+            HybridOp::Equals => "EQUALS",
+            HybridOp::Gather { .. } => "GATHER",
+            HybridOp::TopK { .. } => "TOPK",
+            HybridOp::OneHot { .. } => "ONEHOT",
+            HybridOp::GatherElements { .. } => "GATHERELEMENTS",
+            HybridOp::ScatterElements { .. } => "SCATTERELEMENTS",
+        }
+        .into()
+    }
+
+    /// Returns the lookup tables the operation needs.
+    pub fn required_lookups(&self) -> Vec<LookupOp> {
+        match self {
+            HybridOp::Div {
+                denom,
+                use_range_check_for_int,
+            } => {
+                if *use_range_check_for_int {
+                    vec![]
+                } else {
+                    vec![LookupOp::Div { denom: *denom }]
+                }
+            }
+            HybridOp::Recip {
+                input_scale,
+                output_scale,
+                use_range_check_for_int,
+            } => {
+                if *use_range_check_for_int {
+                    vec![]
+                } else {
+                    vec![LookupOp::Recip {
+                        scale: (input_scale.0 * output_scale.0).into(),
+                    }]
+                }
+            }
+            HybridOp::ReduceMax { .. }
+            | HybridOp::ReduceMin { .. }
+            | HybridOp::ReduceArgMax { .. }
+            | HybridOp::ReduceArgMin { .. }
+            | HybridOp::MaxPool2d { .. }
+            | HybridOp::TopK { .. } => vec![LookupOp::ReLU],
+            // the sum of the exponentials is at `scale`, its reciprocal is taken back to `scale`
+            HybridOp::Softmax { scale, .. } => vec![
+                LookupOp::Exp { scale: *scale },
+                LookupOp::Recip {
+                    scale: scale.0.powi(2).into(),
+                },
+            ],
+            // the relative error is the difference scaled by the reciprocal of the expected values, both bounds of which
+            // are checked against the tolerance with a single greater-than table
+            HybridOp::RangeCheck(tol) => {
+                if tol.val > 0.0 {
+                    let scale_squared = tol.scale.0.powi(2);
+                    vec![
+                        LookupOp::Recip {
+                            scale: scale_squared.into(),
+                        },
+                        LookupOp::GreaterThan {
+                            a: (tol.val / 100.0 * scale_squared).into(),
+                        },
+                    ]
+                } else {
+                    vec![]
+                }
+            }
+            HybridOp::Greater => vec![LookupOp::GreaterThan { a: utils::F32(0.0) }],
+            HybridOp::GreaterEqual => vec![LookupOp::GreaterThanEqual { a: utils::F32(0.0) }],
+            HybridOp::Less => vec![LookupOp::LessThan { a: utils::F32(0.0) }],
+            HybridOp::LessEqual => vec![LookupOp::LessThanEqual { a: utils::F32(0.0) }],
+            HybridOp::Equals | HybridOp::OneHot { .. } => vec![LookupOp::KroneckerDelta],
+            HybridOp::SumPool { .. }
+            | HybridOp::Gather { .. }
+            | HybridOp::GatherElements { .. }
+            | HybridOp::ScatterElements { .. } => vec![],
+        }
+    }
+}
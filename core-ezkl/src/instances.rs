@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::graphsettings::GraphSettings;
+use crate::runargs::Visibility;
+use crate::snark::Snark;
+use crate::utils::Scale;
+
+/// What a span of instances holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstanceKind {
+    /// A public input tensor.
+    Input,
+    /// The Poseidon hash of an input tensor.
+    InputHash,
+    /// The Poseidon hash of a param tensor.
+    ParamHash,
+    /// A public output tensor.
+    Output,
+    /// The Poseidon hash of an output tensor.
+    OutputHash,
+    /// The masked message of an ElGamal ciphertext of an input or output tensor.
+    Ciphertext,
+}
+
+/// A labeled span of instances.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceEntry {
+    /// A name for the span, eg. `output_0` or `input_hash_1`.
+    pub name: String,
+    /// What the span holds.
+    pub kind: InstanceKind,
+    /// The shape of the tensor held.
+    pub shape: Vec<usize>,
+    /// The scale of the values held, `None` for hashes and ciphertexts.
+    pub scale: Option<Scale>,
+    /// The instance column holding the span.
+    pub column: usize,
+    /// The offset of the span in its column.
+    pub offset: usize,
+}
+
+impl InstanceEntry {
+    /// Returns the number of instances in the span.
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Returns true if the span holds no instances.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The layout of the instances of a proof.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstanceLayout {
+    /// The spans, in order.
+    pub entries: Vec<InstanceEntry>,
+}
+
+impl fmt::Display for InstanceLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:<12} {:>6} {:>6} {:>6} {:>8}",
+            "name", "kind", "column", "offset", "len", "scale"
+        )?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:<16} {:<12} {:>6} {:>6} {:>6} {:>8}",
+                entry.name,
+                format!("{:?}", entry.kind),
+                entry.column,
+                entry.offset,
+                entry.len(),
+                entry.scale.map(|s| s.to_string()).unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl InstanceLayout {
+    /// Builds the layout from the settings of the circuit. There is a single instance column holding the instances of the
+    /// inputs, then those of the params, then those of the outputs, where each tensor exposes its values if it is public,
+    /// its hash if it is publicly hashed and its ciphertext if it is encrypted. Public tensors take their shapes from
+    /// `model_instance_shapes`, the params take up the poseidon instances left over by inputs and outputs, and every
+    /// ciphertext takes up the number of instances listed in `module_sizes.elgamal`.
+    pub fn from_settings(settings: &GraphSettings) -> Result<Self, Box<dyn Error>> {
+        let run_args = &settings.run_args;
+        let num_inputs = settings.model_input_scales.len();
+        let num_outputs = settings.model_output_scales.len();
+        let mut shapes = settings.model_instance_shapes.iter();
+        let mut ciphertexts = settings.module_sizes.elgamal.1.iter();
+
+        let num_hashes: usize = settings.module_sizes.poseidon.1.iter().sum();
+        let mut num_param_hashes = num_hashes;
+        if run_args.input_visibility.is_hashed_public() {
+            num_param_hashes = num_param_hashes.saturating_sub(num_inputs);
+        }
+        if run_args.output_visibility.is_hashed_public() {
+            num_param_hashes = num_param_hashes.saturating_sub(num_outputs);
+        }
+
+        let mut layout = InstanceLayout::default();
+        let mut push_tensors = |layout: &mut InstanceLayout,
+                                name: &str,
+                                visibility: &Visibility,
+                                scales: &[Scale],
+                                kinds: (InstanceKind, InstanceKind)|
+         -> Result<(), Box<dyn Error>> {
+            for (i, scale) in scales.iter().enumerate() {
+                if visibility.is_public() {
+                    let shape = shapes
+                        .next()
+                        .ok_or_else(|| format!("model_instance_shapes is missing {} shapes", name))?;
+                    layout.push(format!("{}_{}", name, i), kinds.0, shape.clone(), Some(*scale));
+                } else if visibility.is_hashed_public() {
+                    layout.push(format!("{}_hash_{}", name, i), kinds.1, vec![1], None);
+                } else if *visibility == Visibility::Encrypted {
+                    let len = ciphertexts
+                        .next()
+                        .ok_or("module_sizes.elgamal is missing ciphertext sizes")?;
+                    layout.push(
+                        format!("{}_ciphertext_{}", name, i),
+                        InstanceKind::Ciphertext,
+                        vec![*len],
+                        None,
+                    );
+                }
+            }
+            Ok(())
+        };
+
+        push_tensors(
+            &mut layout,
+            "input",
+            &run_args.input_visibility,
+            &settings.model_input_scales,
+            (InstanceKind::Input, InstanceKind::InputHash),
+        )?;
+        if run_args.param_visibility.is_hashed_public() {
+            for i in 0..num_param_hashes {
+                layout.push(format!("param_hash_{}", i), InstanceKind::ParamHash, vec![1], None);
+            }
+        }
+        push_tensors(
+            &mut layout,
+            "output",
+            &run_args.output_visibility,
+            &settings.model_output_scales,
+            (InstanceKind::Output, InstanceKind::OutputHash),
+        )?;
+        if shapes.next().is_some() {
+            return Err("model_instance_shapes holds more shapes than there are public inputs and outputs".into());
+        }
+        Ok(layout)
+    }
+
+    fn push(&mut self, name: String, kind: InstanceKind, shape: Vec<usize>, scale: Option<Scale>) {
+        let offset = self.entries.last().map(|e| e.offset + e.len()).unwrap_or(0);
+        self.entries.push(InstanceEntry {
+            name,
+            kind,
+            shape,
+            scale,
+            column: 0,
+            offset,
+        });
+    }
+
+    /// Returns the number of instances in the column.
+    pub fn num_instances(&self, column: usize) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.column == column)
+            .map(|e| e.len())
+            .sum()
+    }
+
+    /// Returns the spans of the given kind, in order.
+    pub fn of_kind(&self, kind: InstanceKind) -> Vec<&InstanceEntry> {
+        self.entries.iter().filter(|e| e.kind == kind).collect()
+    }
+
+    /// Splits the instances of a proof into the labeled spans of the layout.
+    pub fn split<'a>(&'a self, snark: &Snark<Fp>) -> Result<Vec<(&'a InstanceEntry, Vec<Fp>)>, Box<dyn Error>> {
+        let num_columns = self.entries.iter().map(|e| e.column + 1).max().unwrap_or(0);
+        for column in 0..num_columns.max(snark.instances.len()) {
+            let found = snark.instances.get(column).map(|c| c.len()).unwrap_or(0);
+            let expected = self.num_instances(column);
+            if found != expected {
+                return Err(format!(
+                    "instance column {} holds {} values but the settings account for {}",
+                    column, found, expected
+                )
+                .into());
+            }
+        }
+        Ok(self
+            .entries
+            .iter()
+            .map(|e| (e, snark.instances[e.column][e.offset..e.offset + e.len()].to_vec()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+
+    fn settings() -> GraphSettings {
+        GraphSettings::from_json(include_str!("../../settings.json")).unwrap()
+    }
+
+    fn layout(settings: &GraphSettings) -> Vec<(String, InstanceKind, usize, usize)> {
+        InstanceLayout::from_settings(settings)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| (e.name.clone(), e.kind, e.offset, e.len()))
+            .collect()
+    }
+
+    #[test]
+    fn bundled_proof() {
+        let settings = settings();
+        let mut snark: Snark<Fp> = serde_json::from_str(include_str!("../../proof.json")).unwrap();
+        let layout = InstanceLayout::from_settings(&settings).unwrap();
+        assert_eq!(layout.num_instances(0), 3);
+        let split = layout.split(&snark).unwrap();
+        assert_eq!(split.len(), 1);
+        let (entry, felts) = &split[0];
+        assert_eq!(entry.name, "output_0");
+        assert_eq!(entry.shape, vec![1, 3]);
+        assert_eq!(entry.scale, Some(3));
+        assert_eq!(felts, &vec![i128_to_felt(0), i128_to_felt(28), i128_to_felt(34)]);
+
+        snark.instances[0].pop();
+        assert!(layout.split(&snark).is_err());
+    }
+
+    #[test]
+    fn module_instances_sit_with_their_tensors() {
+        let mut settings = settings();
+        settings.run_args.input_visibility = Visibility::Encrypted;
+        settings.run_args.param_visibility = Visibility::Hashed {
+            hash_is_public: true,
+            outlets: vec![],
+        };
+        settings.run_args.output_visibility = Visibility::Public;
+        settings.module_sizes.poseidon.1 = vec![2];
+        settings.module_sizes.elgamal.1 = vec![5];
+        assert_eq!(
+            layout(&settings),
+            vec![
+                ("input_ciphertext_0".to_string(), InstanceKind::Ciphertext, 0, 5),
+                ("param_hash_0".to_string(), InstanceKind::ParamHash, 5, 1),
+                ("param_hash_1".to_string(), InstanceKind::ParamHash, 6, 1),
+                ("output_0".to_string(), InstanceKind::Output, 7, 3),
+            ]
+        );
+
+        settings.run_args.input_visibility = Visibility::Hashed {
+            hash_is_public: true,
+            outlets: vec![],
+        };
+        settings.run_args.output_visibility = Visibility::Encrypted;
+        settings.model_instance_shapes.clear();
+        assert_eq!(
+            layout(&settings),
+            vec![
+                ("input_hash_0".to_string(), InstanceKind::InputHash, 0, 1),
+                ("param_hash_0".to_string(), InstanceKind::ParamHash, 1, 1),
+                ("output_ciphertext_0".to_string(), InstanceKind::Ciphertext, 2, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn inconsistent_settings() {
+        let mut settings = settings();
+        settings.model_instance_shapes.push(vec![2]);
+        assert!(InstanceLayout::from_settings(&settings).is_err());
+        settings.model_instance_shapes.clear();
+        assert!(InstanceLayout::from_settings(&settings).is_err());
+        settings.model_instance_shapes = vec![vec![1, 3]];
+        settings.run_args.output_visibility = Visibility::Encrypted;
+        settings.module_sizes.elgamal.1.clear();
+        assert!(InstanceLayout::from_settings(&settings).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+
+use halo2_proofs::poly::commitment::{Blind, Params};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::EvaluationDomain;
+use halo2curves::bn256::{Bn256, Fr as Fp, G1Affine, G1};
+use halo2curves::ff::PrimeField;
+use halo2curves::group::{Curve, GroupEncoding};
+use halo2curves::{Coordinates, CurveAffine};
+
+use crate::cost::RESERVED_BLINDING_ROWS;
+use crate::graphsettings::GraphSettings;
+use crate::graphwitness::{GraphWitness, ModuleForwardResult};
+use crate::runargs::Visibility;
+use crate::snark::Snark;
+
+/// Commits to a message the way ezkl's KZG module does: the message is laid out in Lagrange basis over polynomials of
+/// `2^k - num_unusable_rows` usable rows, the unusable rows are set to the default blind, and every polynomial is committed
+/// to with the default blind. `params` must hold the Lagrange basis for the circuit's `k`, ie. the prover's SRS downsized to `logrows`.
+pub fn commit(message: &[Fp], num_unusable_rows: usize, params: &ParamsKZG<Bn256>) -> Vec<G1Affine> {
+    let k = params.k();
+    let domain = EvaluationDomain::<Fp>::new(2, k);
+    let n = (1usize << k) - num_unusable_rows;
+    let num_poly = message.len() / n + 1;
+    let mut polys = vec![domain.empty_lagrange(); num_poly];
+    for poly in polys.iter_mut() {
+        for i in 0..num_unusable_rows {
+            poly[n + i] = Blind::<Fp>::default().0;
+        }
+    }
+    for (i, m) in message.iter().enumerate() {
+        polys[i / n][i % n] = *m;
+    }
+
+    let projective: Vec<G1> = polys
+        .iter()
+        .map(|poly| params.commit_lagrange(poly, Blind::default()))
+        .collect();
+    let mut commitments = vec![G1Affine::default(); projective.len()];
+    G1::batch_normalize(&projective, &mut commitments);
+    commitments
+}
+
+/// Commits to every tensor separately, as done for the inputs, params or outputs of a `KZGCommit` visibility. Settings
+/// that don't record `num_blinding_factors` fall back to [RESERVED_BLINDING_ROWS] unusable rows, halo2's minimum of 5
+/// blinding factors plus the last row, which is what the bundled proof's permutation argument uses.
+pub fn commit_tensors(tensors: &[Vec<Fp>], settings: &GraphSettings, params: &ParamsKZG<Bn256>) -> Vec<Vec<G1Affine>> {
+    let num_unusable_rows = settings
+        .num_blinding_factors
+        .map(|b| b + 1)
+        .unwrap_or(RESERVED_BLINDING_ROWS);
+    tensors
+        .iter()
+        .map(|t| commit(t, num_unusable_rows, params))
+        .collect()
+}
+
+/// Returns the offset of the commitment in the proof bytes, looking for both the compressed encoding written by the native
+/// transcripts and the uncompressed big-endian `x || y` encoding written by the EVM transcript.
+pub fn find_in_proof(commitment: &G1Affine, proof: &[u8]) -> Option<usize> {
+    let compressed = commitment.to_bytes();
+    let coords: Option<Coordinates<G1Affine>> = commitment.coordinates().into();
+    let mut uncompressed = vec![];
+    if let Some(coords) = coords {
+        for c in [coords.x(), coords.y()] {
+            uncompressed.extend(c.to_repr().as_ref().iter().rev());
+        }
+    }
+    let find = |needle: &[u8]| {
+        if needle.is_empty() {
+            return None;
+        }
+        proof.windows(needle.len()).position(|w| w == needle)
+    };
+    find(&uncompressed).or_else(|| find(compressed.as_ref()))
+}
+
+/// A recomputed commitment that disagrees with the witness or doesn't appear in the proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentMismatch {
+    /// Which tensors were committed to, `inputs` or `outputs`.
+    pub kind: String,
+    /// The index of the tensor.
+    pub index: usize,
+    /// What the commitment was compared to, `witness` or `proof`.
+    pub against: String,
+}
+
+impl fmt::Display for CommitmentMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the commitment to {} {} doesn't match the {}",
+            self.kind, self.index, self.against
+        )
+    }
+}
+
+/// Recomputes the commitments to the witness' inputs and outputs whose visibility is `KZGCommit`, and checks them against
+/// the witness' processed inputs and outputs and, if provided, against the commitments in the proof.
+pub fn check_witness_commitments(
+    witness: &GraphWitness,
+    settings: &GraphSettings,
+    params: &ParamsKZG<Bn256>,
+    snark: Option<&Snark<Fp>>,
+) -> Vec<CommitmentMismatch> {
+    let run_args = &settings.run_args;
+    let mut mismatches = vec![];
+    let mut check = |kind: &str, tensors: &[Vec<Fp>], processed: &Option<ModuleForwardResult>| {
+        let processed = processed.as_ref().and_then(|p| p.kzg_commit.as_ref());
+        for (index, commitments) in commit_tensors(tensors, settings, params).into_iter().enumerate() {
+            if let Some(processed) = processed {
+                if processed.get(index) != Some(&commitments) {
+                    mismatches.push(CommitmentMismatch {
+                        kind: kind.to_string(),
+                        index,
+                        against: "witness".to_string(),
+                    });
+                }
+            }
+            if let Some(snark) = snark {
+                if commitments.iter().any(|c| find_in_proof(c, &snark.proof).is_none()) {
+                    mismatches.push(CommitmentMismatch {
+                        kind: kind.to_string(),
+                        index,
+                        against: "proof".to_string(),
+                    });
+                }
+            }
+        }
+    };
+
+    if run_args.input_visibility == Visibility::KZGCommit {
+        check("inputs", &witness.inputs, &witness.processed_inputs);
+    }
+    if run_args.output_visibility == Visibility::KZGCommit {
+        check("outputs", &witness.outputs, &witness.processed_outputs);
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::SerdeFormat;
+    use halo2curves::ff::Field;
+    use halo2curves::group::Group;
+
+    fn params() -> ParamsKZG<Bn256> {
+        let mut params =
+            ParamsKZG::<Bn256>::read_custom(&mut &include_bytes!("../../kzg.srs")[..], SerdeFormat::RawBytes).unwrap();
+        params.downsize(10);
+        params
+    }
+
+    fn felts(values: &[u64]) -> Vec<Fp> {
+        values.iter().map(|x| Fp::from(*x)).collect()
+    }
+
+    #[test]
+    fn unusable_rows_match_the_bundled_proof() {
+        // the permutation argument queries the last usable row at rotation -(blinding_factors + 1)
+        let proof: serde_json::Value = serde_json::from_str(include_str!("../../proof.json")).unwrap();
+        let min_rotation = proof["protocol"]["queries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|q| q["rotation"].as_i64().unwrap())
+            .min();
+        assert_eq!(min_rotation, Some(-(RESERVED_BLINDING_ROWS as i64)));
+    }
+
+    #[test]
+    fn commitments_are_linear_in_the_message() {
+        let params = params();
+        assert_eq!(params.k(), 10);
+        let blinds = G1::from(commit(&[], RESERVED_BLINDING_ROWS, &params)[0]);
+        let once = commit(&felts(&[1, 2, 3]), RESERVED_BLINDING_ROWS, &params);
+        let twice = commit(&felts(&[2, 4, 6]), RESERVED_BLINDING_ROWS, &params);
+        assert_eq!(once.len(), 1);
+        assert!(!bool::from((G1::from(once[0]) - blinds).is_identity()));
+        assert_eq!(G1::from(twice[0]) - blinds, (G1::from(once[0]) - blinds) * Fp::from(2));
+
+        // the message spills over a second polynomial once it fills the usable rows
+        let usable = (1 << 10) - RESERVED_BLINDING_ROWS;
+        assert_eq!(commit(&vec![Fp::ONE; usable - 1], RESERVED_BLINDING_ROWS, &params).len(), 1);
+        assert_eq!(commit(&vec![Fp::ONE; usable + 1], RESERVED_BLINDING_ROWS, &params).len(), 2);
+
+        let settings = GraphSettings::from_json(include_str!("../../settings.json")).unwrap();
+        assert_eq!(settings.num_blinding_factors, None);
+        assert_eq!(commit_tensors(&[felts(&[1, 2, 3])], &settings, &params), vec![once]);
+    }
+
+    #[test]
+    fn commitments_are_found_in_the_proof() {
+        let params = params();
+        let commitment = commit(&felts(&[0, 28, 34]), RESERVED_BLINDING_ROWS, &params)[0];
+        let other = commit(&felts(&[0, 28, 35]), RESERVED_BLINDING_ROWS, &params)[0];
+
+        let mut proof = vec![7u8; 40];
+        proof.extend(commitment.to_bytes().as_ref());
+        proof.extend([7u8; 8]);
+        assert_eq!(find_in_proof(&commitment, &proof), Some(40));
+        assert_eq!(find_in_proof(&other, &proof), None);
+
+        let coords = commitment.coordinates().unwrap();
+        let mut evm_proof = vec![0u8; 64];
+        evm_proof.extend(coords.x().to_repr().as_ref().iter().rev());
+        evm_proof.extend(coords.y().to_repr().as_ref().iter().rev());
+        assert_eq!(find_in_proof(&commitment, &evm_proof), Some(64));
+        assert_eq!(find_in_proof(&other, &evm_proof), None);
+    }
+}
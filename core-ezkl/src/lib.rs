@@ -28,20 +28,35 @@
 
 pub mod snark;
 pub mod runargs;
+pub mod calibrate;
+pub mod commands;
+pub mod cost;
+pub mod diff;
+pub mod einsum;
+pub mod elgamal;
+pub mod export;
+pub mod fieldutils;
+pub mod forward;
 pub mod graphsettings;
+pub mod graphwitness;
+pub mod hybridop;
+pub mod instances;
+pub mod kzg;
+pub mod lookupcheck;
+pub mod model;
+pub mod optimize;
+pub mod policy;
+pub mod poseidon;
+pub mod poseidon_params;
+pub mod scalecheck;
+pub mod schema;
+pub mod shapecheck;
+pub mod srs_params;
+pub mod stats;
+pub mod supportedop;
+pub mod tensor;
+pub mod tensorops;
+#[cfg(test)]
+mod testutils;
+pub mod utils;
 
-
-pub fn get_verifier_key<C>(vk_path: &str, params: ParamsKZG<Bn256>) -> VerifyingKey<Bn256>
-where 
-    C: Circuit<Scalar: Serialize + DeserializeOwned> 
-    {
-    //read in the path
-    let mut f = File::open(vk_path).expect("File not found");
-    let mut reader = BufReader::new(f);
-    VerifyingKey::Bn256::read::<_, C>(
-        &mut reader,
-        halo2_proofs::SerdeFormat::RawBytes,
-        params,
-    )
-    .expect()
-}
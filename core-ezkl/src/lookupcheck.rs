@@ -0,0 +1,214 @@
+use std::fmt;
+
+use crate::graphsettings::{GraphSettings, LookupOp};
+use crate::model::Model;
+
+/// The lookups a model needs compared against the `required_lookups` declared in its settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LookupReport {
+    /// The lookups derived from the model, in sorted order.
+    pub required: Vec<LookupOp>,
+    /// Lookups the model needs that the settings don't declare.
+    pub missing: Vec<LookupOp>,
+    /// Lookups the settings declare that the model doesn't need.
+    pub extra: Vec<LookupOp>,
+}
+
+impl LookupReport {
+    /// Returns true if the settings declare exactly the lookups the model needs.
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl fmt::Display for LookupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_consistent() {
+            return writeln!(f, "required_lookups match the model ({} lookups)", self.required.len());
+        }
+        for op in &self.missing {
+            writeln!(f, "missing from required_lookups: {} ({:?})", op.as_string(), op)?;
+        }
+        for op in &self.extra {
+            writeln!(f, "not needed by the model: {} ({:?})", op.as_string(), op)?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the lookups needed by every `Nonlinear`, `Hybrid` and `RebaseScale` node of the model, including those of subgraphs,
+/// and diffs them against `settings.required_lookups`. A mismatch means the settings would produce a different verifying key.
+pub fn check_lookups(model: &Model, settings: &GraphSettings) -> LookupReport {
+    let required = model.graph.required_lookups();
+    let missing = required
+        .iter()
+        .filter(|op| !settings.required_lookups.contains(op))
+        .cloned()
+        .collect();
+    let mut extra: Vec<LookupOp> = vec![];
+    for op in &settings.required_lookups {
+        if !required.contains(op) && !extra.contains(op) {
+            extra.push(op.clone());
+        }
+    }
+    LookupReport {
+        required,
+        missing,
+        extra,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr as Fp;
+
+    use super::*;
+    use crate::hybridop::HybridOp;
+    use crate::model::SupportedOp;
+    use crate::runargs::Tolerance;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::supportedop::{PolyOp, RebaseScale, Rescaled};
+    use crate::testutils::{constant, input, model, node, subgraph};
+    use crate::utils::F32;
+
+    const SETTINGS_JSON: &str = include_str!("../../settings.json");
+
+    fn rebase(inner: PolyOp<Fp>, multiplier: f64, original_scale: i32) -> SupportedOp {
+        SupportedOp::RebaseScale(RebaseScale {
+            inner: Box::new(SupportedOp::Linear(inner)),
+            multiplier,
+            target_scale: 2,
+            original_scale,
+        })
+    }
+
+    /// `relu(x * w) * x`, rebased back to the input scale of the bundled settings after each product.
+    fn bundled_model() -> Model {
+        model(
+            vec![
+                input(0, &[1, 3], 2),
+                constant(1, &[0.5, -1.0, 2.0], &[1, 3], 3),
+                node(2, rebase(PolyOp::Mult, 8.0, 5), &[(0, 0), (1, 0)], &[1, 3], 2),
+                node(3, SupportedOp::Nonlinear(LookupOp::ReLU), &[(2, 0)], &[1, 3], 2),
+                node(4, rebase(PolyOp::Mult, 4.0, 4), &[(3, 0), (0, 0)], &[1, 3], 2),
+            ],
+            &[0],
+            &[(4, 0)],
+        )
+    }
+
+    fn lookups(op: HybridOp) -> Vec<LookupOp> {
+        SupportedOp::Hybrid(op).required_lookups()
+    }
+
+    #[test]
+    fn bundled_settings() {
+        let settings = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        let report = check_lookups(&bundled_model(), &settings);
+        assert!(report.is_consistent(), "{}", report);
+        assert_eq!(
+            report.required,
+            vec![
+                LookupOp::Div { denom: F32(4.0) },
+                LookupOp::Div { denom: F32(8.0) },
+                LookupOp::ReLU
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_and_extra() {
+        let mut settings = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        settings.required_lookups = vec![LookupOp::Div { denom: F32(8.0) }, LookupOp::ReLU, LookupOp::Abs];
+        let report = check_lookups(&bundled_model(), &settings);
+        assert_eq!(report.missing, vec![LookupOp::Div { denom: F32(4.0) }]);
+        assert_eq!(report.extra, vec![LookupOp::Abs]);
+    }
+
+    #[test]
+    fn softmax_inverts_at_scale_squared() {
+        let softmax = HybridOp::Softmax {
+            scale: F32(8.0),
+            axes: vec![1],
+        };
+        assert_eq!(
+            lookups(softmax),
+            vec![LookupOp::Exp { scale: F32(8.0) }, LookupOp::Recip { scale: F32(64.0) }]
+        );
+    }
+
+    #[test]
+    fn recip_table_scale() {
+        let recip = HybridOp::Recip {
+            input_scale: F32(4.0),
+            output_scale: F32(8.0),
+            use_range_check_for_int: false,
+        };
+        assert_eq!(lookups(recip), vec![LookupOp::Recip { scale: F32(32.0) }]);
+    }
+
+    #[test]
+    fn range_check_tolerance() {
+        let exact = HybridOp::RangeCheck(Tolerance {
+            val: 0.0,
+            scale: F32(4.0),
+        });
+        assert!(lookups(exact).is_empty());
+        let percent = HybridOp::RangeCheck(Tolerance {
+            val: 50.0,
+            scale: F32(4.0),
+        });
+        assert_eq!(
+            lookups(percent),
+            vec![LookupOp::Recip { scale: F32(16.0) }, LookupOp::GreaterThan { a: F32(8.0) }]
+        );
+    }
+
+    #[test]
+    fn subgraph_and_rescaled_lookups() {
+        let sigmoid = LookupOp::Sigmoid { scale: F32(4.0) };
+        let rescaled = SupportedOp::Rescaled(Rescaled {
+            inner: Box::new(SupportedOp::Nonlinear(sigmoid.clone())),
+            scale: vec![(0, 2)],
+        });
+        let body = model(
+            vec![
+                input(0, &[3], 2),
+                node(1, rescaled, &[(0, 0)], &[3], 3),
+                node(2, SupportedOp::Nonlinear(LookupOp::ReLU), &[(1, 0)], &[3], 3),
+            ],
+            &[0],
+            &[(2, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[3], 2),
+                node(1, SupportedOp::Nonlinear(LookupOp::ReLU), &[(0, 0)], &[3], 2),
+                subgraph(
+                    2,
+                    body,
+                    &[(1, 0)],
+                    vec![InputMapping::Full],
+                    vec![vec![OutputMapping::Single {
+                        outlet: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![3]],
+                    vec![3],
+                ),
+            ],
+            &[0],
+            &[(2, 0)],
+        );
+        let mut settings = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        settings.required_lookups = vec![LookupOp::ReLU, LookupOp::Abs, LookupOp::Abs];
+        let report = check_lookups(&m, &settings);
+        // the ReLU used both outside and inside the subgraph is required once
+        assert_eq!(report.required, vec![LookupOp::ReLU, sigmoid.clone()]);
+        assert_eq!(report.missing, vec![sigmoid]);
+        // a lookup declared twice is reported once
+        assert_eq!(report.extra, vec![LookupOp::Abs]);
+        assert!(!report.is_consistent());
+        assert!(report.to_string().contains("missing from required_lookups: SIGMOID"));
+    }
+}
@@ -1,10 +1,15 @@
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
-use utils::Scale;
-use std::collections::BTreeMap;
+use crate::graphsettings::LookupOp;
+use crate::hybridop::HybridOp;
+use crate::supportedop::{Constant, Input, PolyOp, RebaseScale, Rescaled, Unknown};
+use crate::tensor::TensorError;
+use crate::utils::Scale;
+use std::collections::{BTreeMap, BTreeSet};
 use halo2curves::bn256::Fr as Fp;
 
 /// A struct for loading from an Onnx file and converting a computational graph to a circuit.
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Model {
     /// input indices
     pub graph: ParsedNodes,
@@ -12,17 +17,86 @@ pub struct Model {
     pub visibility: VarVisibility,
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 /// A set of EZKL nodes that represent a computational graph.
 pub struct ParsedNodes {
     /// The nodes in the graph.
     pub nodes: BTreeMap<usize, NodeType>,
-    inputs: Vec<usize>,
-    outputs: Vec<Outlet>,
+    /// The indices of the input nodes.
+    pub inputs: Vec<usize>,
+    /// The outlets that make up the outputs of the graph.
+    pub outputs: Vec<Outlet>,
+}
+
+impl ParsedNodes {
+    /// Returns the number of nodes in the graph, not counting the nodes of subgraphs.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the dims of the tensor at `outlet`, if the node exists and has such an output.
+    pub fn outlet_dims(&self, outlet: &Outlet) -> Option<Vec<usize>> {
+        self.nodes
+            .get(&outlet.0)
+            .and_then(|n| n.out_dims().get(outlet.1).cloned())
+    }
+
+    /// Returns the scale of the tensor at `outlet`, if the node exists and has such an output.
+    pub fn outlet_scale(&self, outlet: &Outlet) -> Option<Scale> {
+        self.nodes
+            .get(&outlet.0)
+            .and_then(|n| n.out_scales().get(outlet.1).cloned())
+    }
+
+    /// Returns the distinct lookup tables needed by the nodes of the graph and of its subgraphs, in sorted order.
+    pub fn required_lookups(&self) -> Vec<LookupOp> {
+        let mut lookups = BTreeSet::new();
+        for node in self.nodes.values() {
+            match node {
+                NodeType::Node(n) => lookups.extend(n.opkind.required_lookups()),
+                NodeType::SubGraph { model, .. } => lookups.extend(model.graph.required_lookups()),
+            }
+        }
+        lookups.into_iter().collect()
+    }
+}
+
+impl NodeType {
+    /// Returns the node's unique identifier.
+    pub fn idx(&self) -> usize {
+        match self {
+            NodeType::Node(n) => n.idx,
+            NodeType::SubGraph { idx, .. } => *idx,
+        }
+    }
+
+    /// Returns the outlets the node reads from.
+    pub fn inputs(&self) -> Vec<Outlet> {
+        match self {
+            NodeType::Node(n) => n.inputs.clone(),
+            NodeType::SubGraph { inputs, .. } => inputs.clone(),
+        }
+    }
+
+    /// Returns the dims of each of the node's outputs.
+    pub fn out_dims(&self) -> Vec<Vec<usize>> {
+        match self {
+            NodeType::Node(n) => vec![n.out_dims.clone()],
+            NodeType::SubGraph { out_dims, .. } => out_dims.clone(),
+        }
+    }
+
+    /// Returns the scale of each of the node's outputs.
+    pub fn out_scales(&self) -> Vec<Scale> {
+        match self {
+            NodeType::Node(n) => vec![n.out_scale],
+            NodeType::SubGraph { out_scales, .. } => out_scales.clone(),
+        }
+    }
 }
 
 /// Enables model as subnode of other models
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum NodeType {
     /// A node in the model
     Node(Node),
@@ -46,7 +120,7 @@ pub enum NodeType {
 }
 
 ///
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum OutputMapping {
     ///
     Single {
@@ -67,7 +141,7 @@ pub enum OutputMapping {
 }
 
 ///
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum InputMapping {
     ///
     Full,
@@ -82,11 +156,38 @@ pub enum InputMapping {
     },
 }
 
+/// Returns the number of iterations of a subgraph: the length of its `Stacked` inputs along their stacked axis, divided
+/// into chunks, or 1 if no input is stacked. Stacked inputs that disagree on the number of iterations are an error.
+pub fn num_iterations(input_mappings: &[InputMapping], input_dims: &[Vec<usize>]) -> Result<usize, TensorError> {
+    let mut num_iter = None;
+    for (mapping, dims) in input_mappings.iter().zip(input_dims) {
+        if let InputMapping::Stacked { axis, chunk } = mapping {
+            let len = dims.get(*axis).ok_or_else(|| {
+                TensorError::AxisError(format!(
+                    "cannot iterate over axis {} of a tensor of dims {:?}",
+                    axis, dims
+                ))
+            })?;
+            let iters = len.div_ceil((*chunk).max(1));
+            match num_iter {
+                Some(n) if n != iters => {
+                    return Err(TensorError::DimMismatch(format!(
+                        "stacked inputs iterate {} and {} times",
+                        n, iters
+                    )))
+                }
+                _ => num_iter = Some(iters),
+            }
+        }
+    }
+    Ok(num_iter.unwrap_or(1))
+}
+
 /// A node's input is a tensor from another node's output.
 pub type Outlet = (usize, usize);
 
 /// Represents whether the model input, model parameters, and model output are Public or Private to the prover.
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, PartialOrd, JsonSchema)]
 pub struct VarVisibility {
     /// Input to the model or computational graph
     pub input: Visibility,
@@ -97,7 +198,7 @@ pub struct VarVisibility {
 }
 
 /// Label enum to track whether model input, model parameters, and model output are public, private, or hashed
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default, JsonSchema)]
 pub enum Visibility {
     /// Mark an item as private to the prover (not in the proof submitted for verification)
     #[default]
@@ -115,12 +216,14 @@ pub enum Visibility {
     },
     /// Mark an item as publicly committed to (KZG commitment sent in the proof submitted for verification)
     KZGCommit,
+    /// Mark an item as encrypted to a public key with ElGamal (the ciphertext is sent in the proof submitted for verification)
+    Encrypted,
     /// assigned as a constant in the circuit
     Fixed,
 }
 
 /// A single operation in a [crate::graph::Model].
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Node {
     /// [Op] i.e what operation this node represents.
     pub opkind: SupportedOp,
@@ -139,7 +242,7 @@ pub struct Node {
 }
 
 /// A single operation in a [crate::graph::Model].
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum SupportedOp {
     /// A linear operation.
     Linear(PolyOp<Fp>),
@@ -147,14 +250,51 @@ pub enum SupportedOp {
     Nonlinear(LookupOp),
     /// A hybrid operation.
     Hybrid(HybridOp),
-    ///
+    /// A model input.
     Input(Input),
-    ///
+    /// A constant (parameter) of the model.
     Constant(Constant<Fp>),
-    ///
+    /// An operation that could not be parsed.
     Unknown(Unknown),
-    ///
+    /// An operation whose inputs are rescaled to a common scale.
     Rescaled(Rescaled),
-    ///
+    /// An operation whose output scale is rebased.
     RebaseScale(RebaseScale),
-}
\ No newline at end of file
+}
+
+impl SupportedOp {
+    /// Returns the name of the operation.
+    pub fn as_string(&self) -> String {
+        match self {
+            SupportedOp::Linear(op) => op.as_string(),
+            SupportedOp::Nonlinear(op) => op.as_string(),
+            SupportedOp::Hybrid(op) => op.as_string(),
+            SupportedOp::Input(_) => "INPUT".into(),
+            SupportedOp::Constant(_) => "CONST".into(),
+            SupportedOp::Unknown(_) => "UNKNOWN".into(),
+            SupportedOp::Rescaled(op) => format!("RESCALED INPUT ({})", op.inner.as_string()),
+            SupportedOp::RebaseScale(op) => {
+                format!("REBASED (div={:?}) ({})", op.multiplier, op.inner.as_string())
+            }
+        }
+    }
+
+    /// Returns the lookup tables the operation needs, including the division of a rebased output.
+    pub fn required_lookups(&self) -> Vec<LookupOp> {
+        match self {
+            SupportedOp::Nonlinear(op) => vec![op.clone()],
+            SupportedOp::Hybrid(op) => op.required_lookups(),
+            SupportedOp::Rescaled(op) => op.inner.required_lookups(),
+            SupportedOp::RebaseScale(op) => {
+                let mut lookups = op.inner.required_lookups();
+                lookups.push(LookupOp::Div {
+                    denom: (op.multiplier as f32).into(),
+                });
+                lookups
+            }
+            SupportedOp::Linear(_) | SupportedOp::Input(_) | SupportedOp::Constant(_) | SupportedOp::Unknown(_) => {
+                vec![]
+            }
+        }
+    }
+}
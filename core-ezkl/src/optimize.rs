@@ -0,0 +1,481 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::cost::{estimate_costs, CostError};
+use crate::fieldutils::dequantize;
+use crate::model::{Model, NodeType, Outlet, ParsedNodes, SupportedOp};
+use crate::runargs::RunArgs;
+use crate::supportedop::{Constant, PolyOp};
+use crate::tensor::Tensor;
+
+/// What an optimization pass changed, by node path.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptimizationReport {
+    /// `Identity` nodes, and reshapes that leave the dims unchanged, whose uses were rewired to their input.
+    pub removed_identities: Vec<Vec<usize>>,
+    /// Reshapes whose input is another reshape, rewired to read from the first reshape's input.
+    pub collapsed_reshapes: Vec<Vec<usize>>,
+    /// Nodes whose inputs were all constants, replaced by the constant they evaluate to.
+    pub folded_constants: Vec<Vec<usize>>,
+    /// Nodes that were removed because nothing used their output.
+    pub removed_dead: Vec<Vec<usize>>,
+    /// The estimated rows of the model before the pass.
+    pub rows_before: usize,
+    /// The estimated rows of the model after the pass.
+    pub rows_after: usize,
+}
+
+impl OptimizationReport {
+    /// Returns the estimated number of rows saved by the pass.
+    pub fn rows_saved(&self) -> usize {
+        self.rows_before.saturating_sub(self.rows_after)
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "removed identities: {:?}", self.removed_identities)?;
+        writeln!(f, "collapsed reshapes: {:?}", self.collapsed_reshapes)?;
+        writeln!(f, "folded constants: {:?}", self.folded_constants)?;
+        writeln!(f, "removed dead nodes: {:?}", self.removed_dead)?;
+        writeln!(
+            f,
+            "rows: {} -> {} ({} saved)",
+            self.rows_before,
+            self.rows_after,
+            self.rows_saved()
+        )
+    }
+}
+
+/// Simplifies the model: rewires the uses of identities and back-to-back reshapes, folds ops whose inputs are all constants
+/// into a single [Constant], removes nodes that are neither used nor an output (graph inputs are kept) and recomputes `num_uses`.
+/// Node indices are left untouched. Row savings are estimated with [estimate_costs] for `run_args`.
+pub fn optimize(model: &Model, run_args: &RunArgs) -> Result<(Model, OptimizationReport), CostError> {
+    let mut optimized = model.clone();
+    let mut report = OptimizationReport {
+        rows_before: estimate_costs(model, run_args)?.total_rows,
+        ..Default::default()
+    };
+    optimize_graph(&mut optimized.graph, &[], &mut report);
+    report.rows_after = estimate_costs(&optimized, run_args)?.total_rows;
+    Ok((optimized, report))
+}
+
+fn optimize_graph(graph: &mut ParsedNodes, prefix: &[usize], report: &mut OptimizationReport) {
+    let path_of = |idx: usize| {
+        let mut path = prefix.to_vec();
+        path.push(idx);
+        path
+    };
+
+    // outlets whose uses are redirected to another outlet
+    let mut rewired: BTreeMap<Outlet, Outlet> = BTreeMap::new();
+    let idxs: Vec<usize> = graph.nodes.keys().copied().collect();
+    for idx in idxs {
+        let (inputs, is_reshape) = match graph.nodes.get_mut(&idx) {
+            Some(NodeType::Node(n)) => {
+                for input in n.inputs.iter_mut() {
+                    *input = resolve(&rewired, *input);
+                }
+                (n.inputs.clone(), is_reshape(&n.opkind))
+            }
+            Some(NodeType::SubGraph { model, inputs, .. }) => {
+                for input in inputs.iter_mut() {
+                    *input = resolve(&rewired, *input);
+                }
+                optimize_graph(&mut model.graph, &path_of(idx), report);
+                continue;
+            }
+            None => continue,
+        };
+
+        let Some(NodeType::Node(n)) = graph.nodes.get(&idx) else {
+            continue;
+        };
+        let input_dims = inputs.first().and_then(|o| graph.outlet_dims(o));
+
+        // identities, and reshapes to the dims they already have
+        let is_identity = matches!(n.opkind, SupportedOp::Linear(PolyOp::Identity))
+            || (is_reshape && input_dims.as_ref() == Some(&n.out_dims));
+        if is_identity && inputs.len() == 1 {
+            rewired.insert((idx, 0), inputs[0]);
+            report.removed_identities.push(path_of(idx));
+            continue;
+        }
+
+        // a reshape of a reshape only needs the dims of the last one
+        if is_reshape && inputs.len() == 1 {
+            let inner = match graph.nodes.get(&inputs[0].0) {
+                Some(NodeType::Node(prev)) if is_reshape(&prev.opkind) && prev.inputs.len() == 1 => {
+                    Some(prev.inputs[0])
+                }
+                _ => None,
+            };
+            if let Some(inner) = inner {
+                if let Some(NodeType::Node(n)) = graph.nodes.get_mut(&idx) {
+                    n.inputs = vec![inner];
+                }
+                report.collapsed_reshapes.push(path_of(idx));
+            }
+        }
+
+        // ops whose inputs are all constants
+        if let Some(folded) = fold_constant(graph, idx) {
+            if let Some(NodeType::Node(n)) = graph.nodes.get_mut(&idx) {
+                n.opkind = SupportedOp::Constant(folded);
+                n.inputs = vec![];
+            }
+            report.folded_constants.push(path_of(idx));
+        }
+    }
+
+    for output in graph.outputs.iter_mut() {
+        *output = resolve(&rewired, *output);
+    }
+
+    // drop unused nodes until none are left, inputs of the graph are kept so that callers can still feed them
+    loop {
+        let uses = count_uses(graph);
+        let dead: Vec<usize> = graph
+            .nodes
+            .keys()
+            .filter(|idx| !uses.contains_key(idx) && !graph.inputs.contains(idx))
+            .copied()
+            .collect();
+        if dead.is_empty() {
+            break;
+        }
+        for idx in dead {
+            graph.nodes.remove(&idx);
+            report.removed_dead.push(path_of(idx));
+        }
+    }
+
+    let uses = count_uses(graph);
+    for (idx, node) in graph.nodes.iter_mut() {
+        if let NodeType::Node(n) = node {
+            n.num_uses = uses.get(idx).copied().unwrap_or(0);
+        }
+    }
+}
+
+fn is_reshape(op: &SupportedOp) -> bool {
+    matches!(op, SupportedOp::Linear(PolyOp::Reshape(_)) | SupportedOp::Linear(PolyOp::Flatten(_)))
+}
+
+fn resolve(rewired: &BTreeMap<Outlet, Outlet>, mut outlet: Outlet) -> Outlet {
+    while let Some(next) = rewired.get(&outlet) {
+        outlet = *next;
+    }
+    outlet
+}
+
+/// Counts, for every node, the inputs of other nodes and the graph outputs that read from it.
+fn count_uses(graph: &ParsedNodes) -> BTreeMap<usize, usize> {
+    let mut uses: BTreeMap<usize, usize> = BTreeMap::new();
+    for node in graph.nodes.values() {
+        for input in node.inputs() {
+            *uses.entry(input.0).or_insert(0) += 1;
+        }
+    }
+    for output in &graph.outputs {
+        *uses.entry(output.0).or_insert(0) += 1;
+    }
+    uses
+}
+
+/// Evaluates a linear op whose inputs are all constants, returns `None` if the node can't be folded.
+fn fold_constant(graph: &ParsedNodes, idx: usize) -> Option<Constant<Fp>> {
+    let Some(NodeType::Node(n)) = graph.nodes.get(&idx) else {
+        return None;
+    };
+    let SupportedOp::Linear(op) = &n.opkind else {
+        return None;
+    };
+    if n.inputs.is_empty() {
+        return None;
+    }
+    let inputs = n
+        .inputs
+        .iter()
+        .map(|outlet| match graph.nodes.get(&outlet.0) {
+            Some(NodeType::Node(input)) => match &input.opkind {
+                SupportedOp::Constant(c) => Some(c.quantized_values.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Option<Vec<Tensor<Fp>>>>()?;
+
+    let mut quantized_values = op.f(&inputs).ok()?;
+    if quantized_values.dims() != n.out_dims.as_slice() {
+        return None;
+    }
+    quantized_values.set_scale(n.out_scale);
+    let mut raw_values: Tensor<f32> = quantized_values
+        .iter()
+        .map(|x| dequantize(*x, n.out_scale, 0.0) as f32)
+        .collect();
+    raw_values.reshape(&n.out_dims).ok()?;
+    Some(Constant {
+        quantized_values,
+        raw_values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::MAX_LOGROWS;
+    use crate::graphsettings::LookupOp;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::testutils::{constant, felts, input, model, node, subgraph};
+
+    fn linear(op: PolyOp<Fp>) -> SupportedOp {
+        SupportedOp::Linear(op)
+    }
+
+    fn relu() -> SupportedOp {
+        SupportedOp::Nonlinear(LookupOp::ReLU)
+    }
+
+    fn num_uses(graph: &ParsedNodes, idx: usize) -> usize {
+        match graph.nodes.get(&idx) {
+            Some(NodeType::Node(n)) => n.num_uses,
+            _ => panic!("node {} is not a node", idx),
+        }
+    }
+
+    #[test]
+    fn identity_rewiring() {
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, linear(PolyOp::Identity), &[(0, 0)], &[2], 0),
+                node(2, relu(), &[(1, 0)], &[2], 0),
+                // a reshape to the dims it already has is an identity too
+                node(3, linear(PolyOp::Reshape(vec![2])), &[(2, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(3, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert_eq!(report.removed_identities, vec![vec![1], vec![3]]);
+        assert_eq!(report.removed_dead, vec![vec![1], vec![3]]);
+        assert_eq!(optimized.graph.nodes[&2].inputs(), vec![(0, 0)]);
+        assert_eq!(optimized.graph.outputs, vec![(2, 0)]);
+        assert_eq!(optimized.graph.num_nodes(), 2);
+    }
+
+    #[test]
+    fn reshape_collapse() {
+        let m = model(
+            vec![
+                input(0, &[2, 3], 0),
+                node(1, linear(PolyOp::Reshape(vec![3, 2])), &[(0, 0)], &[3, 2], 0),
+                node(2, linear(PolyOp::Flatten(vec![6])), &[(1, 0)], &[6], 0),
+            ],
+            &[0],
+            &[(2, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert_eq!(report.collapsed_reshapes, vec![vec![2]]);
+        assert_eq!(report.removed_dead, vec![vec![1]]);
+        assert_eq!(optimized.graph.nodes[&2].inputs(), vec![(0, 0)]);
+        assert_eq!(optimized.graph.nodes[&2].out_dims(), vec![vec![6]]);
+    }
+
+    #[test]
+    fn constant_folding() {
+        let m = model(
+            vec![
+                input(0, &[2], 1),
+                constant(1, &[0.5, 1.0], &[2], 1),
+                constant(2, &[1.0, 2.0], &[2], 1),
+                node(3, linear(PolyOp::Add), &[(1, 0), (2, 0)], &[2], 1),
+                node(4, linear(PolyOp::Mult), &[(0, 0), (3, 0)], &[2], 2),
+            ],
+            &[0],
+            &[(4, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert_eq!(report.folded_constants, vec![vec![3]]);
+        assert_eq!(report.removed_dead, vec![vec![1], vec![2]]);
+        match &optimized.graph.nodes[&3] {
+            NodeType::Node(n) => {
+                let SupportedOp::Constant(c) = &n.opkind else {
+                    panic!("node 3 was not folded");
+                };
+                assert!(n.inputs.is_empty());
+                assert_eq!(c.quantized_values, felts(&[3, 6], &[2]));
+                assert_eq!(c.raw_values, Tensor::new(Some(&[1.5, 3.0]), &[2]).unwrap());
+            }
+            NodeType::SubGraph { .. } => panic!("node 3 is not a node"),
+        }
+        // folding doesn't reach into ops reading non constants
+        assert!(matches!(
+            &optimized.graph.nodes[&4],
+            NodeType::Node(n) if matches!(n.opkind, SupportedOp::Linear(PolyOp::Mult))
+        ));
+    }
+
+    #[test]
+    fn dead_nodes_are_removed_until_none_are_left() {
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                input(1, &[2], 0),
+                node(2, relu(), &[(0, 0)], &[2], 0),
+                node(3, relu(), &[(2, 0)], &[2], 0),
+                node(4, relu(), &[(0, 0)], &[2], 0),
+            ],
+            &[0, 1],
+            &[(4, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        // 2 is only dead once 3 is gone, the unused input 1 is kept
+        assert_eq!(report.removed_dead, vec![vec![3], vec![2]]);
+        assert_eq!(optimized.graph.nodes.keys().copied().collect::<Vec<_>>(), vec![0, 1, 4]);
+        assert!(report.rows_saved() > 0);
+    }
+
+    #[test]
+    fn dead_nodes_in_subgraph_bodies() {
+        let body = model(
+            vec![
+                input(0, &[1], 0),
+                node(1, relu(), &[(0, 0)], &[1], 0),
+                node(2, linear(PolyOp::Neg), &[(1, 0)], &[1], 0),
+                node(3, linear(PolyOp::Identity), &[(0, 0)], &[1], 0),
+            ],
+            &[0],
+            &[(3, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[4], 0),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![vec![OutputMapping::Stacked {
+                        outlet: 0,
+                        axis: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![4]],
+                    vec![0],
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert_eq!(report.removed_identities, vec![vec![1, 3]]);
+        assert_eq!(report.removed_dead, vec![vec![1, 2], vec![1, 3], vec![1, 1]]);
+        let NodeType::SubGraph { model: body, .. } = &optimized.graph.nodes[&1] else {
+            panic!("node 1 is not a subgraph");
+        };
+        assert_eq!(body.graph.outputs, vec![(0, 0)]);
+        assert_eq!(body.graph.num_nodes(), 1);
+        assert_eq!(num_uses(&body.graph, 0), 1);
+    }
+
+    #[test]
+    fn num_uses_recomputed() {
+        let mut m = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, relu(), &[(0, 0)], &[2], 0),
+                node(2, linear(PolyOp::Add), &[(0, 0), (1, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(2, 0), (1, 0)],
+        );
+        for node in m.graph.nodes.values_mut() {
+            if let NodeType::Node(n) = node {
+                n.num_uses = 7;
+            }
+        }
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        // nothing to simplify
+        let unchanged = OptimizationReport {
+            rows_before: report.rows_before,
+            rows_after: report.rows_before,
+            ..Default::default()
+        };
+        assert_eq!(report, unchanged);
+        assert_eq!(num_uses(&optimized.graph, 0), 2);
+        assert_eq!(num_uses(&optimized.graph, 1), 2);
+        assert_eq!(num_uses(&optimized.graph, 2), 1);
+    }
+
+    #[test]
+    fn chained_identities_are_resolved_for_subgraphs_and_outputs() {
+        let body = model(vec![input(0, &[2], 0), node(1, relu(), &[(0, 0)], &[2], 0)], &[0], &[(1, 0)]);
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                node(1, linear(PolyOp::Identity), &[(0, 0)], &[2], 0),
+                node(2, linear(PolyOp::Identity), &[(1, 0)], &[2], 0),
+                subgraph(
+                    3,
+                    body,
+                    &[(2, 0)],
+                    vec![InputMapping::Full],
+                    vec![vec![OutputMapping::Single {
+                        outlet: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![2]],
+                    vec![0],
+                ),
+            ],
+            &[0],
+            &[(3, 0), (2, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert_eq!(report.removed_identities, vec![vec![1], vec![2]]);
+        assert_eq!(optimized.graph.nodes[&3].inputs(), vec![(0, 0)]);
+        // an output read through identities now reads the graph input
+        assert_eq!(optimized.graph.outputs, vec![(3, 0), (0, 0)]);
+        assert_eq!(num_uses(&optimized.graph, 0), 2);
+    }
+
+    #[test]
+    fn unfoldable_constants() {
+        let m = model(
+            vec![
+                input(0, &[2], 0),
+                constant(1, &[1.0, -1.0], &[2], 0),
+                // lookups are not evaluated
+                node(2, relu(), &[(1, 0)], &[2], 0),
+                // the op's output doesn't match the stored dims
+                node(3, linear(PolyOp::Reshape(vec![1, 2])), &[(1, 0)], &[2, 1], 0),
+            ],
+            &[0],
+            &[(2, 0), (3, 0)],
+        );
+        let (optimized, report) = optimize(&m, &RunArgs::default()).unwrap();
+        assert!(report.folded_constants.is_empty());
+        assert_eq!(optimized.graph.num_nodes(), 4);
+        assert_eq!(num_uses(&optimized.graph, 1), 2);
+    }
+
+    #[test]
+    fn cost_errors_are_returned() {
+        let m = model(vec![input(0, &[2], 0)], &[0], &[(0, 0)]);
+        let run_args = RunArgs {
+            logrows: MAX_LOGROWS + 1,
+            ..RunArgs::default()
+        };
+        assert_eq!(
+            optimize(&m, &run_args).unwrap_err(),
+            CostError::LogRowsTooLarge(MAX_LOGROWS + 1)
+        );
+    }
+}
@@ -0,0 +1,456 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::cost::min_logrows;
+use crate::forward::{forward, ForwardError};
+use crate::graphsettings::{CheckMode, GraphSettings};
+use crate::graphwitness::{GraphWitness, WitnessReport};
+use crate::instances::InstanceLayout;
+use crate::lookupcheck::{check_lookups, LookupReport};
+use crate::model::Model;
+use crate::poseidon::{check_witness_hashes, HashMismatch};
+use crate::runargs::RunArgs;
+use crate::scalecheck::{check_scales, ScaleReport};
+use crate::shapecheck::{check_shapes, ShapeReport};
+use crate::snark::Snark;
+use crate::tensor::Tensor;
+
+/// Decides which consistency checks are run before accepting a proof. The checks follow the `check_mode` of the proof's
+/// settings: they are all run in `SAFE` mode and skipped in `UNSAFE` mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerificationPolicy {
+    /// Reject proofs whose settings declare `UNSAFE` instead of skipping their checks.
+    pub forbid_unsafe: bool,
+    /// Skip the `SAFE` checks whose artifacts are missing instead of rejecting the proof.
+    pub allow_partial: bool,
+}
+
+/// The artifacts a proof is checked against. In `SAFE` mode a missing artifact rejects the proof, unless the policy
+/// allows partial checks.
+#[derive(Clone, Copy, Debug)]
+pub struct VerificationContext<'a> {
+    /// the settings of the circuit
+    pub settings: &'a GraphSettings,
+    /// the proof
+    pub snark: &'a Snark<Fp>,
+    /// the witness the proof was generated from
+    pub witness: Option<&'a GraphWitness>,
+    /// the model the circuit was generated from
+    pub model: Option<&'a Model>,
+    /// the `k` of the verifying key's evaluation domain, as read by [vk_logrows]
+    pub vk_k: Option<u32>,
+}
+
+/// A consistency check that failed.
+#[derive(Clone, Debug)]
+pub enum PolicyViolation {
+    /// The settings declare `UNSAFE` and the policy forbids it.
+    UnsafeForbidden,
+    /// An artifact the `SAFE` checks need wasn't provided and the policy doesn't allow partial checks.
+    MissingArtifact(String),
+    /// The instances of the proof don't match the layout declared by the settings.
+    InstanceLayout(String),
+    /// The circuit doesn't fit in `2^logrows` rows.
+    Rows {
+        /// the rows used by the circuit
+        num_rows: usize,
+        /// the log2 number of rows of the settings
+        logrows: u32,
+    },
+    /// The verifying key was generated for another number of rows.
+    LogRows {
+        /// the `k` of the verifying key
+        vk: u32,
+        /// the log2 number of rows of the settings
+        settings: u32,
+    },
+    /// The witness is inconsistent with the settings or the proof.
+    Witness(WitnessReport),
+    /// The hashes of the witness disagree with its processed tensors or the proof.
+    Hashes(Vec<HashMismatch>),
+    /// Running the model over the witness' inputs doesn't reproduce its outputs.
+    Outputs(String),
+    /// The lookups of the model don't match the settings.
+    Lookups(LookupReport),
+    /// The stored shapes of the model are inconsistent.
+    Shapes(ShapeReport),
+    /// The stored scales of the model are inconsistent.
+    Scales(ScaleReport),
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::UnsafeForbidden => write!(f, "the settings declare UNSAFE checks, which the policy forbids"),
+            PolicyViolation::MissingArtifact(artifact) => {
+                write!(f, "no {} was provided and the policy doesn't allow partial checks", artifact)
+            }
+            PolicyViolation::InstanceLayout(e) => write!(f, "instance layout: {}", e),
+            PolicyViolation::Rows { num_rows, logrows } => {
+                write!(f, "{} rows don't fit in 2^{} rows", num_rows, logrows)
+            }
+            PolicyViolation::LogRows { vk, settings } => write!(
+                f,
+                "the verifying key has k = {} but the settings have logrows = {}",
+                vk, settings
+            ),
+            PolicyViolation::Witness(report) => write!(f, "witness:\n{}", report),
+            PolicyViolation::Hashes(mismatches) => {
+                write!(f, "hashes:")?;
+                for m in mismatches {
+                    write!(f, "\n{}", m)?;
+                }
+                Ok(())
+            }
+            PolicyViolation::Outputs(e) => write!(f, "outputs: {}", e),
+            PolicyViolation::Lookups(report) => write!(f, "lookups:\n{}", report),
+            PolicyViolation::Shapes(report) => write!(f, "shapes:\n{}", report),
+            PolicyViolation::Scales(report) => write!(f, "scales:\n{}", report),
+        }
+    }
+}
+
+/// The outcome of applying a [VerificationPolicy].
+#[derive(Clone, Debug)]
+pub struct PolicyReport {
+    /// the check mode declared by the settings
+    pub check_mode: CheckMode,
+    /// the checks that were run
+    pub checks: Vec<String>,
+    /// the checks that were skipped, and why
+    pub skipped: Vec<String>,
+    /// the checks that failed
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl PolicyReport {
+    /// Returns true if the proof can be accepted.
+    pub fn is_accepted(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "check mode: {:?}", self.check_mode)?;
+        for check in &self.checks {
+            writeln!(f, "ran: {}", check)?;
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "skipped: {}", skipped)?;
+        }
+        for violation in &self.violations {
+            writeln!(f, "violation: {}", violation)?;
+        }
+        writeln!(f, "{}", if self.is_accepted() { "accepted" } else { "rejected" })
+    }
+}
+
+impl VerificationPolicy {
+    /// Applies the policy to a proof. In `SAFE` mode the instances are checked against the settings' layout, the settings
+    /// against the verifying key, the witness against the proof and the model against the settings and the witness. A
+    /// missing verifying key, witness or model is a violation unless the policy allows partial checks, in which case the
+    /// checks that need it are skipped. In `UNSAFE` mode nothing is checked, or the proof is rejected if the policy
+    /// forbids it.
+    pub fn apply(&self, ctx: &VerificationContext) -> PolicyReport {
+        let settings = ctx.settings;
+        let mut report = PolicyReport {
+            check_mode: settings.check_mode,
+            checks: vec![],
+            skipped: vec![],
+            violations: vec![],
+        };
+        if settings.check_mode == CheckMode::UNSAFE {
+            if self.forbid_unsafe {
+                report.violations.push(PolicyViolation::UnsafeForbidden);
+            } else {
+                report.skipped.push("all checks: the settings declare UNSAFE".to_string());
+            }
+            return report;
+        }
+
+        report.checks.push("instance layout".to_string());
+        let layout = InstanceLayout::from_settings(settings).and_then(|layout| layout.split(ctx.snark).map(|_| ()));
+        if let Err(e) = layout {
+            report.violations.push(PolicyViolation::InstanceLayout(e.to_string()));
+        }
+
+        let logrows = settings.run_args.logrows;
+        report.checks.push("rows".to_string());
+        if min_logrows(settings.num_rows).map_or(true, |k| k > logrows) {
+            report.violations.push(PolicyViolation::Rows {
+                num_rows: settings.num_rows,
+                logrows,
+            });
+        }
+        match ctx.vk_k {
+            Some(vk) => {
+                report.checks.push("verifying key logrows".to_string());
+                if vk != logrows {
+                    report.violations.push(PolicyViolation::LogRows { vk, settings: logrows });
+                }
+            }
+            None => self.missing(&mut report, "verifying key logrows", "verifying key"),
+        }
+
+        match ctx.witness {
+            Some(witness) => {
+                report.checks.push("witness".to_string());
+                let witness_report = witness.validate(settings, Some(ctx.snark));
+                if !witness_report.is_valid() {
+                    report.violations.push(PolicyViolation::Witness(witness_report));
+                }
+                report.checks.push("hashes".to_string());
+                let mismatches = check_witness_hashes(witness, settings, Some(ctx.snark));
+                if !mismatches.is_empty() {
+                    report.violations.push(PolicyViolation::Hashes(mismatches));
+                }
+            }
+            None => self.missing(&mut report, "witness, hashes", "witness"),
+        }
+
+        let model = match ctx.model {
+            Some(model) => model,
+            None => {
+                self.missing(&mut report, "outputs, lookups, shapes, scales", "model");
+                return report;
+            }
+        };
+        report.checks.push("lookups".to_string());
+        let lookups = check_lookups(model, settings);
+        if !lookups.is_consistent() {
+            report.violations.push(PolicyViolation::Lookups(lookups));
+        }
+        report.checks.push("shapes".to_string());
+        let shapes = check_shapes(model);
+        if !shapes.is_consistent() {
+            report.violations.push(PolicyViolation::Shapes(shapes));
+        }
+        report.checks.push("scales".to_string());
+        let scales = check_scales(model, &settings.run_args);
+        if !scales.is_consistent() {
+            report.violations.push(PolicyViolation::Scales(scales));
+        }
+
+        match ctx.witness {
+            Some(witness) => match reevaluate(model, witness, &settings.run_args) {
+                Ok(()) => report.checks.push("outputs".to_string()),
+                Err(ForwardCheck::Unsupported(e)) => report.skipped.push(format!("outputs: {}", e)),
+                Err(ForwardCheck::Mismatch(e)) => {
+                    report.checks.push("outputs".to_string());
+                    report.violations.push(PolicyViolation::Outputs(e));
+                }
+            },
+            None => report.skipped.push("outputs: no witness".to_string()),
+        }
+        report
+    }
+
+    /// Records the checks that need a missing artifact as skipped, or the artifact as a violation.
+    fn missing(&self, report: &mut PolicyReport, checks: &str, artifact: &str) {
+        if self.allow_partial {
+            report.skipped.push(format!("{}: no {}", checks, artifact));
+        } else {
+            report.violations.push(PolicyViolation::MissingArtifact(artifact.to_string()));
+        }
+    }
+}
+
+/// Reads the `k` of a serialized verifying key's evaluation domain, which halo2 writes first as a big-endian `u32`.
+pub fn vk_logrows(path: impl AsRef<Path>) -> Result<u32, Box<dyn Error>> {
+    let mut k = [0u8; 4];
+    std::fs::File::open(path)?.read_exact(&mut k)?;
+    Ok(u32::from_be_bytes(k))
+}
+
+enum ForwardCheck {
+    Unsupported(ForwardError),
+    Mismatch(String),
+}
+
+/// Runs the model over the witness' inputs and compares the result with the witness' outputs.
+fn reevaluate(model: &Model, witness: &GraphWitness, run_args: &RunArgs) -> Result<(), ForwardCheck> {
+    let inputs = model
+        .graph
+        .inputs
+        .iter()
+        .zip(&witness.inputs)
+        .map(|(idx, felts)| {
+            let dims = model.graph.outlet_dims(&(*idx, 0)).unwrap_or_else(|| vec![felts.len()]);
+            Tensor::new(Some(felts.as_slice()), &dims)
+                .map_err(|e| ForwardCheck::Mismatch(format!("input {}: {}", idx, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let result = match forward(model, &inputs, run_args) {
+        Ok(result) => result,
+        Err(e @ ForwardError::Unsupported { .. }) => return Err(ForwardCheck::Unsupported(e)),
+        Err(e) => return Err(ForwardCheck::Mismatch(e.to_string())),
+    };
+    if result.outputs.len() != witness.outputs.len() {
+        return Err(ForwardCheck::Mismatch(format!(
+            "the model has {} outputs but the witness has {}",
+            result.outputs.len(),
+            witness.outputs.len()
+        )));
+    }
+    for (i, (computed, expected)) in result.outputs.iter().zip(&witness.outputs).enumerate() {
+        if computed.iter().ne(expected.iter()) {
+            return Err(ForwardCheck::Mismatch(format!("output {} differs from the witness", i)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+
+    fn bundled_settings(check_mode: CheckMode) -> GraphSettings {
+        let mut settings = GraphSettings::from_json(include_str!("../../settings.json")).unwrap();
+        settings.check_mode = check_mode;
+        settings
+    }
+
+    fn snark() -> Snark<Fp> {
+        serde_json::from_str(include_str!("../../proof.json")).unwrap()
+    }
+
+    const PARTIAL: VerificationPolicy = VerificationPolicy {
+        forbid_unsafe: false,
+        allow_partial: true,
+    };
+
+    fn bundled_vk() -> u32 {
+        vk_logrows(concat!(env!("CARGO_MANIFEST_DIR"), "/../test.vk")).unwrap()
+    }
+
+    fn apply(policy: VerificationPolicy, settings: &GraphSettings, witness: Option<&GraphWitness>) -> PolicyReport {
+        let snark = snark();
+        let ctx = VerificationContext {
+            settings,
+            snark: &snark,
+            witness,
+            model: None,
+            vk_k: Some(bundled_vk()),
+        };
+        policy.apply(&ctx)
+    }
+
+    #[test]
+    fn bundled_vk_logrows() {
+        assert_eq!(bundled_vk(), 10);
+        assert!(vk_logrows(concat!(env!("CARGO_MANIFEST_DIR"), "/../missing.vk")).is_err());
+    }
+
+    #[test]
+    fn safe_bundled_proof() {
+        let report = apply(PARTIAL, &bundled_settings(CheckMode::SAFE), None);
+        assert!(report.is_accepted(), "{}", report);
+        assert_eq!(report.checks, vec!["instance layout", "rows", "verifying key logrows"]);
+        assert_eq!(
+            report.skipped,
+            vec![
+                "witness, hashes: no witness",
+                "outputs, lookups, shapes, scales: no model",
+            ]
+        );
+
+        let witness = GraphWitness {
+            inputs: vec![vec![i128_to_felt(4), i128_to_felt(-2), i128_to_felt(7)]],
+            outputs: vec![vec![i128_to_felt(0), i128_to_felt(28), i128_to_felt(34)]],
+            ..Default::default()
+        };
+        let report = apply(PARTIAL, &bundled_settings(CheckMode::SAFE), Some(&witness));
+        assert!(report.is_accepted(), "{}", report);
+
+        let mut forged = witness.clone();
+        forged.outputs[0][2] = i128_to_felt(35);
+        let report = apply(PARTIAL, &bundled_settings(CheckMode::SAFE), Some(&forged));
+        assert!(matches!(report.violations[..], [PolicyViolation::Witness(_)]));
+    }
+
+    #[test]
+    fn safe_proof_without_artifacts() {
+        let settings = bundled_settings(CheckMode::SAFE);
+        let report = apply(VerificationPolicy::default(), &settings, None);
+        assert!(!report.is_accepted());
+        assert!(report.skipped.is_empty());
+        let missing: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            missing,
+            vec![
+                "no witness was provided and the policy doesn't allow partial checks",
+                "no model was provided and the policy doesn't allow partial checks",
+            ]
+        );
+
+        let snark = snark();
+        let ctx = VerificationContext {
+            settings: &settings,
+            snark: &snark,
+            witness: None,
+            model: None,
+            vk_k: None,
+        };
+        let report = VerificationPolicy::default().apply(&ctx);
+        assert!(matches!(report.violations[0], PolicyViolation::MissingArtifact(ref a) if a == "verifying key"));
+        let report = PARTIAL.apply(&ctx);
+        assert!(report.is_accepted(), "{}", report);
+        assert_eq!(report.skipped[0], "verifying key logrows: no verifying key");
+    }
+
+    #[test]
+    fn unsafe_bundled_proof() {
+        let settings = bundled_settings(CheckMode::UNSAFE);
+        let report = apply(VerificationPolicy::default(), &settings, None);
+        assert!(report.is_accepted());
+        assert!(report.checks.is_empty());
+        assert_eq!(report.skipped, vec!["all checks: the settings declare UNSAFE"]);
+
+        let forbid_unsafe = VerificationPolicy {
+            forbid_unsafe: true,
+            allow_partial: true,
+        };
+        let report = apply(forbid_unsafe, &settings, None);
+        assert!(!report.is_accepted());
+        assert!(matches!(report.violations[..], [PolicyViolation::UnsafeForbidden]));
+        // a SAFE proof is unaffected by forbid_unsafe
+        assert!(apply(forbid_unsafe, &bundled_settings(CheckMode::SAFE), None).is_accepted());
+    }
+
+    #[test]
+    fn mismatched_settings() {
+        let mut settings = bundled_settings(CheckMode::SAFE);
+        settings.model_instance_shapes = vec![vec![1, 4]];
+        let report = apply(PARTIAL, &settings, None);
+        assert!(matches!(report.violations[..], [PolicyViolation::InstanceLayout(_)]));
+
+        let mut settings = bundled_settings(CheckMode::SAFE);
+        settings.run_args.logrows = 11;
+        settings.num_rows = 1019;
+        let report = apply(PARTIAL, &settings, None);
+        assert!(matches!(
+            report.violations[..],
+            [PolicyViolation::LogRows { vk: 10, settings: 11 }]
+        ));
+
+        settings.run_args.logrows = 10;
+        let report = apply(PARTIAL, &settings, None);
+        assert!(matches!(
+            report.violations[..],
+            [PolicyViolation::Rows {
+                num_rows: 1019,
+                logrows: 10
+            }]
+        ));
+
+        settings.num_rows = usize::MAX;
+        let report = apply(PARTIAL, &settings, None);
+        assert!(matches!(report.violations[..], [PolicyViolation::Rows { .. }]));
+    }
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+
+use halo2_gadgets::poseidon::primitives::{ConstantLength, Hash, Mds, Spec};
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::Field;
+
+use crate::fieldutils::{felt_to_hex, i128_to_felt, quantize_float};
+use crate::graphsettings::GraphSettings;
+use crate::graphwitness::GraphWitness;
+use crate::instances::{InstanceKind, InstanceLayout};
+use crate::poseidon_params::{MDS, MDS_INV, ROUND_CONSTANTS};
+use crate::snark::Snark;
+use crate::tensor::TensorError;
+
+/// The width of the Poseidon permutation.
+pub const POSEIDON_WIDTH: usize = 2;
+/// The rate of the Poseidon sponge.
+pub const POSEIDON_RATE: usize = 1;
+/// The number of felts hashed at a time, messages are hashed in chunks of this length.
+pub const POSEIDON_LEN_GRAPH: usize = 32;
+
+/// The Poseidon spec used by ezkl: x^5 S-box, 8 full rounds and 56 partial rounds over `Fr`, with the hardcoded
+/// constants of [crate::poseidon_params].
+#[derive(Debug, Clone, Copy)]
+pub struct PoseidonSpec;
+
+impl Spec<Fp, POSEIDON_WIDTH, POSEIDON_RATE> for PoseidonSpec {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        56
+    }
+
+    fn sbox(val: Fp) -> Fp {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        // only used to generate constants, which `constants` overrides with the hardcoded ones
+        0
+    }
+
+    fn constants() -> (Vec<[Fp; POSEIDON_WIDTH]>, Mds<Fp, POSEIDON_WIDTH>, Mds<Fp, POSEIDON_WIDTH>) {
+        (ROUND_CONSTANTS.to_vec(), MDS, MDS_INV)
+    }
+}
+
+/// Hashes a message the way ezkl's Poseidon module does: the message is split in zero padded chunks of
+/// [POSEIDON_LEN_GRAPH] felts which are hashed, and the resulting hashes are hashed in turn until one remains.
+/// An empty message hashes a single chunk of zeros.
+pub fn poseidon_hash(message: &[Fp]) -> Fp {
+    let mut hash_inputs = message.to_vec();
+    let mut one_iter = false;
+    while hash_inputs.len() > 1 || !one_iter {
+        let blocks: Vec<&[Fp]> = if hash_inputs.is_empty() {
+            vec![&hash_inputs[..]]
+        } else {
+            hash_inputs.chunks(POSEIDON_LEN_GRAPH).collect()
+        };
+        hash_inputs = blocks
+            .into_iter()
+            .map(|block| {
+                let mut padded = [Fp::ZERO; POSEIDON_LEN_GRAPH];
+                padded[..block.len()].copy_from_slice(block);
+                Hash::<_, PoseidonSpec, ConstantLength<POSEIDON_LEN_GRAPH>, POSEIDON_WIDTH, POSEIDON_RATE>::init()
+                    .hash(padded)
+            })
+            .collect();
+        one_iter = true;
+    }
+    hash_inputs[0]
+}
+
+/// Hashes every tensor separately, as done for the inputs, params or outputs of a `Hashed` visibility.
+pub fn hash_tensors(tensors: &[Vec<Fp>]) -> Vec<Fp> {
+    tensors.iter().map(|t| poseidon_hash(t)).collect()
+}
+
+/// A recomputed hash that disagrees with the witness or with the proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashMismatch {
+    /// Which tensors were hashed, `inputs` or `outputs`.
+    pub kind: String,
+    /// The index of the tensor.
+    pub index: usize,
+    /// What the hash was compared to, `witness` or `proof`.
+    pub against: String,
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the hash of {} {} differs from the one in the {}",
+            self.kind, self.index, self.against
+        )
+    }
+}
+
+/// Recomputes the hashes of the witness' inputs and outputs whose visibility is `Hashed`, and checks them against the
+/// witness' processed inputs and outputs and, if the hashes are public, against the instances of the proof.
+pub fn check_witness_hashes(
+    witness: &GraphWitness,
+    settings: &GraphSettings,
+    snark: Option<&Snark<Fp>>,
+) -> Vec<HashMismatch> {
+    let run_args = &settings.run_args;
+    let instances: &[Fp] = snark
+        .and_then(|s| s.instances.first())
+        .map(|i| i.as_slice())
+        .unwrap_or(&[]);
+    let processed_inputs = witness.processed_inputs.as_ref().and_then(|p| p.poseidon_hash.as_ref());
+    let processed_outputs = witness.processed_outputs.as_ref().and_then(|p| p.poseidon_hash.as_ref());
+    // without a layout none of the hashes can be found in the proof, which is reported as a mismatch
+    let layout = InstanceLayout::from_settings(settings).unwrap_or_default();
+
+    let mut mismatches = vec![];
+    let mut check = |kind: &str,
+                     tensors: &[Vec<Fp>],
+                     processed: Option<&Vec<Fp>>,
+                     public: bool,
+                     instance_kind: InstanceKind| {
+        let entries = layout.of_kind(instance_kind);
+        for (index, hash) in hash_tensors(tensors).into_iter().enumerate() {
+            if let Some(processed) = processed {
+                if processed.get(index) != Some(&hash) {
+                    mismatches.push(HashMismatch {
+                        kind: kind.to_string(),
+                        index,
+                        against: "witness".to_string(),
+                    });
+                }
+            }
+            let instance = entries.get(index).and_then(|e| instances.get(e.offset));
+            if public && snark.is_some() && instance != Some(&hash) {
+                mismatches.push(HashMismatch {
+                    kind: kind.to_string(),
+                    index,
+                    against: "proof".to_string(),
+                });
+            }
+        }
+    };
+
+    if run_args.input_visibility.is_hashed() {
+        check(
+            "inputs",
+            &witness.inputs,
+            processed_inputs,
+            run_args.input_visibility.is_hashed_public(),
+            InstanceKind::InputHash,
+        );
+    }
+    if run_args.output_visibility.is_hashed() {
+        check(
+            "outputs",
+            &witness.outputs,
+            processed_outputs,
+            run_args.output_visibility.is_hashed_public(),
+            InstanceKind::OutputHash,
+        );
+    }
+    mismatches
+}
+
+/// Why raw inputs failed to match the hashed inputs of a proof.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputHashError {
+    /// The settings don't declare `Hashed { hash_is_public: true }` inputs.
+    NotHashedPublic,
+    /// The number of input tensors doesn't match `model_input_scales`.
+    InputCount {
+        /// the number of inputs of the model
+        expected: usize,
+        /// the number of inputs provided
+        provided: usize,
+    },
+    /// A value could not be quantized.
+    Quantize {
+        /// the index of the input tensor
+        tensor: usize,
+        /// the underlying error
+        error: TensorError,
+    },
+    /// The proof has no instance for the hash of the tensor.
+    MissingInstance {
+        /// the index of the input tensor
+        tensor: usize,
+    },
+    /// The hash of the tensor differs from the proof's instance.
+    Mismatch {
+        /// the index of the input tensor
+        tensor: usize,
+        /// the hash of the raw data, as a 0x string
+        computed: String,
+        /// the instance of the proof, as a 0x string
+        instance: String,
+    },
+}
+
+impl fmt::Display for InputHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputHashError::NotHashedPublic => write!(f, "the inputs of the proof are not publicly hashed"),
+            InputHashError::InputCount { expected, provided } => {
+                write!(f, "the model has {} inputs but {} were provided", expected, provided)
+            }
+            InputHashError::Quantize { tensor, error } => write!(f, "input {}: {}", tensor, error),
+            InputHashError::MissingInstance { tensor } => {
+                write!(f, "the proof has no instance for the hash of input {}", tensor)
+            }
+            InputHashError::Mismatch {
+                tensor,
+                computed,
+                instance,
+            } => write!(
+                f,
+                "the hash of input {} is {} but the proof holds {}",
+                tensor, computed, instance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputHashError {}
+
+/// Quantizes raw input data with `model_input_scales`, hashes every input tensor and checks the hashes against the
+/// instances of a proof whose inputs are `Hashed { hash_is_public: true }`. The hashes are found through the
+/// [InstanceLayout] of `settings`.
+pub fn verify_hashed_inputs(
+    data: &[Vec<f64>],
+    settings: &GraphSettings,
+    snark: &Snark<Fp>,
+) -> Result<(), InputHashError> {
+    if !settings.run_args.input_visibility.is_hashed_public() {
+        return Err(InputHashError::NotHashedPublic);
+    }
+    let scales = &settings.model_input_scales;
+    if data.len() != scales.len() {
+        return Err(InputHashError::InputCount {
+            expected: scales.len(),
+            provided: data.len(),
+        });
+    }
+    let instances: &[Fp] = snark.instances.first().map(|i| i.as_slice()).unwrap_or(&[]);
+    let layout = InstanceLayout::from_settings(settings).unwrap_or_default();
+    let entries = layout.of_kind(InstanceKind::InputHash);
+
+    for (tensor, (values, scale)) in data.iter().zip(scales).enumerate() {
+        let felts = values
+            .iter()
+            .map(|x| quantize_float(x, 0.0, *scale).map(i128_to_felt::<Fp>))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| InputHashError::Quantize { tensor, error })?;
+        let computed = poseidon_hash(&felts);
+        let instance = entries
+            .get(tensor)
+            .and_then(|e| instances.get(e.offset))
+            .ok_or(InputHashError::MissingInstance { tensor })?;
+        if *instance != computed {
+            return Err(InputHashError::Mismatch {
+                tensor,
+                computed: felt_to_hex(computed),
+                instance: felt_to_hex(*instance),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runargs::Visibility;
+
+    /// The Poseidon permutation of [crate::poseidon_params], without the sponge.
+    fn permute(mut state: [Fp; POSEIDON_WIDTH]) -> [Fp; POSEIDON_WIDTH] {
+        let (half_full, partial) = (PoseidonSpec::full_rounds() / 2, PoseidonSpec::partial_rounds());
+        for (round, constants) in ROUND_CONSTANTS.iter().enumerate() {
+            for (word, c) in state.iter_mut().zip(constants) {
+                *word += c;
+            }
+            if round < half_full || round >= half_full + partial {
+                state = state.map(PoseidonSpec::sbox);
+            } else {
+                state[0] = PoseidonSpec::sbox(state[0]);
+            }
+            state = MDS.map(|row| row.iter().zip(&state).map(|(m, x)| m * x).sum());
+        }
+        state
+    }
+
+    fn hex(felt: Fp) -> String {
+        felt_to_hex(felt)
+    }
+
+    #[test]
+    fn permutation_matches_circomlib() {
+        // circomlib's poseidon([1]) for t = 2, which permutes [0, 1] with the same reference constants
+        assert_eq!(
+            hex(permute([Fp::ZERO, Fp::ONE])[0]),
+            "0x29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133"
+        );
+    }
+
+    #[test]
+    fn mds_inverse() {
+        for i in 0..POSEIDON_WIDTH {
+            for j in 0..POSEIDON_WIDTH {
+                let x: Fp = (0..POSEIDON_WIDTH).map(|k| MDS[i][k] * MDS_INV[k][j]).sum();
+                assert_eq!(x, if i == j { Fp::ONE } else { Fp::ZERO });
+            }
+        }
+    }
+
+    #[test]
+    fn known_hashes() {
+        let felts = |values: &[i128]| values.iter().map(|x| i128_to_felt::<Fp>(*x)).collect::<Vec<_>>();
+        // a single zero padded chunk, including the empty message
+        assert_eq!(
+            hex(poseidon_hash(&[])),
+            "0x23cb7aca94a1fe912ecd4c244d0925cef05445d79c1a335957d4ff8897564996"
+        );
+        assert_eq!(hex(poseidon_hash(&[])), hex(poseidon_hash(&[Fp::ZERO; POSEIDON_LEN_GRAPH])));
+        assert_eq!(
+            hex(poseidon_hash(&felts(&[1]))),
+            "0x08c7c508d79ff2702d62f307915ba33f6812722f6b5335982f0c47688fbe864d"
+        );
+        assert_eq!(
+            hex(poseidon_hash(&felts(&[1, 2, 3]))),
+            "0x126be1620a9dafb39eb97ed82e337201660725e28a760377a24485fc3c1d300b"
+        );
+        assert_eq!(
+            hex(poseidon_hash(&felts(&[-1]))),
+            "0x2d47a7e4bd68dec756f5d186bd70d756296533422755231ecf70ea1a0e2607f2"
+        );
+        // two chunks, whose hashes are hashed in turn
+        let message = felts(&(0..33).collect::<Vec<_>>());
+        assert_eq!(
+            hex(poseidon_hash(&message)),
+            "0x1515e29aa492530a61e7bb3228efa46945596feb75c5a63d15b531b9ada84b46"
+        );
+        assert_eq!(
+            poseidon_hash(&message),
+            poseidon_hash(&[poseidon_hash(&message[..32]), poseidon_hash(&message[32..])])
+        );
+        assert_eq!(hash_tensors(&[felts(&[1]), vec![]]), vec![poseidon_hash(&felts(&[1])), poseidon_hash(&[])]);
+    }
+
+    /// The bundled settings with publicly hashed inputs, and a proof holding the hash of `[4, -2, 7]` followed by the
+    /// bundled outputs.
+    fn hashed_inputs() -> (GraphSettings, Snark<Fp>) {
+        let mut settings = GraphSettings::from_json(include_str!("../../settings.json")).unwrap();
+        settings.run_args.input_visibility = Visibility::Hashed {
+            hash_is_public: true,
+            outlets: vec![],
+        };
+        settings.module_sizes.poseidon.1 = vec![1];
+        let hash = poseidon_hash(&[i128_to_felt(4), i128_to_felt(-2), i128_to_felt(7)]);
+        let snark = Snark {
+            protocol: String::new(),
+            instances: vec![vec![hash, i128_to_felt(0), i128_to_felt(28), i128_to_felt(34)]],
+            proof: vec![],
+            transcript_type: String::new(),
+            split: String::new(),
+        };
+        (settings, snark)
+    }
+
+    #[test]
+    fn hashed_inputs_match() {
+        let (settings, snark) = hashed_inputs();
+        assert_eq!(verify_hashed_inputs(&[vec![1.0, -0.5, 1.75]], &settings, &snark), Ok(()));
+    }
+
+    #[test]
+    fn hashed_inputs_mismatch() {
+        let (settings, snark) = hashed_inputs();
+        let computed = felt_to_hex(poseidon_hash(&[i128_to_felt(4), i128_to_felt(-2), i128_to_felt(8)]));
+        assert_eq!(
+            verify_hashed_inputs(&[vec![1.0, -0.5, 2.0]], &settings, &snark),
+            Err(InputHashError::Mismatch {
+                tensor: 0,
+                computed,
+                instance: felt_to_hex(snark.instances[0][0]),
+            })
+        );
+    }
+
+    #[test]
+    fn hashed_inputs_errors() {
+        let (settings, mut snark) = hashed_inputs();
+        assert!(matches!(
+            verify_hashed_inputs(&[vec![f64::NAN]], &settings, &snark),
+            Err(InputHashError::Quantize { tensor: 0, .. })
+        ));
+        assert_eq!(
+            verify_hashed_inputs(&[vec![1.0], vec![2.0]], &settings, &snark),
+            Err(InputHashError::InputCount {
+                expected: 1,
+                provided: 2,
+            })
+        );
+        snark.instances.clear();
+        assert_eq!(
+            verify_hashed_inputs(&[vec![1.0]], &settings, &snark),
+            Err(InputHashError::MissingInstance { tensor: 0 })
+        );
+
+        let bundled = GraphSettings::from_json(include_str!("../../settings.json")).unwrap();
+        assert_eq!(
+            verify_hashed_inputs(&[vec![1.0]], &bundled, &snark),
+            Err(InputHashError::NotHashedPublic)
+        );
+    }
+}
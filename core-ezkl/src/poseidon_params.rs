@@ -0,0 +1,968 @@
+//! The constants of ezkl's Poseidon spec, generated by the reference implementation's
+//! `generate_parameters_grain.sage 1 0 254 2 8 56 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001`.
+
+use halo2curves::bn256::Fr as Fp;
+
+/// The round constants, one pair per round.
+pub const ROUND_CONSTANTS: [[Fp; 2]; 64] = [
+    [
+        Fp::from_raw([
+            0x6c7dc0dbd0abd7a7,
+            0xa71aa177534cdd1b,
+            0xfe1faaba294cba38,
+            0x09c46e9ec68e9bd4,
+        ]),
+        Fp::from_raw([
+            0x3c1d83ffa604cb81,
+            0xc5142b3ae405b834,
+            0x2a97ed937f3135cf,
+            0x0c0356530896eec4,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x317ea977cc154a30,
+            0xa00ea5aabd6268bd,
+            0x142e51182bb54cf4,
+            0x1e28a1d935698ad1,
+        ]),
+        Fp::from_raw([
+            0x4cf9e2b12b91251f,
+            0x0e5757c3e008db96,
+            0x080965db30e298e4,
+            0x27af2d831a9d2748,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x79aaf43545b74e03,
+            0x41291462f214cd08,
+            0x3a6a3cfe16ae175a,
+            0x1e6f11ce60fc8f51,
+        ]),
+        Fp::from_raw([
+            0xf719206268d142d3,
+            0x04462ed14c3613d8,
+            0x8541819cb681f0be,
+            0x2a67384d3bbd5e43,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x36408f5d5c9f45d0,
+            0xb985e381f0251889,
+            0x1609f8e12fbfecf0,
+            0x0b66fdf356093a61,
+        ]),
+        Fp::from_raw([
+            0xdaa6852dbdb09e21,
+            0x0b26c83cc5cebeed,
+            0x830c61093c2ade37,
+            0x012ee3ec1e78d470,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x2d108e7b445bb1b9,
+            0x6cd1c431b099b6bb,
+            0xfd88f67f8175e3fd,
+            0x0252ba5f6760bfbd,
+        ]),
+        Fp::from_raw([
+            0xef5aeaad7ca932f1,
+            0x54391a8935ff71d6,
+            0x6c6bec3cef542963,
+            0x179474cceca5ff67,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x7e1a2589bbed2b91,
+            0x9c1f974a264969b3,
+            0x9228ff4a503fd4ed,
+            0x2c24261379a51bfa,
+        ]),
+        Fp::from_raw([
+            0x53e66c0551801b05,
+            0xc2f63f5001fc0fc5,
+            0xac2f288bd0695b43,
+            0x1cc1d7b62692e63e,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x5d9eff5fd9c91b56,
+            0x00784dbf17fbacd0,
+            0xb2ed55f852979e96,
+            0x255059301aada98b,
+        ]),
+        Fp::from_raw([
+            0xb11c29ce7e59efd9,
+            0xaea24234970a8193,
+            0x79e1f5c0eccd32b3,
+            0x28437be3ac1cb2e4,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x338762c37f5f2043,
+            0x18548da8fb4f78d4,
+            0x1ca4fa6b53766eb1,
+            0x28216a442f2e1f71,
+        ]),
+        Fp::from_raw([
+            0x131f2377323482c9,
+            0xeee1efce03094581,
+            0x1f39f4e7056dd03f,
+            0x2c1f47cd17fa5adf,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x646b8566a621afc9,
+            0xd9dafca276638a63,
+            0x8632bcc9356ceb7d,
+            0x07abad02b7a5ebc4,
+        ]),
+        Fp::from_raw([
+            0x37da0c4d15f96c3c,
+            0x9429f90880a69cd1,
+            0x275b33ffaab51dfe,
+            0x0230264601ffdf29,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x717e5d66899aa0a9,
+            0xa864414557ee289e,
+            0xa0f168656497ca40,
+            0x1bc973054e51d905,
+        ]),
+        Fp::from_raw([
+            0x2a6b22288f0a67fc,
+            0xd249aff5c2d8421f,
+            0x206c3157e86341ed,
+            0x2e1c22f964435008,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xa70452bc2bba86b8,
+            0x9e8ea1598e46c9f7,
+            0x121c1d5f461bbc50,
+            0x1224f38df67c5378,
+        ]),
+        Fp::from_raw([
+            0x69d2989186cde20e,
+            0xd7bfe8cd9dfeda19,
+            0x9280b4bd9ed0068f,
+            0x02e4e69d8ba59e51,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x6d47e9735d98018e,
+            0x4f19ee364e653f07,
+            0x7f5df81fc04ff3ee,
+            0x1f1eccc34aaba013,
+        ]),
+        Fp::from_raw([
+            0xeacb8a4d4284f582,
+            0x1424448032cd1819,
+            0x74266c3039a9a731,
+            0x1672ad3d709a3539,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x1d2ed602df8c8fc7,
+            0xcda6961f284d2499,
+            0x56f44af5192b4ae9,
+            0x283e3fdc2c6e420c,
+        ]),
+        Fp::from_raw([
+            0x614fbd69ff394bcc,
+            0x683751f8fdff59d6,
+            0xd0db0957170fa013,
+            0x1c2a3d120c550ecf,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x96cb6b8177653fbd,
+            0x143a9a43773ea6f2,
+            0xf7897a7323456efe,
+            0x216f84877aac6172,
+        ]),
+        Fp::from_raw([
+            0x11a1f51552f94788,
+            0xceaa47ea61ca59a4,
+            0x64ba7e8e3e28d12b,
+            0x2c0d272becf2a757,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xcb4a6c3d89546f43,
+            0x170a5480abe0508f,
+            0x484ee7a74c454e9f,
+            0x16e34299865c0e28,
+        ]),
+        Fp::from_raw([
+            0x48cd939755488fc5,
+            0x772047765802290f,
+            0x375a232a6fb9cc71,
+            0x175ceba599e96f5b,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xd8c5ffbb44a1ee32,
+            0x6aa410bfbc354f54,
+            0xfead9e1758b02806,
+            0x0c7594440dc48c16,
+        ]),
+        Fp::from_raw([
+            0x92479882d919fd8d,
+            0x760e20013ccf912c,
+            0xc466db7d7eb6fd8f,
+            0x1a3c29bc39f21bb5,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x95c8eeabcd22e68f,
+            0x0855d349074f5a66,
+            0xc0986ea049b25340,
+            0x0ccfdd906f3426e5,
+        ]),
+        Fp::from_raw([
+            0xe0e699b67dd9e796,
+            0x66a7a8a3fd065b3c,
+            0x2bdb475ce6c94118,
+            0x14f6bc81d9f186f6,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x88edeb7386b97052,
+            0xcc099810c9c495c8,
+            0x9702ca70b2f6c5aa,
+            0x0962b82789fb3d12,
+        ]),
+        Fp::from_raw([
+            0xafef0c8f6a31a86d,
+            0x13284ab01ef02575,
+            0xbf20c79de25127bc,
+            0x1a880af7074d18b3,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x4c3012bb7ae9311b,
+            0x20af2924fc20ff3f,
+            0xcd5e77f0211c154b,
+            0x10cba18419a6a332,
+        ]),
+        Fp::from_raw([
+            0x756a2849f302f10d,
+            0xfa27b7319cae3406,
+            0xbdc76ba63a9eaca8,
+            0x057e62a9a8f89b3e,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xafa0413b44280cee,
+            0xb961303bbf65cff5,
+            0xd44adf5384b4988c,
+            0x287c971de91dc0ab,
+        ]),
+        Fp::from_raw([
+            0x6f7f7960e306891d,
+            0x1e562bc46d4aba4e,
+            0xb3bca9da0cca908f,
+            0x21df3388af1687bb,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x3eff8b560e1682b3,
+            0x789df8f70b498fd8,
+            0x3e25cc974d0934cd,
+            0x1be5c887d25bce70,
+        ]),
+        Fp::from_raw([
+            0x48d59c2706a0d5c1,
+            0xd2cb5d42fda5acea,
+            0x68117175cea2cd0d,
+            0x268da36f76e568fb,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xbd06460cc26a5ed6,
+            0xc5d8bb74135ebd05,
+            0xc609beaf5510ecec,
+            0x0e17ab091f6eae50,
+        ]),
+        Fp::from_raw([
+            0x040f5caa1f62af40,
+            0x91ef62d8cf83d270,
+            0x7aee535ab074a430,
+            0x04d727e728ffa0a6,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x2b15417d7e39ca6e,
+            0x33702ac10f1bfd86,
+            0x81b549762bc022ed,
+            0x0ddbd7bf9c293415,
+        ]),
+        Fp::from_raw([
+            0x8a29c49c8789654b,
+            0x34f5b0d1d3af9b58,
+            0x768162e82989c6c2,
+            0x2790eb3351621752,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x84b764206142f9e9,
+            0x395f3d9ab8b2fd09,
+            0x4471950193d8a570,
+            0x1e457c601a63b73e,
+        ]),
+        Fp::from_raw([
+            0xc4c686fc46e091b0,
+            0xfa90ecd0c43ff91f,
+            0x638d6ab2bbe7135f,
+            0x21ae64301dca9625,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x5858534eed8d350b,
+            0x854be9e3432e0955,
+            0x4da293166f494928,
+            0x0379f63c8ce3468d,
+        ]),
+        Fp::from_raw([
+            0x8c9f58a324c35049,
+            0xca0e4921a46686ac,
+            0x6a744a080809e054,
+            0x002d56420359d026,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x0fc2c5af963515a6,
+            0xda8d62459e21f409,
+            0x1d68b3cd32e10bbe,
+            0x123158e5965b5d9b,
+        ]),
+        Fp::from_raw([
+            0x60c80eb49cad9ec1,
+            0x0fbb2b6f52836d4e,
+            0x661d14bbf6cbe042,
+            0x0be29fc40847a941,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x233802f24fdf4c1a,
+            0x36db9d859cad5f9a,
+            0x57716142015a453c,
+            0x1ac96991dec2bb05,
+        ]),
+        Fp::from_raw([
+            0x51ca3355bcb0627e,
+            0x5e12c9fa97f18a92,
+            0x5f4964fc61d23b3e,
+            0x1596443f763dbcc2,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xd6d049eae3ba3212,
+            0xf1857d9f17e715ae,
+            0x6b2861d4ec3aeae0,
+            0x12e0bcd3654bdfa7,
+        ]),
+        Fp::from_raw([
+            0x04e6c76c7cf964ba,
+            0xceabac7f37154b19,
+            0x9ea73d4af9af2a50,
+            0x0fc92b4f1bbea82b,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x9c7e965233872762,
+            0xb14f7c7722236f4f,
+            0xd6f2e592a8013f40,
+            0x1f9c0b1610446442,
+        ]),
+        Fp::from_raw([
+            0x8d159f643dbbf4d3,
+            0x050d914da38b4c05,
+            0xf8cde06157a782f4,
+            0x0ebd74244ae72675,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x7a839839dccfc6d1,
+            0x3b0671e97346ee39,
+            0x69a9fafd4ab951c0,
+            0x2cb7f0ed39e16e9f,
+        ]),
+        Fp::from_raw([
+            0x90c72bca7352d9bf,
+            0xce761d0514ce5266,
+            0x5605443ee41bab20,
+            0x1a9d6e2ecff022cc,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x87da182d648ec72f,
+            0xd0c13326a9a7ba30,
+            0x5ea83c3bc44a9331,
+            0x2a115439607f335a,
+        ]),
+        Fp::from_raw([
+            0x9535c115c5a4c060,
+            0xe738b56305cd44f2,
+            0x15b8fa7aee3e3410,
+            0x23f9b6529b5d040d,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x260eb939f0e6e8a7,
+            0xa3ce97c16d58b68b,
+            0x249ac6ba484bb9c3,
+            0x05872c16db0f72a2,
+        ]),
+        Fp::from_raw([
+            0x2b624a7cdeddf6a7,
+            0x0219b6151d55b5c5,
+            0xca20fb80118075f4,
+            0x1300bdee08bb7824,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x072e4e7b7d52b376,
+            0x8d7ad29916d98cb1,
+            0xe63817863a8f6c28,
+            0x19b9b63d2f108e17,
+        ]),
+        Fp::from_raw([
+            0x24a20128481b4f7f,
+            0x13d1c88726b5ec42,
+            0xb5bda237668522f6,
+            0x015bee1357e3c015,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xea92c785b128ffd1,
+            0xfe1e1ce4bab218cb,
+            0x1b9707a4f1615e4e,
+            0x2953736e94bb6b9f,
+        ]),
+        Fp::from_raw([
+            0x4ce7266ed6608dfc,
+            0x851b98d372b45f54,
+            0x862f806180c0385f,
+            0x0b069353ba091618,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x4f588ac97d81f429,
+            0x55aeb7eb9306b64e,
+            0x15e4e0bcfb93817e,
+            0x304f74d461ccc131,
+        ]),
+        Fp::from_raw([
+            0xb8ee5415cde913fc,
+            0xaad2a164a4617a4c,
+            0xe8a33f5e77dfe4f5,
+            0x15bbf146ce9bca09,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xa9ff23859572c8c6,
+            0x9b8f4b850405c10c,
+            0x44901031487964ed,
+            0x0ab4dfe0c2742cde,
+        ]),
+        Fp::from_raw([
+            0x251de39f9639779a,
+            0xef5eedfea546dea9,
+            0x97f45f7649a19675,
+            0x0e32db320a044e31,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xa3078efa516da016,
+            0x6797733a82774896,
+            0xb27635a78b6888e6,
+            0x0a1756aa1f378ca4,
+        ]),
+        Fp::from_raw([
+            0x4254d6a2a25d93ef,
+            0x95e61d328f85efa9,
+            0x47fd17177f952ef8,
+            0x044c4a33b10f6934,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xd37b07b5466c4b8b,
+            0xfe0879d79a496891,
+            0xbe655b537f66f700,
+            0x2ed3611b725b8a70,
+        ]),
+        Fp::from_raw([
+            0xd8339ea7120858aa,
+            0xadfdeb9cfdd347b5,
+            0xc8ecc3d722aa2e0e,
+            0x1f9ba4e8bab7ce42,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xb74056f865c5d3da,
+            0xa38e82ac4502066d,
+            0x8f7ee907a84e518a,
+            0x1b233043052e8c28,
+        ]),
+        Fp::from_raw([
+            0xca2f97b020875954,
+            0x902053bfc0f14db0,
+            0x74031ab72bd55b4c,
+            0x2431e1cc164bb8d0,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xa791f273965801fd,
+            0xa13e322097583319,
+            0x30cd6953a0a7db45,
+            0x082f934c91f5aac3,
+        ]),
+        Fp::from_raw([
+            0x9ad6bb930c48997c,
+            0xc77245e2ae7cbe99,
+            0xa34be074315542a3,
+            0x2b9a0a223e7538b0,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xb0b589cc70214e7d,
+            0x8164163e75a8a00e,
+            0xceb85483b887a9be,
+            0x0e1cd91edd2cfa2c,
+        ]),
+        Fp::from_raw([
+            0x88d324601cebe2f9,
+            0x99774f19854d00f5,
+            0xc951f61477e36989,
+            0x2e1eac0f2bfdfd63,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x23d748115b500b83,
+            0x7345784d8efdb33c,
+            0x0c76158e769d6d15,
+            0x0cbfa95f37fb7406,
+        ]),
+        Fp::from_raw([
+            0x980c232dfa4a4f84,
+            0x76d991e3a77513d9,
+            0xd65ad49d8a61e9a6,
+            0x08f05b3be923ed44,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x25a2dd510c047ef6,
+            0xe7284925dc0758a3,
+            0x52bf8e21984d0443,
+            0x22719e2a070bcd08,
+        ]),
+        Fp::from_raw([
+            0xf41f62b2f26830c0,
+            0x7bdbf036119982c0,
+            0xc060f7fcc3a1ab4c,
+            0x041f596a9ee1cb2b,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x19fcdd0986b10f89,
+            0x021be1c2d0dc464a,
+            0x87628eb06f6b1d4c,
+            0x233fd35de1be520a,
+        ]),
+        Fp::from_raw([
+            0xefcb453c61c9c267,
+            0xd31e078aa1b4707e,
+            0x4325e0a423ebc810,
+            0x0524b46d1aa87a5e,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xcc4486237c515211,
+            0x4227bb954b0f3199,
+            0xce47fcac894b8582,
+            0x2c34f424c81e5716,
+        ]),
+        Fp::from_raw([
+            0xf33010327de4915e,
+            0x2dd2025b5457cc97,
+            0x207effc2b5541fb7,
+            0x0b5f2a4b63387819,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xaefac41fe05c659f,
+            0xc17435d2f57af6ce,
+            0xc5b72fe439d2cfd6,
+            0x22207856082ccc54,
+        ]),
+        Fp::from_raw([
+            0x27854048ce2c8171,
+            0xcdfb210194caf79f,
+            0x4e24159b7f8950b5,
+            0x24d57a8bf5da63fe,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x73919bb23b79396e,
+            0x374ad7097bb01a85,
+            0x3b371d75bd693f98,
+            0x0afab181fdd5e058,
+        ]),
+        Fp::from_raw([
+            0xf16290d62b1128ee,
+            0x76c0057194c16c0b,
+            0x998a52efac7cbd56,
+            0x2dba9b108f208772,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x5aff13e6bce420b3,
+            0xcbb83de0bd592b25,
+            0x56f881c788f53f83,
+            0x26349b66edb8b16f,
+        ]),
+        Fp::from_raw([
+            0x235288a3e6f137db,
+            0xd81a56d28ecc193b,
+            0x685e95f92339753a,
+            0x25af7ce0e5e10357,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x1f7c0187fe35011f,
+            0x70eed7aae88b2bff,
+            0xc094d6a55edd68b9,
+            0x25b4ce7bd2294390,
+        ]),
+        Fp::from_raw([
+            0x8cb9d54c1e02b631,
+            0xde9cef28ebdf30b1,
+            0x387e53f1908a88e5,
+            0x22c543f10f6c89ec,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xdf668e74882f87a9,
+            0x425e906a919d7a34,
+            0x4fc7908a9f191e1e,
+            0x0236f93e7789c472,
+        ]),
+        Fp::from_raw([
+            0x9cb497af980c4b52,
+            0x652bdae114eb0165,
+            0x0e7d27e37d05da99,
+            0x29350b401166ca01,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xee1260916652363f,
+            0x65edb75d844ebb89,
+            0x6bd31bbab547f75a,
+            0x0eed787d65820d3f,
+        ]),
+        Fp::from_raw([
+            0x1906f656f4de6fad,
+            0xfdcd0e99bd94297d,
+            0x036a753f520b3291,
+            0x07cc1170f13b46f2,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x2059435689e8acea,
+            0x908786d7f9f5d10c,
+            0xf49bcf613a3d30b1,
+            0x22b939233b1d7205,
+        ]),
+        Fp::from_raw([
+            0xadd650ace60ae5a6,
+            0x740f083a5aa85438,
+            0x8aad1dc8bc33e870,
+            0x01451762a0aab81c,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xe704fec0892fce89,
+            0xe32eaa61dec7da57,
+            0x61fabf1025d46d1f,
+            0x23506bb5d8727d44,
+        ]),
+        Fp::from_raw([
+            0x7f8bd68907355522,
+            0x2a3709531e1efea9,
+            0xbac06ae3f71bdd09,
+            0x2e484c44e838aea0,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x45418da26835b54c,
+            0xaf4a594545cedc25,
+            0x379e78c50bd2e42b,
+            0x0f4bc7d07ebafd64,
+        ]),
+        Fp::from_raw([
+            0xe620996d50d8e74e,
+            0x51582388725df460,
+            0xfa76637862faaee8,
+            0x1f4d3c8f6583e9e5,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x53eb9bcb48fe7389,
+            0xfae02abc7b681d91,
+            0x2660d07be0e4a988,
+            0x093514e0c70711f8,
+        ]),
+        Fp::from_raw([
+            0x4a58e0a347e153d8,
+            0x43ee83ece47228f2,
+            0x46699a2b5f3bc036,
+            0x1adab0c8e2b3bad3,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x1a22dbef9e80dad2,
+            0x378c1b94b8072bac,
+            0xd14709ebb474641a,
+            0x1672b1726057d99d,
+        ]),
+        Fp::from_raw([
+            0x30d47b239b479c14,
+            0xc5d8e2fae0acc4ee,
+            0x8f44f53fdcab468c,
+            0x1dfd53d4576af2e3,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xbc7f207753205c60,
+            0xe6d77d640f6fc3de,
+            0xa70a36263a37e17f,
+            0x0c6888a10b75b0f3,
+        ]),
+        Fp::from_raw([
+            0x85091ecca9d1e508,
+            0x611a61e00ee6848b,
+            0x92b34a7e77d12fe8,
+            0x1addb933a65be770,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x7935628e299d1791,
+            0xf638ff5425f0afff,
+            0x5c10ae18d1de933c,
+            0x00d7540dcd268a84,
+        ]),
+        Fp::from_raw([
+            0xd316939d20b82c0e,
+            0x26fedde4acd99db1,
+            0x01b2827a5664ca9c,
+            0x140c0e42687e9ead,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xc091e2ae56565984,
+            0xc20a0f9b24f8c5ed,
+            0x91ba89b8d13d1806,
+            0x2f0c3a115d4317d1,
+        ]),
+        Fp::from_raw([
+            0xd8c538a1dc958c61,
+            0x08a0cff670b22b82,
+            0x3006ed220cf9c810,
+            0x0c4ee778ff7c1455,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x27c3d7485de74c69,
+            0x9424ed26c0acc662,
+            0x3693f00440ccc360,
+            0x1704f2766d46f82c,
+        ]),
+        Fp::from_raw([
+            0x39b66fe9009c3cfa,
+            0xf0769c9f8544e402,
+            0xa7a02c1b51d244ab,
+            0x2f2d19cc3ea5d78e,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xd6c766a806fc6629,
+            0xdd7ee6cb9cfed9c7,
+            0x5053f112e2a8e8dc,
+            0x1ae03853b75fcaba,
+        ]),
+        Fp::from_raw([
+            0x4e41a86ddaf056d5,
+            0x3556921b2d6f014e,
+            0x51d131d0fa61aa5f,
+            0x0971aabf795241df,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x5f5c29f7bfe2f646,
+            0xda624f8380df1c87,
+            0x91d4cf6b6e0de73e,
+            0x1408c316e6014e1a,
+        ]),
+        Fp::from_raw([
+            0x41691f39822ef5bd,
+            0x6c89f1f773ef2853,
+            0x248abe42b543093b,
+            0x1667f3fe2edbe850,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x424c69576500fe37,
+            0x5b81718409e5c133,
+            0xa48b0a03557cdf91,
+            0x13bf7c5d0d2c4376,
+        ]),
+        Fp::from_raw([
+            0x19bc0ba743a62c2c,
+            0x024b95347856b797,
+            0x3016adf3d3533c24,
+            0x07620a6dfb0b6cec,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x1675de3e1982b4d0,
+            0x75d2959e2f322b73,
+            0x36a8ca08bdbdd8b0,
+            0x1574c7ef0c43545f,
+        ]),
+        Fp::from_raw([
+            0xc06e03a7ff8378f0,
+            0x5bd4184571c254fd,
+            0xfd567970a717ceec,
+            0x269e4b5b7a2eb21a,
+        ]),
+    ],
+];
+
+/// The MDS matrix.
+pub const MDS: [[Fp; 2]; 2] = [
+    [
+        Fp::from_raw([
+            0xbceca70bd2af7ad5,
+            0xaf07f38af8c952a7,
+            0xec10345351a23a3a,
+            0x066f6f85d6f68a85,
+        ]),
+        Fp::from_raw([
+            0x05462b9f8125b1e8,
+            0x20a7c02bbd8bea73,
+            0x7782e1509b1d0fdb,
+            0x2b9d4b4110c9ae99,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0xf573f431221f8ff9,
+            0xb6c09d557013fff1,
+            0x2bf67a4493cc262f,
+            0x0cc57cdbb08507d6,
+        ]),
+        Fp::from_raw([
+            0x21bcd147943203c8,
+            0xade857e86eb5c3a1,
+            0xa31a6ed69724e1ad,
+            0x1274e649a32ed355,
+        ]),
+    ],
+];
+
+/// The inverse of [MDS].
+pub const MDS_INV: [[Fp; 2]; 2] = [
+    [
+        Fp::from_raw([
+            0x8dbebd0fa8c53e66,
+            0x0554569d9b29d1ea,
+            0x70819ab1c7846f21,
+            0x13abec390ada7f43,
+        ]),
+        Fp::from_raw([
+            0xaaf6185b1a1e60fe,
+            0xbd521ead5dfe0345,
+            0x4c9862a1d97d1510,
+            0x1eb9e1dc19a33a62,
+        ]),
+    ],
+    [
+        Fp::from_raw([
+            0x763f7875036bcb02,
+            0x8ce5169030a2ad69,
+            0x601abc49fdad4f03,
+            0x0fc1c9394db89bb2,
+        ]),
+        Fp::from_raw([
+            0x8abced6bd147c8be,
+            0x2b7eac34345961bc,
+            0x9502054edc03e7b2,
+            0x16a9e98c493a902b,
+        ]),
+    ],
+];
@@ -1,4 +1,5 @@
 use std::error::Error;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use crate::utils::F32;
 use clap::Args;
@@ -7,7 +8,7 @@ use clap::Args;
 pub type Scale = i32;
 
 /// Parameters specific to a proving run
-#[derive(Debug, Args, Deserialize, Serialize, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Args, Deserialize, Serialize, Clone, PartialEq, PartialOrd, JsonSchema)]
 pub struct RunArgs {
     /// The tolerance for error on model outputs
     #[arg(short = 'T', long, default_value = "0")]
@@ -44,8 +45,26 @@ pub struct RunArgs {
     pub param_visibility: Visibility,
 }
 
+impl Default for RunArgs {
+    fn default() -> Self {
+        RunArgs {
+            tolerance: Tolerance::default(),
+            input_scale: 7,
+            param_scale: 7,
+            scale_rebase_multiplier: 1,
+            lookup_range: (-32768, 32768),
+            logrows: 17,
+            num_inner_cols: 2,
+            variables: vec![("batch_size".to_string(), 1)],
+            input_visibility: Visibility::Private,
+            output_visibility: Visibility::Public,
+            param_visibility: Visibility::Private,
+        }
+    }
+}
+
 /// Label enum to track whether model input, model parameters, and model output are public, private, or hashed
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default, JsonSchema)]
 pub enum Visibility {
     /// Mark an item as private to the prover (not in the proof submitted for verification)
     #[default]
@@ -63,13 +82,32 @@ pub enum Visibility {
     },
     /// Mark an item as publicly committed to (KZG commitment sent in the proof submitted for verification)
     KZGCommit,
+    /// Mark an item as encrypted to a public key with ElGamal (the ciphertext is sent in the proof submitted for verification)
+    Encrypted,
     /// assigned as a constant in the circuit
     Fixed,
 }
 
+impl Visibility {
+    /// Returns true if the values themselves are instances of the proof.
+    pub fn is_public(&self) -> bool {
+        matches!(self, Visibility::Public)
+    }
+
+    /// Returns true if the values are hashed with Poseidon.
+    pub fn is_hashed(&self) -> bool {
+        matches!(self, Visibility::Hashed { .. })
+    }
+
+    /// Returns true if the Poseidon hash of the values is an instance of the proof.
+    pub fn is_hashed_public(&self) -> bool {
+        matches!(self, Visibility::Hashed { hash_is_public: true, .. })
+    }
+}
+
 #[allow(missing_docs)]
 /// An enum representing the tolerance we can accept for the accumulated arguments, either absolute or percentage
-#[derive(Clone, Default, Debug, PartialEq, PartialOrd, Serialize, Deserialize, Copy)]
+#[derive(Clone, Default, Debug, PartialEq, PartialOrd, Serialize, Deserialize, Copy, JsonSchema)]
 pub struct Tolerance {
     pub val: f32,
     pub scale: F32,
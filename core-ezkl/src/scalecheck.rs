@@ -0,0 +1,480 @@
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::graphsettings::LookupOp;
+use crate::hybridop::HybridOp;
+use crate::model::{Model, NodeType, SupportedOp};
+use crate::runargs::RunArgs;
+use crate::supportedop::PolyOp;
+use crate::utils::{multiplier_to_scale, Scale};
+
+/// A problem with the fixed point scale of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScaleIssue {
+    /// The stored `out_scale` differs from the scale recomputed from the node's inputs.
+    Mismatch {
+        /// the recomputed scale
+        expected: Scale,
+        /// the stored scale
+        stored: Scale,
+    },
+    /// Tensors of differing scales are combined by an op that requires equal scales.
+    MixedInputScales {
+        /// the scales of the combined inputs
+        scales: Vec<Scale>,
+    },
+    /// The output scale exceeds `scale_rebase_multiplier * input_scale` but the node is not rebased.
+    MissingRebase {
+        /// the output scale of the node
+        scale: Scale,
+        /// the scale above which the output should have been rebased
+        threshold: Scale,
+    },
+    /// A `RebaseScale` node whose parameters disagree with its inner op or whose target is not the input scale.
+    InconsistentRebase(String),
+}
+
+/// A scale issue at a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleFinding {
+    /// The indices of the enclosing subgraphs (outermost first) followed by the node's index.
+    pub path: Vec<usize>,
+    /// The issue found.
+    pub issue: ScaleIssue,
+}
+
+/// The result of rechecking every node's output scale.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScaleReport {
+    /// Issues found, in graph order.
+    pub findings: Vec<ScaleFinding>,
+    /// Nodes that were not checked (eg. `Unknown` ops or nodes with missing inputs).
+    pub unchecked: Vec<Vec<usize>>,
+}
+
+impl ScaleReport {
+    /// Returns true if no issue was found.
+    pub fn is_consistent(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl fmt::Display for ScaleReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for finding in &self.findings {
+            write!(f, "node {:?}: ", finding.path)?;
+            match &finding.issue {
+                ScaleIssue::Mismatch { expected, stored } => {
+                    writeln!(f, "stored out_scale {} but inputs imply {}", stored, expected)?
+                }
+                ScaleIssue::MixedInputScales { scales } => {
+                    writeln!(f, "combines tensors of differing scales {:?}", scales)?
+                }
+                ScaleIssue::MissingRebase { scale, threshold } => writeln!(
+                    f,
+                    "output scale {} exceeds the rebase threshold {} but is not rebased",
+                    scale, threshold
+                )?,
+                ScaleIssue::InconsistentRebase(msg) => writeln!(f, "{}", msg)?,
+            }
+        }
+        for path in &self.unchecked {
+            writeln!(f, "node {:?}: unchecked", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recomputes every node's output scale from the stored scales of its inputs and checks it against the node's `out_scale`,
+/// flagging nodes that combine tensors of differing scales and nodes that should have been rebased.
+pub fn check_scales(model: &Model, run_args: &RunArgs) -> ScaleReport {
+    let mut report = ScaleReport::default();
+    check_graph(model, run_args, &[], &mut report);
+    report
+}
+
+fn check_graph(model: &Model, run_args: &RunArgs, prefix: &[usize], report: &mut ScaleReport) {
+    let graph = &model.graph;
+    let threshold = run_args.scale_rebase_multiplier as Scale * run_args.input_scale;
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+
+        let n = match node {
+            NodeType::Node(n) => n,
+            NodeType::SubGraph { model, .. } => {
+                check_graph(model, run_args, &path, report);
+                continue;
+            }
+        };
+
+        let input_scales = match n
+            .inputs
+            .iter()
+            .map(|o| graph.outlet_scale(o))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(scales) => scales,
+            None => {
+                report.unchecked.push(path);
+                continue;
+            }
+        };
+
+        let mut issues = vec![];
+        let expected = match &n.opkind {
+            SupportedOp::RebaseScale(rebase) => {
+                match expected_scale(&rebase.inner, &input_scales, run_args, &mut issues) {
+                    Some(inner) => {
+                        if inner != rebase.original_scale {
+                            issues.push(ScaleIssue::InconsistentRebase(format!(
+                                "rebases from scale {} but the inner op outputs scale {}",
+                                rebase.original_scale, inner
+                            )));
+                        }
+                        let implied = multiplier_to_scale(rebase.multiplier);
+                        if rebase.original_scale - rebase.target_scale != implied {
+                            issues.push(ScaleIssue::InconsistentRebase(format!(
+                                "divides by {} (scale {}) to go from scale {} to {}",
+                                rebase.multiplier, implied, rebase.original_scale, rebase.target_scale
+                            )));
+                        }
+                        if rebase.target_scale != run_args.input_scale {
+                            issues.push(ScaleIssue::InconsistentRebase(format!(
+                                "rebases to scale {} but outputs are rebased to the input scale {}",
+                                rebase.target_scale, run_args.input_scale
+                            )));
+                        }
+                        Some(rebase.target_scale)
+                    }
+                    None => None,
+                }
+            }
+            op => {
+                let scale = expected_scale(op, &input_scales, run_args, &mut issues);
+                // inputs and constants are quantized directly and are never rebased
+                let rebasable = !matches!(op, SupportedOp::Input(_) | SupportedOp::Constant(_));
+                if let Some(scale) = scale.filter(|_| rebasable) {
+                    if threshold > 0 && scale > threshold {
+                        issues.push(ScaleIssue::MissingRebase { scale, threshold });
+                    }
+                }
+                scale
+            }
+        };
+
+        match expected {
+            Some(expected) if expected != n.out_scale => issues.push(ScaleIssue::Mismatch {
+                expected,
+                stored: n.out_scale,
+            }),
+            Some(_) => {}
+            None => report.unchecked.push(path.clone()),
+        }
+
+        report
+            .findings
+            .extend(issues.into_iter().map(|issue| ScaleFinding {
+                path: path.clone(),
+                issue,
+            }));
+    }
+}
+
+fn require_equal(scales: &[Scale], issues: &mut Vec<ScaleIssue>) -> Option<Scale> {
+    let first = *scales.first()?;
+    if scales.iter().any(|s| *s != first) {
+        issues.push(ScaleIssue::MixedInputScales {
+            scales: scales.to_vec(),
+        });
+    }
+    Some(scales.iter().copied().max().unwrap_or(first))
+}
+
+/// Recomputes the output scale of an op from the scales of its inputs, recording any issue with the inputs.
+/// Returns `None` if the scale cannot be derived (eg. for `Unknown` ops).
+pub fn expected_scale(
+    op: &SupportedOp,
+    input_scales: &[Scale],
+    run_args: &RunArgs,
+    issues: &mut Vec<ScaleIssue>,
+) -> Option<Scale> {
+    let first = input_scales.first().copied();
+    match op {
+        SupportedOp::Input(input) => Some(input.scale),
+        SupportedOp::Constant(c) => Some(c.quantized_values.scale().unwrap_or(run_args.param_scale)),
+        SupportedOp::Unknown(_) => None,
+        SupportedOp::Rescaled(rescaled) => {
+            let mut scales = input_scales.to_vec();
+            for (i, mult) in &rescaled.scale {
+                if let Some(s) = scales.get_mut(*i) {
+                    *s += multiplier_to_scale(*mult as f64);
+                }
+            }
+            expected_scale(&rescaled.inner, &scales, run_args, issues)
+        }
+        SupportedOp::RebaseScale(rebase) => Some(rebase.target_scale),
+        SupportedOp::Linear(op) => poly_scale(op, input_scales, run_args, issues),
+        SupportedOp::Nonlinear(op) => match op {
+            LookupOp::GreaterThan { .. }
+            | LookupOp::LessThan { .. }
+            | LookupOp::GreaterThanEqual { .. }
+            | LookupOp::LessThanEqual { .. }
+            | LookupOp::Sign
+            | LookupOp::KroneckerDelta => Some(0),
+            _ => first,
+        },
+        SupportedOp::Hybrid(op) => match op {
+            HybridOp::Greater
+            | HybridOp::GreaterEqual
+            | HybridOp::Less
+            | HybridOp::LessEqual
+            | HybridOp::Equals => {
+                require_equal(input_scales, issues);
+                Some(0)
+            }
+            HybridOp::ReduceArgMax { .. } | HybridOp::ReduceArgMin { .. } | HybridOp::OneHot { .. } => Some(0),
+            HybridOp::Recip { output_scale, .. } => Some(multiplier_to_scale(output_scale.0 as f64)),
+            HybridOp::Softmax { scale, .. } => Some(multiplier_to_scale(scale.0 as f64)),
+            HybridOp::RangeCheck(_) => require_equal(input_scales, issues),
+            HybridOp::ScatterElements { .. } => {
+                // the scattered values must be at the scale of the tensor they are written into
+                let values: Vec<Scale> = input_scales
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != 1)
+                    .map(|(_, s)| *s)
+                    .collect();
+                require_equal(&values, issues)
+            }
+            _ => first,
+        },
+    }
+}
+
+fn poly_scale(
+    op: &PolyOp<Fp>,
+    input_scales: &[Scale],
+    run_args: &RunArgs,
+    issues: &mut Vec<ScaleIssue>,
+) -> Option<Scale> {
+    let first = input_scales.first().copied();
+    match op {
+        PolyOp::Add | PolyOp::Sub | PolyOp::Concat { .. } | PolyOp::And | PolyOp::Or | PolyOp::Xor => {
+            require_equal(input_scales, issues)
+        }
+        PolyOp::Iff => {
+            // the mask is boolean, the selected branches must agree
+            require_equal(input_scales.get(1..).unwrap_or(&[]), issues)
+        }
+        PolyOp::Mult | PolyOp::Einsum { .. } => Some(input_scales.iter().sum()),
+        PolyOp::Conv { kernel, bias, .. } | PolyOp::DeConv { kernel, bias, .. } => {
+            let out = first? + kernel.scale().unwrap_or(run_args.param_scale);
+            if let Some(bias_scale) = bias.as_ref().and_then(|b| b.scale()) {
+                if bias_scale != out {
+                    issues.push(ScaleIssue::MixedInputScales {
+                        scales: vec![out, bias_scale],
+                    });
+                }
+            }
+            Some(out)
+        }
+        PolyOp::Pow(exp) => Some(first? * *exp as Scale),
+        PolyOp::Prod { len_prod, .. } => Some(first? * *len_prod as Scale),
+        _ => first,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::supportedop::{RebaseScale, Rescaled, Unknown};
+    use crate::testutils::{constant, felts, input, model, node, subgraph};
+
+    fn run_args() -> RunArgs {
+        RunArgs {
+            input_scale: 2,
+            param_scale: 3,
+            ..RunArgs::default()
+        }
+    }
+
+    fn mult() -> SupportedOp {
+        SupportedOp::Linear(PolyOp::Mult)
+    }
+
+    /// `input * weight`, the product being stored as node 2.
+    fn product(op: SupportedOp, out_scale: Scale) -> Model {
+        model(
+            vec![
+                input(0, &[2], 2),
+                constant(1, &[0.5, 1.5], &[2], 3),
+                node(2, op, &[(0, 0), (1, 0)], &[2], out_scale),
+            ],
+            &[0],
+            &[(2, 0)],
+        )
+    }
+
+    fn rebase(multiplier: f64, target_scale: Scale) -> SupportedOp {
+        SupportedOp::RebaseScale(RebaseScale {
+            inner: Box::new(mult()),
+            multiplier,
+            target_scale,
+            original_scale: 5,
+        })
+    }
+
+    fn issues(report: &ScaleReport) -> Vec<&ScaleIssue> {
+        report.findings.iter().map(|f| &f.issue).collect()
+    }
+
+    #[test]
+    fn rebased_to_input_scale() {
+        let report = check_scales(&product(rebase(8.0, 2), 2), &run_args());
+        assert!(report.is_consistent(), "{}", report);
+    }
+
+    #[test]
+    fn missing_rebase() {
+        let report = check_scales(&product(mult(), 5), &run_args());
+        assert_eq!(issues(&report), vec![&ScaleIssue::MissingRebase { scale: 5, threshold: 2 }]);
+        // a larger multiplier raises the threshold
+        let run_args = RunArgs {
+            scale_rebase_multiplier: 3,
+            ..run_args()
+        };
+        assert!(check_scales(&product(mult(), 5), &run_args).is_consistent());
+    }
+
+    #[test]
+    fn rebase_to_another_scale() {
+        // consistent with its inner op, but rebased to 3 instead of the input scale
+        let report = check_scales(&product(rebase(4.0, 3), 3), &run_args());
+        assert_eq!(report.findings.len(), 1);
+        assert!(matches!(report.findings[0].issue, ScaleIssue::InconsistentRebase(_)));
+        assert_eq!(report.findings[0].path, vec![2]);
+    }
+
+    #[test]
+    fn rebase_with_wrong_multiplier() {
+        let report = check_scales(&product(rebase(2.0, 2), 2), &run_args());
+        assert_eq!(report.findings.len(), 1);
+        assert!(matches!(report.findings[0].issue, ScaleIssue::InconsistentRebase(_)));
+    }
+
+    #[test]
+    fn stored_scale_mismatch() {
+        let report = check_scales(&product(rebase(8.0, 2), 3), &run_args());
+        assert_eq!(issues(&report), vec![&ScaleIssue::Mismatch { expected: 2, stored: 3 }]);
+    }
+
+    #[test]
+    fn mixed_input_scales() {
+        let report = check_scales(&product(SupportedOp::Linear(PolyOp::Add), 3), &run_args());
+        let expected = [
+            ScaleIssue::MixedInputScales { scales: vec![2, 3] },
+            ScaleIssue::MissingRebase { scale: 3, threshold: 2 },
+        ];
+        assert_eq!(issues(&report), expected.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rescaled_and_comparison_scales() {
+        let run_args = RunArgs {
+            scale_rebase_multiplier: 2,
+            ..run_args()
+        };
+        // the input is lifted from scale 2 to the weight's scale 3 before the add
+        let add = SupportedOp::Rescaled(Rescaled {
+            inner: Box::new(SupportedOp::Linear(PolyOp::Add)),
+            scale: vec![(0, 2)],
+        });
+        let report = check_scales(&product(add, 3), &run_args);
+        assert!(report.is_consistent(), "{}", report);
+        // comparisons output booleans but still need equal input scales
+        let report = check_scales(&product(SupportedOp::Hybrid(HybridOp::Greater), 0), &run_args);
+        assert_eq!(issues(&report), vec![&ScaleIssue::MixedInputScales { scales: vec![2, 3] }]);
+    }
+
+    #[test]
+    fn conv_adds_the_kernel_scale() {
+        let run_args = RunArgs {
+            scale_rebase_multiplier: 3,
+            ..run_args()
+        };
+        let conv = |kernel_scale: Option<Scale>, bias_scale: Option<Scale>| {
+            let mut kernel = felts(&[1; 4], &[1, 1, 2, 2]);
+            if let Some(scale) = kernel_scale {
+                kernel.set_scale(scale);
+            }
+            let bias = bias_scale.map(|scale| {
+                let mut bias = felts(&[1], &[1]);
+                bias.set_scale(scale);
+                bias
+            });
+            let op = SupportedOp::Linear(PolyOp::Conv {
+                kernel,
+                bias,
+                padding: [(0, 0); 2],
+                stride: (1, 1),
+            });
+            model(
+                vec![input(0, &[1, 1, 3, 3], 2), node(1, op, &[(0, 0)], &[1, 1, 2, 2], 3)],
+                &[0],
+                &[(1, 0)],
+            )
+        };
+        assert!(check_scales(&conv(Some(1), Some(3)), &run_args).is_consistent());
+        let report = check_scales(&conv(Some(1), Some(4)), &run_args);
+        assert_eq!(issues(&report), vec![&ScaleIssue::MixedInputScales { scales: vec![3, 4] }]);
+        // a kernel without a scale is at the params scale
+        let report = check_scales(&conv(None, None), &run_args);
+        assert_eq!(issues(&report), vec![&ScaleIssue::Mismatch { expected: 5, stored: 3 }]);
+    }
+
+    #[test]
+    fn subgraph_nodes_are_checked_under_their_path() {
+        let body = model(
+            vec![
+                input(0, &[2], 2),
+                node(1, SupportedOp::Unknown(Unknown), &[(0, 0)], &[2], 2),
+                node(2, SupportedOp::Linear(PolyOp::Identity), &[(5, 0)], &[2], 2),
+                node(3, mult(), &[(0, 0), (0, 0)], &[2], 4),
+            ],
+            &[0],
+            &[(3, 0)],
+        );
+        let m = model(
+            vec![
+                input(0, &[2], 2),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Full],
+                    vec![vec![OutputMapping::Single {
+                        outlet: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![2]],
+                    vec![4],
+                ),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let report = check_scales(&m, &run_args());
+        assert_eq!(
+            report.findings,
+            vec![ScaleFinding {
+                path: vec![1, 3],
+                issue: ScaleIssue::MissingRebase { scale: 4, threshold: 2 },
+            }]
+        );
+        // unknown ops and nodes reading a missing outlet can't be checked
+        assert_eq!(report.unchecked, vec![vec![1, 1], vec![1, 2]]);
+    }
+}
@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+use halo2curves::bn256::Fr as Fp;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+
+use crate::graphsettings::GraphSettings;
+use crate::graphwitness::GraphWitness;
+use crate::model::Model;
+use crate::runargs::RunArgs;
+use crate::snark::Snark;
+
+/// The JSON layout of a field element: the four 64 bit limbs of its Montgomery form, least significant first. The limbs
+/// hold `x * 2^256 mod p` rather than `x`, eg. 1 is serialized as `2^256 mod p`.
+pub type FeltSchema = [u64; 4];
+
+/// The JSON layout of a BN254 G1 point in affine coordinates, each in the Montgomery form of [FeltSchema].
+#[derive(Debug, JsonSchema)]
+pub struct G1AffineSchema {
+    /// the x coordinate
+    pub x: FeltSchema,
+    /// the y coordinate
+    pub y: FeltSchema,
+}
+
+/// The files a JSON schema can be generated for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SchemaTarget {
+    /// `settings.json`
+    Settings,
+    /// the run args embedded in the settings
+    RunArgs,
+    /// `proof.json`
+    Proof,
+    /// a witness generated by `ezkl gen-witness`
+    Witness,
+    /// a serialized model
+    Model,
+}
+
+/// Generates the JSON schema of what core-ezkl accepts for a file.
+pub fn schema(target: SchemaTarget) -> RootSchema {
+    match target {
+        SchemaTarget::Settings => schema_for!(GraphSettings),
+        SchemaTarget::RunArgs => schema_for!(RunArgs),
+        SchemaTarget::Proof => schema_for!(Snark<Fp>),
+        SchemaTarget::Witness => schema_for!(GraphWitness),
+        SchemaTarget::Model => schema_for!(Model),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+
+    fn validate(target: SchemaTarget, json: &str) -> Vec<String> {
+        let schema = serde_json::to_value(schema(target)).unwrap();
+        let compiled = jsonschema::JSONSchema::compile(&schema).unwrap();
+        let instance: serde_json::Value = serde_json::from_str(json).unwrap();
+        let errors = compiled.validate(&instance).err().into_iter().flatten();
+        errors.map(|e| format!("{}: {}", e.instance_path, e)).collect()
+    }
+
+    #[test]
+    fn bundled_files_validate() {
+        assert_eq!(validate(SchemaTarget::Settings, include_str!("../../settings.json")), Vec::<String>::new());
+        assert_eq!(validate(SchemaTarget::Proof, include_str!("../../proof.json")), Vec::<String>::new());
+        let settings: serde_json::Value = serde_json::from_str(include_str!("../../settings.json")).unwrap();
+        assert_eq!(validate(SchemaTarget::RunArgs, &settings["run_args"].to_string()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        let mut settings: serde_json::Value = serde_json::from_str(include_str!("../../settings.json")).unwrap();
+        settings["run_args"]["logrows"] = serde_json::json!("ten");
+        assert!(!validate(SchemaTarget::Settings, &settings.to_string()).is_empty());
+        assert!(!validate(SchemaTarget::Proof, r#"{"instances": [[[1, 2, 3]]], "proof": []}"#).is_empty());
+    }
+
+    #[test]
+    fn felts_are_serialized_in_montgomery_form() {
+        let one = serde_json::to_value(i128_to_felt::<Fp>(1)).unwrap();
+        assert_eq!(
+            one,
+            serde_json::json!([
+                12436184717236109307u64,
+                3962172157175319849u64,
+                7381016538464732718u64,
+                1011752739694698287u64
+            ])
+        );
+    }
+}
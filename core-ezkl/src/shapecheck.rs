@@ -0,0 +1,561 @@
+use std::fmt;
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::einsum::EinsumEquation;
+use crate::hybridop::HybridOp;
+use crate::model::{num_iterations, InputMapping, Model, NodeType, OutputMapping, SupportedOp};
+use crate::supportedop::PolyOp;
+use crate::tensor::{get_broadcasted_shape, TensorError};
+use crate::tensorops::{conv_output_dims, deconv_output_dims};
+
+/// A node whose stored `out_dims` disagree with the dims recomputed from its inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    /// The indices of the enclosing subgraphs (outermost first) followed by the node's index.
+    pub path: Vec<usize>,
+    /// The output dims recomputed from the node's inputs and parameters.
+    pub expected: Vec<usize>,
+    /// The output dims stored in the serialized model.
+    pub stored: Vec<usize>,
+}
+
+/// The result of rechecking every node's output dims.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShapeReport {
+    /// Nodes whose stored dims disagree with the recomputed ones.
+    pub mismatches: Vec<ShapeMismatch>,
+    /// Nodes whose output dims could not be recomputed, with the reason.
+    pub errors: Vec<(Vec<usize>, String)>,
+    /// Nodes that were not checked (eg. `Unknown` ops).
+    pub unchecked: Vec<Vec<usize>>,
+}
+
+impl ShapeReport {
+    /// Returns true if no node is inconsistent.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty() && self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for ShapeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for m in &self.mismatches {
+            writeln!(
+                f,
+                "node {:?}: stored out_dims {:?} but inputs imply {:?}",
+                m.path, m.stored, m.expected
+            )?;
+        }
+        for (path, err) in &self.errors {
+            writeln!(f, "node {:?}: {}", path, err)?;
+        }
+        for path in &self.unchecked {
+            writeln!(f, "node {:?}: unchecked", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recomputes every node's output dims from the stored dims of its inputs and compares them with the node's stored `out_dims`.
+/// Each node is checked locally, so a single inconsistent node is reported once rather than cascading to its consumers.
+pub fn check_shapes(model: &Model) -> ShapeReport {
+    let mut report = ShapeReport::default();
+    check_graph(model, &[], &mut report);
+    report
+}
+
+fn check_graph(model: &Model, prefix: &[usize], report: &mut ShapeReport) {
+    let graph = &model.graph;
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+
+        let input_dims = match node
+            .inputs()
+            .iter()
+            .map(|o| graph.outlet_dims(o).ok_or(*o))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(dims) => dims,
+            Err(outlet) => {
+                report
+                    .errors
+                    .push((path, format!("input outlet {:?} does not exist", outlet)));
+                continue;
+            }
+        };
+
+        match node {
+            NodeType::Node(n) => match infer_out_dims(&n.opkind, &input_dims) {
+                Ok(Some(expected)) => {
+                    if expected != n.out_dims {
+                        report.mismatches.push(ShapeMismatch {
+                            path,
+                            expected,
+                            stored: n.out_dims.clone(),
+                        });
+                    }
+                }
+                Ok(None) => report.unchecked.push(path),
+                Err(e) => report.errors.push((path, e.to_string())),
+            },
+            NodeType::SubGraph {
+                model: body,
+                input_mappings,
+                output_mappings,
+                out_dims,
+                ..
+            } => {
+                check_graph(body, &path, report);
+                match subgraph_out_dims(body, input_mappings, output_mappings, &input_dims, out_dims.len()) {
+                    Ok(expected) => {
+                        for (expected, stored) in expected.into_iter().zip(out_dims) {
+                            if expected != *stored {
+                                report.mismatches.push(ShapeMismatch {
+                                    path: path.clone(),
+                                    expected,
+                                    stored: stored.clone(),
+                                });
+                            }
+                        }
+                    }
+                    Err(e) => report.errors.push((path, e.to_string())),
+                }
+            }
+        }
+    }
+}
+
+/// Recomputes the dims of a subgraph's outputs from the dims of its body's outputs: `Single` outputs keep the body
+/// output's dims and `Stacked` outputs concatenate one body output per iteration along their axis.
+pub fn subgraph_out_dims(
+    body: &Model,
+    input_mappings: &[InputMapping],
+    output_mappings: &[Vec<OutputMapping>],
+    input_dims: &[Vec<usize>],
+    num_outlets: usize,
+) -> Result<Vec<Vec<usize>>, TensorError> {
+    let num_iter = num_iterations(input_mappings, input_dims)?;
+    let mut out_dims = vec![None; num_outlets];
+    for (mappings, body_output) in output_mappings.iter().zip(&body.graph.outputs) {
+        let dims = body.graph.outlet_dims(body_output).ok_or_else(|| {
+            TensorError::DimMismatch(format!("body output {:?} does not exist", body_output))
+        })?;
+        for mapping in mappings {
+            let (outlet, dims) = match mapping {
+                OutputMapping::Single { outlet, .. } => (*outlet, dims.clone()),
+                OutputMapping::Stacked { outlet, axis, .. } => {
+                    let mut dims = dims.clone();
+                    check_axis(&dims, *axis)?;
+                    dims[*axis] *= num_iter;
+                    (*outlet, dims)
+                }
+            };
+            let slot = out_dims.get_mut(outlet).ok_or_else(|| {
+                TensorError::DimMismatch(format!("output mapping to outlet {} of {}", outlet, num_outlets))
+            })?;
+            *slot = Some(dims);
+        }
+    }
+    out_dims
+        .into_iter()
+        .enumerate()
+        .map(|(outlet, dims)| {
+            dims.ok_or_else(|| TensorError::DimMismatch(format!("no output mapping to outlet {}", outlet)))
+        })
+        .collect()
+}
+
+/// Recomputes the output dims of an op from the dims of its inputs.
+/// Returns `None` for ops whose output dims cannot be derived from their inputs (inputs and unknown ops).
+pub fn infer_out_dims(op: &SupportedOp, inputs: &[Vec<usize>]) -> Result<Option<Vec<usize>>, TensorError> {
+    let dims = match op {
+        SupportedOp::Linear(op) => poly_out_dims(op, inputs)?,
+        SupportedOp::Nonlinear(_) => input(inputs, 0)?.to_vec(),
+        SupportedOp::Hybrid(op) => hybrid_out_dims(op, inputs)?,
+        SupportedOp::Constant(c) => c.quantized_values.dims().to_vec(),
+        SupportedOp::Input(_) | SupportedOp::Unknown(_) => return Ok(None),
+        SupportedOp::Rescaled(op) => return infer_out_dims(&op.inner, inputs),
+        SupportedOp::RebaseScale(op) => return infer_out_dims(&op.inner, inputs),
+    };
+    Ok(Some(dims))
+}
+
+fn input(inputs: &[Vec<usize>], i: usize) -> Result<&[usize], TensorError> {
+    inputs
+        .get(i)
+        .map(|d| d.as_slice())
+        .ok_or_else(|| TensorError::DimMismatch(format!("missing input {}", i)))
+}
+
+fn check_axis(dims: &[usize], axis: usize) -> Result<(), TensorError> {
+    if axis >= dims.len() {
+        return Err(TensorError::AxisError(format!(
+            "axis {} out of range for dims {:?}",
+            axis, dims
+        )));
+    }
+    Ok(())
+}
+
+fn reduce_dims(dims: &[usize], axes: &[usize]) -> Result<Vec<usize>, TensorError> {
+    for axis in axes {
+        check_axis(dims, *axis)?;
+    }
+    Ok(dims
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if axes.contains(&i) { 1 } else { *d })
+        .collect())
+}
+
+fn broadcast_all(inputs: &[Vec<usize>]) -> Result<Vec<usize>, TensorError> {
+    let mut shape = input(inputs, 0)?.to_vec();
+    for dims in &inputs[1..] {
+        shape = get_broadcasted_shape(&shape, dims)?;
+    }
+    Ok(shape)
+}
+
+fn pool_dims(
+    dims: &[usize],
+    padding: [(usize, usize); 2],
+    stride: (usize, usize),
+    window: (usize, usize),
+) -> Result<Vec<usize>, TensorError> {
+    // a pool is a depthwise conv with a window-sized kernel
+    let channels = dims.get(1).copied().unwrap_or(0);
+    let mut out = conv_output_dims(dims, &[channels, 1, window.0, window.1], padding, stride)?;
+    out[1] = channels;
+    Ok(out)
+}
+
+fn poly_out_dims(op: &PolyOp<Fp>, inputs: &[Vec<usize>]) -> Result<Vec<usize>, TensorError> {
+    Ok(match op {
+        PolyOp::MultiBroadcastTo { shape } => {
+            get_broadcasted_shape(input(inputs, 0)?, shape)?;
+            shape.clone()
+        }
+        PolyOp::Einsum { equation } => {
+            let shapes: Vec<&[usize]> = inputs.iter().map(|d| d.as_slice()).collect();
+            EinsumEquation::parse(equation)?.output_dims(&shapes)?
+        }
+        PolyOp::Conv {
+            kernel,
+            padding,
+            stride,
+            ..
+        } => conv_output_dims(input(inputs, 0)?, kernel.dims(), *padding, *stride)?,
+        PolyOp::DeConv {
+            kernel,
+            padding,
+            output_padding,
+            stride,
+            ..
+        } => deconv_output_dims(input(inputs, 0)?, kernel.dims(), *padding, *output_padding, *stride)?,
+        PolyOp::Downsample {
+            axis,
+            stride,
+            modulo,
+        } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            check_axis(&dims, *axis)?;
+            if *stride == 0 {
+                return Err(TensorError::DimError("downsample stride must be non-zero".to_string()));
+            }
+            dims[*axis] = dims[*axis].saturating_sub(*modulo).div_ceil(*stride);
+            dims
+        }
+        PolyOp::Add
+        | PolyOp::Sub
+        | PolyOp::Mult
+        | PolyOp::Iff
+        | PolyOp::And
+        | PolyOp::Or
+        | PolyOp::Xor => broadcast_all(inputs)?,
+        PolyOp::Neg | PolyOp::Identity | PolyOp::Not | PolyOp::Pow(_) => input(inputs, 0)?.to_vec(),
+        PolyOp::Reshape(new_dims) | PolyOp::Flatten(new_dims) => {
+            let len: usize = input(inputs, 0)?.iter().product();
+            if new_dims.iter().product::<usize>() != len {
+                return Err(TensorError::DimError(format!(
+                    "cannot reshape {:?} to {:?}",
+                    inputs[0], new_dims
+                )));
+            }
+            new_dims.clone()
+        }
+        PolyOp::MoveAxis {
+            source,
+            destination,
+        } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            check_axis(&dims, *source)?;
+            check_axis(&dims, *destination)?;
+            let d = dims.remove(*source);
+            dims.insert(*destination, d);
+            dims
+        }
+        PolyOp::Pad(padding) => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            let rank = dims.len();
+            if rank < 2 {
+                return Err(TensorError::DimError(format!("cannot pad dims {:?}", dims)));
+            }
+            dims[rank - 2] += padding[0].0 + padding[0].1;
+            dims[rank - 1] += padding[1].0 + padding[1].1;
+            dims
+        }
+        PolyOp::Sum { axes } | PolyOp::Prod { axes, .. } => reduce_dims(input(inputs, 0)?, axes)?,
+        PolyOp::Pack(_, _) => vec![1],
+        PolyOp::GlobalSumPool => {
+            let dims = input(inputs, 0)?;
+            reduce_dims(dims, &(2..dims.len()).collect::<Vec<_>>())?
+        }
+        PolyOp::Concat { axis } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            check_axis(&dims, *axis)?;
+            for other in &inputs[1..] {
+                let compatible = other.len() == dims.len()
+                    && other
+                        .iter()
+                        .zip(dims.iter())
+                        .enumerate()
+                        .all(|(i, (a, b))| i == *axis || a == b);
+                if !compatible {
+                    return Err(TensorError::DimMismatch(format!(
+                        "concat of {:?} and {:?} along axis {}",
+                        dims, other, axis
+                    )));
+                }
+                dims[*axis] += other[*axis];
+            }
+            dims
+        }
+        PolyOp::Slice { axis, start, end } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            check_axis(&dims, *axis)?;
+            if start > end || *end > dims[*axis] {
+                return Err(TensorError::DimError(format!(
+                    "slice {}..{} of axis {} with dims {:?}",
+                    start, end, axis, dims
+                )));
+            }
+            dims[*axis] = end - start;
+            dims
+        }
+        PolyOp::Resize { scale_factor } => {
+            let dims = input(inputs, 0)?;
+            if scale_factor.len() != dims.len() {
+                return Err(TensorError::DimMismatch(format!(
+                    "resize of {:?} by {:?}",
+                    dims, scale_factor
+                )));
+            }
+            dims.iter().zip(scale_factor).map(|(d, s)| d * s).collect()
+        }
+    })
+}
+
+fn hybrid_out_dims(op: &HybridOp, inputs: &[Vec<usize>]) -> Result<Vec<usize>, TensorError> {
+    Ok(match op {
+        HybridOp::Recip { .. }
+        | HybridOp::Div { .. }
+        | HybridOp::Softmax { .. }
+        | HybridOp::RangeCheck(_) => input(inputs, 0)?.to_vec(),
+        HybridOp::ReduceMax { axes } | HybridOp::ReduceMin { axes } => reduce_dims(input(inputs, 0)?, axes)?,
+        HybridOp::ReduceArgMax { dim } | HybridOp::ReduceArgMin { dim } => {
+            reduce_dims(input(inputs, 0)?, &[*dim])?
+        }
+        HybridOp::SumPool {
+            padding,
+            stride,
+            kernel_shape,
+        } => pool_dims(input(inputs, 0)?, *padding, *stride, *kernel_shape)?,
+        HybridOp::MaxPool2d {
+            padding,
+            stride,
+            pool_dims: window,
+        } => pool_dims(input(inputs, 0)?, *padding, *stride, *window)?,
+        HybridOp::Greater
+        | HybridOp::GreaterEqual
+        | HybridOp::Less
+        | HybridOp::LessEqual
+        | HybridOp::Equals => broadcast_all(inputs)?,
+        HybridOp::Gather { dim, constant_idx } => {
+            let dims = input(inputs, 0)?;
+            check_axis(dims, *dim)?;
+            let idx_dims = match constant_idx {
+                Some(idx) => idx.dims().to_vec(),
+                None => input(inputs, 1)?.to_vec(),
+            };
+            let mut out = dims[..*dim].to_vec();
+            out.extend(idx_dims);
+            out.extend_from_slice(&dims[dim + 1..]);
+            out
+        }
+        HybridOp::TopK { dim, k, .. } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            check_axis(&dims, *dim)?;
+            if *k > dims[*dim] {
+                return Err(TensorError::DimError(format!(
+                    "top {} of axis {} with dims {:?}",
+                    k, dim, dims
+                )));
+            }
+            dims[*dim] = *k;
+            dims
+        }
+        HybridOp::OneHot { dim, num_classes } => {
+            let mut dims = input(inputs, 0)?.to_vec();
+            if *dim > dims.len() {
+                return Err(TensorError::AxisError(format!(
+                    "one hot axis {} out of range for dims {:?}",
+                    dim, dims
+                )));
+            }
+            dims.insert(*dim, *num_classes);
+            dims
+        }
+        HybridOp::GatherElements { dim, constant_idx } => {
+            check_axis(input(inputs, 0)?, *dim)?;
+            match constant_idx {
+                Some(idx) => idx.dims().to_vec(),
+                None => input(inputs, 1)?.to_vec(),
+            }
+        }
+        HybridOp::ScatterElements { dim, .. } => {
+            let dims = input(inputs, 0)?;
+            check_axis(dims, *dim)?;
+            dims.to_vec()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{input, model, node, subgraph};
+
+    /// A Scan over the rows of a [4, 2] input, summing each row and stacking the sums.
+    fn scan(out_dims: Vec<Vec<usize>>) -> Model {
+        let body = model(
+            vec![
+                input(0, &[1, 2], 0),
+                node(1, SupportedOp::Linear(PolyOp::Sum { axes: vec![1] }), &[(0, 0)], &[1, 1], 0),
+            ],
+            &[0],
+            &[(1, 0), (0, 0)],
+        );
+        model(
+            vec![
+                input(0, &[4, 2], 0),
+                subgraph(
+                    1,
+                    body,
+                    &[(0, 0)],
+                    vec![InputMapping::Stacked { axis: 0, chunk: 1 }],
+                    vec![
+                        vec![OutputMapping::Stacked {
+                            outlet: 1,
+                            axis: 0,
+                            is_state: false,
+                        }],
+                        vec![OutputMapping::Single {
+                            outlet: 0,
+                            is_state: false,
+                        }],
+                    ],
+                    out_dims,
+                    vec![0, 0],
+                ),
+            ],
+            &[0],
+            &[(1, 0), (1, 1)],
+        )
+    }
+
+    #[test]
+    fn consistent_model() {
+        let report = check_shapes(&scan(vec![vec![1, 2], vec![4, 1]]));
+        assert!(report.is_consistent(), "{}", report);
+        // the inputs' dims are not recomputed
+        assert_eq!(report.unchecked, vec![vec![0], vec![1, 0]]);
+    }
+
+    #[test]
+    fn node_mismatch() {
+        let m = model(
+            vec![
+                input(0, &[2, 3], 0),
+                node(1, SupportedOp::Linear(PolyOp::Sum { axes: vec![0] }), &[(0, 0)], &[2, 3], 0),
+                node(2, SupportedOp::Linear(PolyOp::Reshape(vec![3, 2])), &[(0, 0)], &[3, 2], 0),
+            ],
+            &[0],
+            &[(1, 0)],
+        );
+        let report = check_shapes(&m);
+        assert_eq!(
+            report.mismatches,
+            vec![ShapeMismatch {
+                path: vec![1],
+                expected: vec![1, 3],
+                stored: vec![2, 3],
+            }]
+        );
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn subgraph_out_dims_mismatch() {
+        // the stacked outlet must hold one row per iteration
+        let report = check_shapes(&scan(vec![vec![1, 2], vec![1, 1]]));
+        assert_eq!(
+            report.mismatches,
+            vec![ShapeMismatch {
+                path: vec![1],
+                expected: vec![4, 1],
+                stored: vec![1, 1],
+            }]
+        );
+    }
+
+    #[test]
+    fn subgraph_unmapped_outlet() {
+        let report = check_shapes(&scan(vec![vec![1, 2], vec![4, 1], vec![1]]));
+        assert!(report.mismatches.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, vec![1]);
+    }
+
+    #[test]
+    fn stacked_inputs_agree_on_iterations() {
+        let stacked = vec![
+            InputMapping::Stacked { axis: 0, chunk: 1 },
+            InputMapping::Full,
+            InputMapping::Stacked { axis: 1, chunk: 2 },
+        ];
+        assert_eq!(num_iterations(&[InputMapping::Full], &[vec![4, 2]]), Ok(1));
+        assert_eq!(num_iterations(&stacked, &[vec![4, 2], vec![5], vec![3, 7]]), Ok(4));
+        assert!(matches!(
+            num_iterations(&stacked, &[vec![4, 2], vec![5], vec![3, 6]]),
+            Err(TensorError::DimMismatch(_))
+        ));
+        assert!(matches!(
+            num_iterations(&stacked, &[vec![4, 2], vec![5], vec![3]]),
+            Err(TensorError::AxisError(_))
+        ));
+    }
+
+    #[test]
+    fn missing_input_outlet() {
+        let m = model(
+            vec![node(1, SupportedOp::Linear(PolyOp::Identity), &[(0, 0)], &[1], 0)],
+            &[],
+            &[(1, 0)],
+        );
+        let report = check_shapes(&m);
+        assert_eq!(report.errors.len(), 1);
+        assert!(!report.is_consistent());
+    }
+}
@@ -1,15 +1,21 @@
 use serde::de::DeserializeOwned;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use halo2curves::serde::SerdeObject;
 use halo2curves::ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
 
+use crate::schema::FeltSchema;
+
 
 /// An application snark with proof and instance variables ready for aggregation (raw field element)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Snark", bound = "F: PrimeField + SerdeObject")]
 pub struct Snark<F: PrimeField + SerdeObject> {
+    /// the protocol of the snark
     #[serde(skip)]
     pub protocol: String,
     /// public instances of the snark
+    #[schemars(with = "Vec<Vec<FeltSchema>>")]
     pub instances: Vec<Vec<F>>,
     /// the proof
     pub proof: Vec<u8>,
@@ -23,6 +29,7 @@ pub struct Snark<F: PrimeField + SerdeObject> {
 
 impl<F: PrimeField + SerdeObject + Serialize + FromUniformBytes<64> + DeserializeOwned> Snark<F>
 {
+    /// Parses a snark from its JSON.
     pub fn load(
         proof_path: &str,
     ) -> Self 
@@ -34,12 +41,13 @@ impl<F: PrimeField + SerdeObject + Serialize + FromUniformBytes<64> + Deserializ
     }
 }
 impl<Scalar:  SerdeObject + PrimeField + FromUniformBytes<64> + WithSmallOrderMulGroup<3> + Ord + Serialize + DeserializeOwned> Snark<Scalar> {
+    /// Prints the instances of the snark.
     pub fn format_instances(&self)
     {
         let pi_inner = self
         .instances
         .iter()
-        .map(|e| e.deref())
+        .map(|e| e.as_slice())
         .collect::<Vec<&[Scalar]>>();
         let instances: &[&[&[Scalar]]] = &[&pi_inner];
         println!("Instances: {:?}", &instances);
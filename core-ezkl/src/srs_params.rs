@@ -1,36 +1,42 @@
-use graphsettings::GraphSettings;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
 use halo2curves::bn256::Bn256;
-use halo2_proofs::poly::commitment::{Params, CommitmentScheme};
-use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
 
+use crate::graphsettings::GraphSettings;
 
-fn get_log_rows(settings_path: &str) -> u32 {
-    let settings: GraphSettings = serde_json::from_str(&settings_path).unwrap();
-    settings.run_args.logrows
+fn get_log_rows(settings_path: impl AsRef<Path>) -> Result<u32, Box<dyn Error>> {
+    let settings = GraphSettings::load(settings_path)?;
+    Ok(settings.run_args.logrows)
 }
 
-pub fn get_verifier_params(settings_path: &str, srs_path: &str) -> ParamsKZG<Bn256> {
-    // read in log_rows from teh settings struct
-    let logrows = get_log_rows(settings_path);
+/// Reads the KZG params at `srs_path`, downsized to the `logrows` of the settings at `settings_path`, and returns the
+/// verifier params.
+pub fn get_verifier_params(
+    settings_path: impl AsRef<Path>,
+    srs_path: impl AsRef<Path>,
+) -> Result<ParamsKZG<Bn256>, Box<dyn Error>> {
+    // read in log_rows from the settings struct
+    let logrows = get_log_rows(settings_path)?;
 
     // read in the params binary file as bytes
-    let mut f = File::open(srs_path).expect("File not found");
-    let metadata = fs::metadata(srs_path).expect("unable to read metadata");
-    let mut buf: Vec<u8> = vec![0; metadata.len() as usize];
-    f.read(&mut buf).expect("Buffer overflow");
+    let buf = fs::read(srs_path)?;
 
     // deserialize the params and downsize if necessary
-    let mut params: ParamsKZG<Bn256> = Params::read::<_>(&mut &buf[..]).unwrap();
+    let mut params: ParamsKZG<Bn256> = Params::read::<_>(&mut &buf[..])?;
     if logrows < params.k() {
-       params.downsize(logrows);
+        params.downsize(logrows);
     }
-    let vparams = params.verifier_params();
-    vparams.clone()
+    Ok(params.verifier_params().clone())
 }
 
+/// Serializes the verifier params to bytes.
 pub fn v_params_to_bytes(params: ParamsKZG<Bn256>) -> Vec<u8> {
     // obtain the verifier params and serialize to bytes
     let mut v_params_bytes: Vec<u8> = Vec::new();
     let _ = <ParamsKZG<_> as Params<_>>::write(&params, &mut v_params_bytes);
     v_params_bytes
-}
\ No newline at end of file
+}
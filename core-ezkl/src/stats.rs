@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::graphsettings::{GraphSettings, LookupOp};
+use crate::lookupcheck::check_lookups;
+use crate::model::{Model, NodeType, ParsedNodes, SupportedOp};
+use crate::supportedop::PolyOp;
+
+/// Summary statistics of a model, for capacity planning.
+#[derive(Clone, Debug, Default)]
+pub struct ModelStats {
+    /// The number of nodes, including the nodes of subgraphs and the subgraphs themselves.
+    pub num_nodes: usize,
+    /// Node counts by [SupportedOp] variant (and `SubGraph`).
+    pub by_variant: BTreeMap<String, usize>,
+    /// Node counts by op kind, eg. `EINSUM` or `RELU`, looking through rescaled and rebased ops.
+    pub by_kind: BTreeMap<String, usize>,
+    /// The number of parameters held by constants and by conv/deconv kernels and biases.
+    pub num_params: usize,
+    /// The number of nodes on the longest path from an input to an output, counting the nodes of subgraph bodies.
+    pub depth: usize,
+    /// The largest `num_uses` of any node, and the path of that node.
+    pub max_fan_out: (usize, Vec<usize>),
+    /// The number of subgraphs, including nested ones.
+    pub num_subgraphs: usize,
+    /// The distinct lookup ops needed by the model.
+    pub lookups: Vec<LookupOp>,
+    /// Lookup ops used by the model but absent from the settings' `required_lookups`.
+    pub missing_lookups: Vec<LookupOp>,
+    /// Lookup ops in the settings' `required_lookups` that the model doesn't use.
+    pub unused_lookups: Vec<LookupOp>,
+}
+
+impl fmt::Display for ModelStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nodes: {}", self.num_nodes)?;
+        writeln!(f, "subgraphs: {}", self.num_subgraphs)?;
+        writeln!(f, "params: {}", self.num_params)?;
+        writeln!(f, "depth: {}", self.depth)?;
+        writeln!(f, "max fan-out: {} (node {:?})", self.max_fan_out.0, self.max_fan_out.1)?;
+        writeln!(f, "by variant:")?;
+        for (variant, count) in &self.by_variant {
+            writeln!(f, "  {:<24} {:>8}", variant, count)?;
+        }
+        writeln!(f, "by kind:")?;
+        for (kind, count) in &self.by_kind {
+            writeln!(f, "  {:<24} {:>8}", kind, count)?;
+        }
+        let names = |ops: &[LookupOp]| ops.iter().map(|op| op.as_string()).collect::<Vec<_>>().join(", ");
+        writeln!(f, "lookups: [{}]", names(&self.lookups))?;
+        writeln!(f, "missing from settings: [{}]", names(&self.missing_lookups))?;
+        writeln!(f, "unused in settings: [{}]", names(&self.unused_lookups))
+    }
+}
+
+/// Gathers statistics over the model. If settings are provided, the lookups used by the model are compared
+/// against `required_lookups`.
+pub fn model_stats(model: &Model, settings: Option<&GraphSettings>) -> ModelStats {
+    let mut stats = ModelStats::default();
+    let depth = collect_stats(&model.graph, &[], &mut stats);
+    stats.depth = depth;
+    stats.lookups = model.graph.required_lookups();
+    if let Some(settings) = settings {
+        let report = check_lookups(model, settings);
+        stats.missing_lookups = report.missing;
+        stats.unused_lookups = report.extra;
+    }
+    stats
+}
+
+/// Collects the stats of a graph and returns its depth.
+fn collect_stats(graph: &ParsedNodes, prefix: &[usize], stats: &mut ModelStats) -> usize {
+    // the number of nodes on the longest path ending at each node
+    let mut depths: BTreeMap<usize, usize> = BTreeMap::new();
+    for (idx, node) in graph.nodes.iter() {
+        let mut path = prefix.to_vec();
+        path.push(*idx);
+        stats.num_nodes += 1;
+        let input_depth = node
+            .inputs()
+            .iter()
+            .filter_map(|outlet| depths.get(&outlet.0))
+            .max()
+            .copied()
+            .unwrap_or(0);
+        let node_depth = match node {
+            NodeType::Node(n) => {
+                *stats.by_variant.entry(variant_name(&n.opkind).to_string()).or_insert(0) += 1;
+                *stats.by_kind.entry(kind_name(&n.opkind)).or_insert(0) += 1;
+                stats.num_params += num_params(&n.opkind);
+                if n.num_uses > stats.max_fan_out.0 {
+                    stats.max_fan_out = (n.num_uses, path);
+                }
+                1
+            }
+            NodeType::SubGraph { model, .. } => {
+                *stats.by_variant.entry("SubGraph".to_string()).or_insert(0) += 1;
+                stats.num_subgraphs += 1;
+                collect_stats(&model.graph, &path, stats)
+            }
+        };
+        depths.insert(*idx, input_depth + node_depth);
+    }
+    graph
+        .outputs
+        .iter()
+        .filter_map(|outlet| depths.get(&outlet.0))
+        .max()
+        .copied()
+        .unwrap_or(0)
+}
+
+fn variant_name(op: &SupportedOp) -> &'static str {
+    match op {
+        SupportedOp::Linear(_) => "Linear",
+        SupportedOp::Nonlinear(_) => "Nonlinear",
+        SupportedOp::Hybrid(_) => "Hybrid",
+        SupportedOp::Input(_) => "Input",
+        SupportedOp::Constant(_) => "Constant",
+        SupportedOp::Unknown(_) => "Unknown",
+        SupportedOp::Rescaled(_) => "Rescaled",
+        SupportedOp::RebaseScale(_) => "RebaseScale",
+    }
+}
+
+fn kind_name(op: &SupportedOp) -> String {
+    match op {
+        SupportedOp::Rescaled(op) => kind_name(&op.inner),
+        SupportedOp::RebaseScale(op) => kind_name(&op.inner),
+        _ => op.as_string(),
+    }
+}
+
+fn num_params(op: &SupportedOp) -> usize {
+    match op {
+        SupportedOp::Constant(c) => c.quantized_values.len(),
+        SupportedOp::Linear(PolyOp::Conv { kernel, bias, .. })
+        | SupportedOp::Linear(PolyOp::DeConv { kernel, bias, .. }) => {
+            kernel.len() + bias.as_ref().map(|b| b.len()).unwrap_or(0)
+        }
+        SupportedOp::Rescaled(op) => num_params(&op.inner),
+        SupportedOp::RebaseScale(op) => num_params(&op.inner),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr as Fp;
+
+    use super::*;
+    use crate::model::{InputMapping, OutputMapping};
+    use crate::supportedop::RebaseScale;
+    use crate::testutils::{constant, felts, input, model, node, subgraph};
+    use crate::utils::F32;
+
+    const SETTINGS_JSON: &str = include_str!("../../settings.json");
+
+    fn linear(op: PolyOp<Fp>) -> SupportedOp {
+        SupportedOp::Linear(op)
+    }
+
+    /// `-((x + w) * w)`, the product and negation running in a subgraph.
+    fn nested_model() -> Model {
+        let mut body = model(
+            vec![
+                input(0, &[2], 0),
+                input(1, &[2], 0),
+                node(2, linear(PolyOp::Mult), &[(0, 0), (1, 0)], &[2], 0),
+                node(3, linear(PolyOp::Neg), &[(2, 0)], &[2], 0),
+            ],
+            &[0, 1],
+            &[(3, 0)],
+        );
+        if let Some(NodeType::Node(n)) = body.graph.nodes.get_mut(&2) {
+            n.num_uses = 4;
+        }
+        let mut m = model(
+            vec![
+                input(0, &[2], 0),
+                constant(1, &[0.5, 1.0], &[2], 0),
+                node(2, linear(PolyOp::Add), &[(0, 0), (1, 0)], &[2], 0),
+                subgraph(
+                    3,
+                    body,
+                    &[(2, 0), (1, 0)],
+                    vec![InputMapping::Full, InputMapping::Full],
+                    vec![vec![OutputMapping::Single {
+                        outlet: 0,
+                        is_state: false,
+                    }]],
+                    vec![vec![2]],
+                    vec![0],
+                ),
+                node(4, linear(PolyOp::Identity), &[(3, 0)], &[2], 0),
+            ],
+            &[0],
+            &[(4, 0)],
+        );
+        if let Some(NodeType::Node(n)) = m.graph.nodes.get_mut(&1) {
+            n.num_uses = 2;
+        }
+        m
+    }
+
+    #[test]
+    fn num_nodes() {
+        let stats = model_stats(&nested_model(), None);
+        // the subgraph and its 4 body nodes are counted
+        assert_eq!(stats.num_nodes, 9);
+        assert_eq!(stats.num_subgraphs, 1);
+        assert_eq!(stats.by_variant["Input"], 3);
+        assert_eq!(stats.by_variant["Linear"], 4);
+        assert_eq!(stats.by_variant["SubGraph"], 1);
+        assert_eq!(stats.by_kind["MULT"], 1);
+    }
+
+    #[test]
+    fn depth_through_a_subgraph() {
+        // input, add, then the body's input, product and negation, then the identity
+        assert_eq!(model_stats(&nested_model(), None).depth, 6);
+    }
+
+    #[test]
+    fn max_fan_out() {
+        assert_eq!(model_stats(&nested_model(), None).max_fan_out, (4, vec![3, 2]));
+    }
+
+    #[test]
+    fn num_params() {
+        let conv = PolyOp::Conv {
+            kernel: felts(&[1; 8], &[2, 1, 2, 2]),
+            bias: Some(felts(&[1, 1], &[2])),
+            padding: [(0, 0); 2],
+            stride: (1, 1),
+        };
+        let deconv = PolyOp::DeConv {
+            kernel: felts(&[1; 18], &[1, 2, 3, 3]),
+            bias: None,
+            padding: [(0, 0); 2],
+            output_padding: (0, 0),
+            stride: (1, 1),
+        };
+        let rebased = SupportedOp::RebaseScale(RebaseScale {
+            inner: Box::new(linear(conv.clone())),
+            multiplier: 2.0,
+            target_scale: 0,
+            original_scale: 1,
+        });
+        let m = model(
+            vec![
+                input(0, &[1, 1, 3, 3], 0),
+                constant(1, &[1.0; 6], &[2, 3], 0),
+                node(2, linear(conv), &[(0, 0)], &[1, 2, 2, 2], 0),
+                node(3, linear(deconv), &[(0, 0)], &[1, 2, 5, 5], 0),
+                node(4, rebased, &[(0, 0)], &[1, 2, 2, 2], 0),
+            ],
+            &[0],
+            &[(2, 0), (3, 0), (4, 0)],
+        );
+        // 6 constants, 8 + 2 for each conv and 18 for the deconv
+        assert_eq!(model_stats(&m, None).num_params, 6 + 10 + 18 + 10);
+    }
+
+    #[test]
+    fn missing_and_unused_lookups() {
+        let settings = GraphSettings::from_json(SETTINGS_JSON).unwrap();
+        let m = model(
+            vec![
+                input(0, &[1, 3], 2),
+                node(1, SupportedOp::Nonlinear(LookupOp::ReLU), &[(0, 0)], &[1, 3], 2),
+                node(2, SupportedOp::Nonlinear(LookupOp::Abs), &[(1, 0)], &[1, 3], 2),
+            ],
+            &[0],
+            &[(2, 0)],
+        );
+        let stats = model_stats(&m, Some(&settings));
+        assert_eq!(stats.lookups, vec![LookupOp::Abs, LookupOp::ReLU]);
+        assert_eq!(stats.missing_lookups, vec![LookupOp::Abs]);
+        // the bundled settings also rebase two products
+        assert_eq!(
+            stats.unused_lookups,
+            vec![
+                LookupOp::Div { denom: F32(4.0) },
+                LookupOp::Div { denom: F32(8.0) }
+            ]
+        );
+    }
+}
@@ -1,10 +1,17 @@
 use halo2curves::ff::PrimeField;
-use utils::Scale;
-use model::Visibility;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::utils::Scale;
+use crate::model::SupportedOp;
+use crate::einsum;
+use crate::tensorops;
+
+pub use crate::tensor::{Tensor, TensorElementSchema, TensorError, TensorType};
 
 #[allow(missing_docs)]
 /// An enum representing the operations that can be expressed as arithmetic (non lookup) operations.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "PolyOp", bound = "F: TensorElementSchema")]
 pub enum PolyOp<F: PrimeField + TensorType + PartialOrd> {
     MultiBroadcastTo {
         shape: Vec<usize>,
@@ -70,29 +77,238 @@ pub enum PolyOp<F: PrimeField + TensorType + PartialOrd> {
     Xor,
 }
 
-/// The (inner) type of tensor elements.
-pub trait TensorType: Clone + Debug + 'static {
-    /// Returns the zero value.
-    fn zero() -> Option<Self> {
-        None
+impl<F: PrimeField + TensorType + PartialOrd> PolyOp<F> {
+    /// Evaluates the operation on its input tensors.
+    pub fn f(&self, inputs: &[Tensor<F>]) -> Result<Tensor<F>, TensorError> {
+        let res = match &self {
+            PolyOp::MultiBroadcastTo { shape } => {
+                check_arity(self, inputs, 1)?;
+                inputs[0].expand(shape)
+            }
+            PolyOp::Einsum { equation } => {
+                einsum::einsum(equation, &inputs.iter().collect::<Vec<_>>())
+            }
+            PolyOp::Conv {
+                kernel,
+                bias,
+                padding,
+                stride,
+            } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::conv(&inputs[0], kernel, bias.as_ref(), *padding, *stride)
+            }
+            PolyOp::DeConv {
+                kernel,
+                bias,
+                padding,
+                output_padding,
+                stride,
+            } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::deconv(&inputs[0], kernel, bias.as_ref(), *padding, *output_padding, *stride)
+            }
+            PolyOp::Downsample {
+                axis,
+                stride,
+                modulo,
+            } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::downsample(&inputs[0], *axis, *stride, *modulo)
+            }
+            PolyOp::Add => tensorops::add(inputs),
+            PolyOp::Sub => tensorops::sub(inputs),
+            PolyOp::Mult => tensorops::mult(inputs),
+            PolyOp::Neg => {
+                check_arity(self, inputs, 1)?;
+                Ok(-inputs[0].clone())
+            }
+            PolyOp::Identity => {
+                check_arity(self, inputs, 1)?;
+                Ok(inputs[0].clone())
+            }
+            PolyOp::Reshape(new_dims) | PolyOp::Flatten(new_dims) => {
+                check_arity(self, inputs, 1)?;
+                let mut t = inputs[0].clone();
+                t.reshape(new_dims)?;
+                Ok(t)
+            }
+            PolyOp::MoveAxis {
+                source,
+                destination,
+            } => {
+                check_arity(self, inputs, 1)?;
+                inputs[0].move_axis(*source, *destination)
+            }
+            PolyOp::Pad(padding) => {
+                check_arity(self, inputs, 1)?;
+                tensorops::pad(&inputs[0], *padding)
+            }
+            PolyOp::Sum { axes } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::sum_axes(&inputs[0], axes)
+            }
+            PolyOp::Prod { axes, .. } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::prod_axes(&inputs[0], axes)
+            }
+            PolyOp::Pow(u) => {
+                check_arity(self, inputs, 1)?;
+                tensorops::pow(&inputs[0], *u)
+            }
+            PolyOp::Pack(base, scale) => {
+                check_arity(self, inputs, 1)?;
+                tensorops::pack(&inputs[0], F::from(*base as u64), *scale)
+            }
+            PolyOp::GlobalSumPool => {
+                check_arity(self, inputs, 1)?;
+                // sum over every spatial dimension, keeping batch and channels
+                let axes: Vec<usize> = (2..inputs[0].dims().len()).collect();
+                tensorops::sum_axes(&inputs[0], &axes)
+            }
+            PolyOp::Concat { axis } => {
+                tensorops::concat(&inputs.iter().collect::<Vec<_>>(), *axis)
+            }
+            PolyOp::Slice { axis, start, end } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::slice(&inputs[0], *axis, *start, *end)
+            }
+            PolyOp::Iff => {
+                check_arity(self, inputs, 3)?;
+                tensorops::iff(&inputs[0], &inputs[1], &inputs[2])
+            }
+            PolyOp::Resize { scale_factor } => {
+                check_arity(self, inputs, 1)?;
+                tensorops::resize(&inputs[0], scale_factor)
+            }
+            PolyOp::Not => {
+                check_arity(self, inputs, 1)?;
+                tensorops::not(&inputs[0])
+            }
+            PolyOp::And => {
+                check_arity(self, inputs, 2)?;
+                tensorops::and(&inputs[0], &inputs[1])
+            }
+            PolyOp::Or => {
+                check_arity(self, inputs, 2)?;
+                tensorops::or(&inputs[0], &inputs[1])
+            }
+            PolyOp::Xor => {
+                check_arity(self, inputs, 2)?;
+                tensorops::xor(&inputs[0], &inputs[1])
+            }
+        }?;
+        Ok(res)
     }
-    /// Returns the unit value.
-    fn one() -> Option<Self> {
-        None
+
+    /// Returns the name of the operation.
+    pub fn as_string(&self) -> String {
+        match &self {
+            PolyOp::MultiBroadcastTo { .. } => "MULTIBROADCASTTO",
+            PolyOp::Einsum { .. } => "EINSUM",
+            PolyOp::Conv { .. } => "CONV",
+            PolyOp::Downsample { .. } => "DOWNSAMPLE",
+            PolyOp::DeConv { .. } => "DECONV",
+            PolyOp::Add => "ADD",
+            PolyOp::Sub => "SUB",
+            PolyOp::Neg => "NEG",
+            PolyOp::Mult => "MULT",
+            PolyOp::Identity => "IDENTITY",
+            PolyOp::Reshape(_) => "RESHAPE",
+            PolyOp::MoveAxis { .. } => "MOVEAXIS",
+            PolyOp::Flatten(_) => "FLATTEN",
+            PolyOp::Pad(_) => "PAD",
+            PolyOp::Sum { .. } => "SUM",
+            PolyOp::Prod { .. } => "PROD",
+            PolyOp::Pow(_) => "POW",
+            PolyOp::Pack(_, _) => "PACK",
+            PolyOp::GlobalSumPool => "GLOBALSUMPOOL",
+            PolyOp::Concat { .. } => "CONCAT",
+            PolyOp::Slice { .. } => "SLICE",
+            PolyOp::Iff => "IFF",
+            PolyOp::Resize { .. } => "RESIZE",
+            PolyOp::Not => "NOT",
+            PolyOp::And => "AND",
+            PolyOp::Or => "OR",
+            PolyOp::Xor => "XOR",
+        }
+        .into()
     }
-    /// Max operator for ordering values.
-    fn tmax(&self, _: &Self) -> Option<Self> {
-        None
+}
+
+fn check_arity<F: PrimeField + TensorType + PartialOrd>(
+    op: &PolyOp<F>,
+    inputs: &[Tensor<F>],
+    expected: usize,
+) -> Result<(), TensorError> {
+    if inputs.len() != expected {
+        return Err(TensorError::DimMismatch(format!(
+            "{} expects {} inputs, got {}",
+            op.as_string(),
+            expected,
+            inputs.len()
+        )));
     }
+    Ok(())
+}
+
+/// The datum type of a model input, as recorded when the graph was parsed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum InputType {
+    ///
+    Bool,
+    ///
+    F16,
+    ///
+    F32,
+    ///
+    F64,
+    ///
+    Int,
+    ///
+    TDim,
+}
+
+/// An input to the model.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The fixed point scale the input is quantized at.
+    pub scale: Scale,
+    /// The datum type of the input.
+    pub datum_type: InputType,
+}
+
+/// A constant (parameter) of the model, stored both quantized and as the raw floats.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Constant", bound = "F: TensorElementSchema")]
+pub struct Constant<F: PrimeField + TensorType + PartialOrd> {
+    /// The quantized values of the constant.
+    pub quantized_values: Tensor<F>,
+    /// The raw (unquantized) values of the constant.
+    pub raw_values: Tensor<f32>,
+}
+
+/// An operation that could not be parsed into any of the supported ops.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, JsonSchema)]
+pub struct Unknown;
+
+/// An operation whose inputs are multiplied by a constant so that their scales match.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Rescaled {
+    /// The operation that consumes the rescaled inputs.
+    pub inner: Box<SupportedOp>,
+    /// (input index, multiplier) pairs applied before `inner`.
+    pub scale: Vec<(usize, u128)>,
 }
 
-/// A generic multi-dimensional array representation of a Tensor.
-/// The `inner` attribute contains a vector of values whereas `dims` corresponds to the dimensionality of the array
-/// and as such determines how we index, query for values, or slice a Tensor.
-#[derive(Clone, Debug, Eq, Serialize, Deserialize, PartialOrd, Ord)]
-pub struct Tensor<T: TensorType> {
-    inner: Vec<T>,
-    dims: Vec<usize>,
-    scale: Option<Scale>,
-    visibility: Option<Visibility>,
+/// An operation whose output is divided down to `target_scale` once it overflows the rebase threshold.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RebaseScale {
+    /// The operation whose output is rebased.
+    pub inner: Box<SupportedOp>,
+    /// The divisor applied to the output of `inner`.
+    pub multiplier: f64,
+    /// The scale of the output after rebasing.
+    pub target_scale: Scale,
+    /// The scale of the output of `inner`.
+    pub original_scale: Scale,
 }
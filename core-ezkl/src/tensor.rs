@@ -0,0 +1,647 @@
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::ops::{Add, Deref, DerefMut, Mul, Neg, Range, Sub};
+
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::Field;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::model::Visibility;
+use crate::schema::FeltSchema;
+use crate::utils::Scale;
+
+/// A wrapper for tensor related errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TensorError {
+    /// Shape mismatch in an operation
+    DimMismatch(String),
+    /// Shape when instantiating
+    DimError(String),
+    /// Axis out of range for the tensor
+    AxisError(String),
+    /// Operation not supported for this element type or these inputs
+    Unsupported(String),
+}
+
+impl fmt::Display for TensorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TensorError::DimMismatch(op) => write!(f, "dimension mismatch in tensor op: {}", op),
+            TensorError::DimError(msg) => write!(f, "dimension error: {}", msg),
+            TensorError::AxisError(msg) => write!(f, "axis error: {}", msg),
+            TensorError::Unsupported(msg) => write!(f, "unsupported operation: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TensorError {}
+
+/// The (inner) type of tensor elements.
+pub trait TensorType: Clone + Debug + 'static {
+    /// Returns the zero value.
+    fn zero() -> Option<Self> {
+        None
+    }
+    /// Returns the unit value.
+    fn one() -> Option<Self> {
+        None
+    }
+    /// Max operator for ordering values.
+    fn tmax(&self, _: &Self) -> Option<Self> {
+        None
+    }
+}
+
+macro_rules! tensor_type {
+    ($rust_type:ty, $zero:expr, $one:expr) => {
+        impl TensorType for $rust_type {
+            fn zero() -> Option<Self> {
+                Some($zero)
+            }
+            fn one() -> Option<Self> {
+                Some($one)
+            }
+            fn tmax(&self, other: &Self) -> Option<Self> {
+                Some(std::cmp::max(*self, *other))
+            }
+        }
+    };
+}
+
+tensor_type!(bool, false, true);
+tensor_type!(i32, 0, 1);
+tensor_type!(i64, 0, 1);
+tensor_type!(i128, 0, 1);
+tensor_type!(usize, 0, 1);
+
+impl TensorType for f32 {
+    fn zero() -> Option<Self> {
+        Some(0.0)
+    }
+    fn one() -> Option<Self> {
+        Some(1.0)
+    }
+    fn tmax(&self, other: &Self) -> Option<Self> {
+        Some(self.max(*other))
+    }
+}
+
+impl TensorType for f64 {
+    fn zero() -> Option<Self> {
+        Some(0.0)
+    }
+    fn one() -> Option<Self> {
+        Some(1.0)
+    }
+    fn tmax(&self, other: &Self) -> Option<Self> {
+        Some(self.max(*other))
+    }
+}
+
+impl TensorType for Fp {
+    fn zero() -> Option<Self> {
+        Some(Fp::ZERO)
+    }
+    fn one() -> Option<Self> {
+        Some(Fp::ONE)
+    }
+    fn tmax(&self, other: &Self) -> Option<Self> {
+        Some(std::cmp::max(*self, *other))
+    }
+}
+
+/// The JSON layout of the elements of a serialized tensor.
+pub trait TensorElementSchema {
+    /// The type the elements serialize as.
+    type Schema: JsonSchema;
+}
+
+impl TensorElementSchema for Fp {
+    type Schema = FeltSchema;
+}
+
+impl TensorElementSchema for f32 {
+    type Schema = f32;
+}
+
+impl TensorElementSchema for usize {
+    type Schema = usize;
+}
+
+/// A generic multi-dimensional array representation of a Tensor.
+/// The `inner` attribute contains a vector of values whereas `dims` corresponds to the dimensionality of the array
+/// and as such determines how we index, query for values, or slice a Tensor.
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, PartialOrd, Ord)]
+pub struct Tensor<T: TensorType> {
+    inner: Vec<T>,
+    dims: Vec<usize>,
+    scale: Option<Scale>,
+    visibility: Option<Visibility>,
+}
+
+/// Mirrors the serialized fields of [Tensor] to derive its schema.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct TensorSchema<T> {
+    inner: Vec<T>,
+    dims: Vec<usize>,
+    scale: Option<Scale>,
+    visibility: Option<Visibility>,
+}
+
+impl<T: TensorType + TensorElementSchema> JsonSchema for Tensor<T> {
+    fn schema_name() -> String {
+        format!("Tensor_of_{}", T::Schema::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        TensorSchema::<T::Schema>::json_schema(gen)
+    }
+}
+
+impl<T: TensorType> Deref for Tensor<T> {
+    type Target = [T];
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.inner.deref()
+    }
+}
+
+impl<T: TensorType> DerefMut for Tensor<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.inner.deref_mut()
+    }
+}
+
+impl<T: PartialEq + TensorType> PartialEq for Tensor<T> {
+    fn eq(&self, other: &Tensor<T>) -> bool {
+        self.dims == other.dims && self.deref() == other.deref()
+    }
+}
+
+impl<T: TensorType> IntoIterator for Tensor<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<T: TensorType> FromIterator<T> for Tensor<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(value: I) -> Tensor<T> {
+        let data: Vec<T> = value.into_iter().collect();
+        let len = data.len();
+        Tensor {
+            inner: data,
+            dims: vec![len],
+            scale: None,
+            visibility: None,
+        }
+    }
+}
+
+impl<T: TensorType> Tensor<T> {
+    /// Sets (copies) the tensor values to the provided ones, or fills the tensor with zeros if `values` is `None`.
+    pub fn new(values: Option<&[T]>, dims: &[usize]) -> Result<Self, TensorError> {
+        match values {
+            Some(v) => {
+                let dims = if dims.is_empty() { vec![v.len()] } else { dims.to_vec() };
+                if dims.iter().product::<usize>() != v.len() {
+                    return Err(TensorError::DimError(format!(
+                        "cannot build a tensor of dims {:?} from {} values",
+                        dims,
+                        v.len()
+                    )));
+                }
+                Ok(Tensor {
+                    inner: v.to_vec(),
+                    dims,
+                    scale: None,
+                    visibility: None,
+                })
+            }
+            None => {
+                let zero = T::zero().ok_or_else(|| {
+                    TensorError::Unsupported("element type has no zero value".to_string())
+                })?;
+                Ok(Tensor {
+                    inner: vec![zero; dims.iter().product()],
+                    dims: dims.to_vec(),
+                    scale: None,
+                    visibility: None,
+                })
+            }
+        }
+    }
+
+    /// Returns the number of elements in the tensor.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Checks if the tensor is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the tensor's dimensions.
+    pub fn dims(&self) -> &[usize] {
+        &self.dims
+    }
+
+    /// Returns the fixed point scale of the tensor, if set.
+    pub fn scale(&self) -> Option<Scale> {
+        self.scale
+    }
+
+    /// Sets the fixed point scale of the tensor.
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.scale = Some(scale)
+    }
+
+    /// Returns the visibility of the tensor, if set.
+    pub fn visibility(&self) -> Option<Visibility> {
+        self.visibility.clone()
+    }
+
+    /// Sets the visibility of the tensor.
+    pub fn set_visibility(&mut self, visibility: &Visibility) {
+        self.visibility = Some(visibility.clone())
+    }
+
+    /// Returns the flat index of a multi-dimensional index.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let a = Tensor::<i128>::new(None, &[2, 3, 5]).unwrap();
+    /// assert_eq!(a.get_index(&[1, 2, 3]), 28);
+    /// ```
+    pub fn get_index(&self, indices: &[usize]) -> usize {
+        assert_eq!(self.dims.len(), indices.len());
+        let mut index = 0;
+        let mut d = 1;
+        for i in (0..indices.len()).rev() {
+            assert!(self.dims[i] > indices[i]);
+            index += indices[i] * d;
+            d *= self.dims[i];
+        }
+        index
+    }
+
+    /// Gets the element at a multi-dimensional index.
+    pub fn get(&self, indices: &[usize]) -> T {
+        self.inner[self.get_index(indices)].clone()
+    }
+
+    /// Sets the element at a multi-dimensional index.
+    pub fn set(&mut self, indices: &[usize], value: T) {
+        let index = self.get_index(indices);
+        self.inner[index] = value;
+    }
+
+    /// Gets a subtensor. Ranges apply to the leading dimensions; the remaining dimensions are taken in full.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let a = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+    /// let b = Tensor::<i128>::new(Some(&[2, 3, 5, 6]), &[2, 2]).unwrap();
+    /// assert_eq!(a.get_slice(&[0..2, 1..3]).unwrap(), b);
+    /// ```
+    pub fn get_slice(&self, indices: &[Range<usize>]) -> Result<Tensor<T>, TensorError> {
+        if indices.len() > self.dims.len() {
+            return Err(TensorError::DimError(format!(
+                "slice of rank {} on a tensor of dims {:?}",
+                indices.len(),
+                self.dims
+            )));
+        }
+        let mut full_indices = indices.to_vec();
+        for d in self.dims[indices.len()..].iter() {
+            full_indices.push(0..*d);
+        }
+        for (r, d) in full_indices.iter().zip(self.dims.iter()) {
+            if r.start > r.end || r.end > *d {
+                return Err(TensorError::DimError(format!(
+                    "slice range {:?} out of bounds for dimension of size {}",
+                    r, d
+                )));
+            }
+        }
+        let dims: Vec<usize> = full_indices.iter().map(|r| r.end - r.start).collect();
+        let mut res = Vec::with_capacity(dims.iter().product());
+        for coord in index_iter(&dims) {
+            let src: Vec<usize> = coord
+                .iter()
+                .zip(full_indices.iter())
+                .map(|(c, r)| c + r.start)
+                .collect();
+            res.push(self.get(&src));
+        }
+        Tensor::new(Some(&res), &dims)
+    }
+
+    /// Reshapes the tensor in place.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let mut a = Tensor::<i128>::new(None, &[3, 3, 3]).unwrap();
+    /// a.reshape(&[9, 3]).unwrap();
+    /// assert_eq!(a.dims(), &[9, 3]);
+    /// ```
+    pub fn reshape(&mut self, new_dims: &[usize]) -> Result<(), TensorError> {
+        if new_dims.iter().product::<usize>() != self.len() {
+            return Err(TensorError::DimError(format!(
+                "cannot reshape a tensor of dims {:?} to {:?}",
+                self.dims, new_dims
+            )));
+        }
+        self.dims = new_dims.to_vec();
+        Ok(())
+    }
+
+    /// Flattens the tensor to a single dimension.
+    pub fn flatten(&mut self) {
+        self.dims = vec![self.len()];
+    }
+
+    /// Broadcasts the tensor to `shape`, following numpy's broadcasting rules.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let a = Tensor::<i128>::new(Some(&[1, 2, 3]), &[3, 1]).unwrap();
+    /// let b = Tensor::<i128>::new(Some(&[1, 1, 2, 2, 3, 3]), &[3, 2]).unwrap();
+    /// assert_eq!(a.expand(&[3, 2]).unwrap(), b);
+    /// ```
+    pub fn expand(&self, shape: &[usize]) -> Result<Self, TensorError> {
+        if shape.len() < self.dims.len() {
+            return Err(TensorError::DimError(format!(
+                "cannot broadcast dims {:?} to fewer dims {:?}",
+                self.dims, shape
+            )));
+        }
+        let offset = shape.len() - self.dims.len();
+        for (d, s) in self.dims.iter().zip(shape[offset..].iter()) {
+            if d != s && *d != 1 {
+                return Err(TensorError::DimError(format!(
+                    "cannot broadcast dims {:?} to {:?}",
+                    self.dims, shape
+                )));
+            }
+        }
+        let mut res = Vec::with_capacity(shape.iter().product());
+        for coord in index_iter(shape) {
+            let src: Vec<usize> = coord[offset..]
+                .iter()
+                .zip(self.dims.iter())
+                .map(|(c, d)| if *d == 1 { 0 } else { *c })
+                .collect();
+            res.push(self.get(&src));
+        }
+        let mut output = Tensor::new(Some(&res), shape)?;
+        output.scale = self.scale;
+        output.visibility = self.visibility.clone();
+        Ok(output)
+    }
+
+    /// Moves the axis `source` to position `destination`, shifting the axes in between.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let a = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+    /// let b = Tensor::<i128>::new(Some(&[1, 4, 2, 5, 3, 6]), &[3, 2]).unwrap();
+    /// assert_eq!(a.move_axis(0, 1).unwrap(), b);
+    /// ```
+    pub fn move_axis(&self, source: usize, destination: usize) -> Result<Self, TensorError> {
+        if source >= self.dims.len() || destination >= self.dims.len() {
+            return Err(TensorError::AxisError(format!(
+                "cannot move axis {} to {} in a tensor of rank {}",
+                source,
+                destination,
+                self.dims.len()
+            )));
+        }
+        let mut order: Vec<usize> = (0..self.dims.len()).collect();
+        let axis = order.remove(source);
+        order.insert(destination, axis);
+        self.permute(&order)
+    }
+
+    /// Permutes the axes of the tensor, such that output axis `i` is input axis `order[i]`.
+    pub fn permute(&self, order: &[usize]) -> Result<Self, TensorError> {
+        let mut seen = vec![false; self.dims.len()];
+        if order.len() != self.dims.len() || order.iter().any(|o| *o >= seen.len()) {
+            return Err(TensorError::AxisError(format!(
+                "invalid permutation {:?} for a tensor of rank {}",
+                order,
+                self.dims.len()
+            )));
+        }
+        for o in order {
+            if seen[*o] {
+                return Err(TensorError::AxisError(format!(
+                    "repeated axis in permutation {:?}",
+                    order
+                )));
+            }
+            seen[*o] = true;
+        }
+        let new_dims: Vec<usize> = order.iter().map(|o| self.dims[*o]).collect();
+        let mut res = Vec::with_capacity(self.len());
+        let mut src = vec![0; self.dims.len()];
+        for coord in index_iter(&new_dims) {
+            for (c, o) in coord.iter().zip(order.iter()) {
+                src[*o] = *c;
+            }
+            res.push(self.get(&src));
+        }
+        let mut output = Tensor::new(Some(&res), &new_dims)?;
+        output.scale = self.scale;
+        output.visibility = self.visibility.clone();
+        Ok(output)
+    }
+
+    /// Maps a function to tensors, keeping the dimensions.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let a = Tensor::<i128>::new(Some(&[1, 4]), &[2]).unwrap();
+    /// let c = a.map(|x| i128::pow(x, 2));
+    /// assert_eq!(c, Tensor::from_iter([1, 16]));
+    /// ```
+    pub fn map<F: FnMut(T) -> G, G: TensorType>(&self, mut f: F) -> Tensor<G> {
+        let mut t: Tensor<G> = self.inner.iter().map(|e| f(e.clone())).collect();
+        t.dims = self.dims.clone();
+        t.scale = self.scale;
+        t.visibility = self.visibility.clone();
+        t
+    }
+
+    /// Maps a fallible function to tensors, keeping the dimensions.
+    pub fn map_result<F: FnMut(T) -> Result<G, E>, G: TensorType, E>(
+        &self,
+        mut f: F,
+    ) -> Result<Tensor<G>, E> {
+        let mut t: Tensor<G> = self
+            .inner
+            .iter()
+            .map(|e| f(e.clone()))
+            .collect::<Result<Tensor<G>, E>>()?;
+        t.dims = self.dims.clone();
+        t.scale = self.scale;
+        t.visibility = self.visibility.clone();
+        Ok(t)
+    }
+
+    /// Applies `f` elementwise to `self` and `other` after broadcasting both to a common shape.
+    pub fn zip_with<F: FnMut(T, T) -> T>(
+        &self,
+        other: &Tensor<T>,
+        mut f: F,
+    ) -> Result<Tensor<T>, TensorError> {
+        let shape = get_broadcasted_shape(&self.dims, &other.dims)?;
+        let lhs = self.expand(&shape)?;
+        let rhs = other.expand(&shape)?;
+        let mut output: Tensor<T> = lhs
+            .inner
+            .into_iter()
+            .zip(rhs.inner)
+            .map(|(a, b)| f(a, b))
+            .collect();
+        output.reshape(&shape)?;
+        output.scale = self.scale;
+        output.visibility = self.visibility.clone();
+        Ok(output)
+    }
+}
+
+/// Returns the shape two tensors broadcast to, following numpy's broadcasting rules.
+/// ```
+/// use core_ezkl::tensor::get_broadcasted_shape;
+/// assert_eq!(get_broadcasted_shape(&[2, 1, 3], &[4, 1]).unwrap(), vec![2, 4, 3]);
+/// assert!(get_broadcasted_shape(&[2, 3], &[4]).is_err());
+/// ```
+pub fn get_broadcasted_shape(shape_a: &[usize], shape_b: &[usize]) -> Result<Vec<usize>, TensorError> {
+    let num_dims = std::cmp::max(shape_a.len(), shape_b.len());
+    let mut shape = vec![0; num_dims];
+    for i in 0..num_dims {
+        let a = if i < num_dims - shape_a.len() { 1 } else { shape_a[i + shape_a.len() - num_dims] };
+        let b = if i < num_dims - shape_b.len() { 1 } else { shape_b[i + shape_b.len() - num_dims] };
+        shape[i] = if a == b || b == 1 {
+            a
+        } else if a == 1 {
+            b
+        } else {
+            return Err(TensorError::DimMismatch(format!(
+                "cannot broadcast {:?} with {:?}",
+                shape_a, shape_b
+            )));
+        };
+    }
+    Ok(shape)
+}
+
+/// Returns the row-major strides of a tensor with dimensions `dims`.
+pub fn strides(dims: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; dims.len()];
+    for i in (0..dims.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * dims[i + 1];
+    }
+    strides
+}
+
+/// Iterates over every multi-dimensional index of a tensor with dimensions `dims`, in row-major order.
+pub fn index_iter(dims: &[usize]) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let total: usize = dims.iter().product();
+    let strides = strides(dims);
+    (0..total).map(move |flat| {
+        dims.iter()
+            .zip(strides.iter())
+            .map(|(d, s)| (flat / s) % d)
+            .collect()
+    })
+}
+
+impl<T: TensorType + Add<Output = T>> Add for Tensor<T> {
+    type Output = Result<Tensor<T>, TensorError>;
+    /// Adds two tensors, broadcasting them to a common shape.
+    /// ```
+    /// use core_ezkl::tensor::Tensor;
+    /// let x = Tensor::<i128>::new(Some(&[2, 1, 2, 1, 1, 1]), &[2, 3]).unwrap();
+    /// let k = Tensor::<i128>::new(Some(&[2]), &[1]).unwrap();
+    /// let result = x.add(k).unwrap();
+    /// let expected = Tensor::<i128>::new(Some(&[4, 3, 4, 3, 3, 3]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip_with(&rhs, |a, b| a + b)
+    }
+}
+
+impl<T: TensorType + Sub<Output = T>> Sub for Tensor<T> {
+    type Output = Result<Tensor<T>, TensorError>;
+    /// Subtracts two tensors, broadcasting them to a common shape.
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip_with(&rhs, |a, b| a - b)
+    }
+}
+
+impl<T: TensorType + Mul<Output = T>> Mul for Tensor<T> {
+    type Output = Result<Tensor<T>, TensorError>;
+    /// Multiplies two tensors elementwise, broadcasting them to a common shape.
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.zip_with(&rhs, |a, b| a * b)
+    }
+}
+
+impl<T: TensorType + Neg<Output = T>> Neg for Tensor<T> {
+    type Output = Tensor<T>;
+    /// Negates a tensor elementwise.
+    fn neg(self) -> Self::Output {
+        self.map(|a| -a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tensor_get_index_and_set() {
+        let mut a = Tensor::<i128>::new(None, &[2, 3]).unwrap();
+        a.set(&[1, 2], 7);
+        assert_eq!(a.get(&[1, 2]), 7);
+        assert_eq!(a[5], 7);
+    }
+
+    #[test]
+    fn tensor_new_rejects_bad_dims() {
+        assert!(Tensor::<i128>::new(Some(&[1, 2, 3]), &[2, 2]).is_err());
+    }
+
+    #[test]
+    fn tensor_broadcast_arithmetic() {
+        let a = Tensor::<i128>::new(Some(&[1, 2, 3]), &[3, 1]).unwrap();
+        let b = Tensor::<i128>::new(Some(&[10, 20]), &[2]).unwrap();
+        let c = (a.clone() * b.clone()).unwrap();
+        assert_eq!(c.dims(), &[3, 2]);
+        assert_eq!(&c[..], &[10, 20, 20, 40, 30, 60]);
+        let d = (a - b).unwrap();
+        assert_eq!(&d[..], &[-9, -19, -8, -18, -7, -17]);
+    }
+
+    #[test]
+    fn tensor_fp_arithmetic() {
+        let a = Tensor::<Fp>::new(Some(&[Fp::from(2), Fp::from(3)]), &[2]).unwrap();
+        let b = Tensor::<Fp>::new(Some(&[Fp::from(5)]), &[1]).unwrap();
+        let c = (a + b).unwrap();
+        assert_eq!(&c[..], &[Fp::from(7), Fp::from(8)]);
+        assert_eq!((-c)[0], -Fp::from(7));
+    }
+
+    #[test]
+    fn tensor_permute_roundtrip() {
+        let a = Tensor::<i128>::new(Some(&(0..24).collect::<Vec<_>>()), &[2, 3, 4]).unwrap();
+        let b = a.move_axis(2, 0).unwrap();
+        assert_eq!(b.dims(), &[4, 2, 3]);
+        assert_eq!(b.get(&[3, 1, 2]), a.get(&[1, 2, 3]));
+        assert_eq!(b.move_axis(0, 2).unwrap(), a);
+    }
+}
@@ -0,0 +1,679 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::tensor::{index_iter, Tensor, TensorError, TensorType};
+
+/// Elementwise adds multiple tensors, broadcasting them to a common shape.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::add;
+/// let x = Tensor::<i128>::new(Some(&[2, 1, 2, 1, 1, 1]), &[2, 3]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[2, 3, 2, 1, 1, 1]), &[2, 3]).unwrap();
+/// let result = add(&[x, k]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 4, 4, 2, 2, 2]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn add<T: TensorType + Add<Output = T>>(t: &[Tensor<T>]) -> Result<Tensor<T>, TensorError> {
+    fold_inputs(t, "add", |a, b| a.zip_with(b, |x, y| x + y))
+}
+
+/// Elementwise subtracts the tensors that follow the first one from it, broadcasting them to a common shape.
+pub fn sub<T: TensorType + Sub<Output = T>>(t: &[Tensor<T>]) -> Result<Tensor<T>, TensorError> {
+    fold_inputs(t, "sub", |a, b| a.zip_with(b, |x, y| x - y))
+}
+
+/// Elementwise multiplies multiple tensors, broadcasting them to a common shape.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::mult;
+/// let x = Tensor::<i128>::new(Some(&[2, 1, 2, 1, 1, 1]), &[2, 3]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[2]), &[1]).unwrap();
+/// let result = mult(&[x, k]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 2, 4, 2, 2, 2]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn mult<T: TensorType + Mul<Output = T>>(t: &[Tensor<T>]) -> Result<Tensor<T>, TensorError> {
+    fold_inputs(t, "mult", |a, b| a.zip_with(b, |x, y| x * y))
+}
+
+fn fold_inputs<T: TensorType, F>(t: &[Tensor<T>], op: &str, f: F) -> Result<Tensor<T>, TensorError>
+where
+    F: Fn(&Tensor<T>, &Tensor<T>) -> Result<Tensor<T>, TensorError>,
+{
+    let (first, rest) = t
+        .split_first()
+        .ok_or_else(|| TensorError::DimMismatch(format!("{} requires at least one input", op)))?;
+    let mut output = first.clone();
+    for e in rest {
+        output = f(&output, e)?;
+    }
+    Ok(output)
+}
+
+/// Raises every element of a tensor to the power `exponent`.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::pow;
+/// let x = Tensor::<i128>::new(Some(&[2, 15, 2, 1, 1, 0]), &[2, 3]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 225, 4, 1, 1, 0]), &[2, 3]).unwrap();
+/// assert_eq!(pow(&x, 2).unwrap(), expected);
+/// ```
+pub fn pow<T: TensorType + Mul<Output = T>>(a: &Tensor<T>, exponent: u32) -> Result<Tensor<T>, TensorError> {
+    let one = T::one().ok_or_else(|| TensorError::Unsupported("element type has no unit value".to_string()))?;
+    Ok(a.map(|x| {
+        let mut acc = one.clone();
+        for _ in 0..exponent {
+            acc = acc * x.clone();
+        }
+        acc
+    }))
+}
+
+/// Sums all the elements of a tensor into a tensor of dims `[1]`.
+pub fn sum<T: TensorType + Add<Output = T>>(a: &Tensor<T>) -> Result<Tensor<T>, TensorError> {
+    let mut output = sum_axes(a, &(0..a.dims().len()).collect::<Vec<_>>())?;
+    output.reshape(&[1])?;
+    Ok(output)
+}
+
+/// Sums a tensor over `axes`, keeping the reduced axes as dimensions of size 1.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::sum_axes;
+/// let x = Tensor::<i128>::new(Some(&[2, 15, 2, 1, 1, 0]), &[2, 3]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[19, 2]), &[2, 1]).unwrap();
+/// assert_eq!(sum_axes(&x, &[1]).unwrap(), expected);
+/// ```
+pub fn sum_axes<T: TensorType + Add<Output = T>>(a: &Tensor<T>, axes: &[usize]) -> Result<Tensor<T>, TensorError> {
+    reduce_axes(a, axes, |acc, x| acc + x)
+}
+
+/// Multiplies all the elements of a tensor into a tensor of dims `[1]`.
+pub fn prod<T: TensorType + Mul<Output = T>>(a: &Tensor<T>) -> Result<Tensor<T>, TensorError> {
+    let mut output = prod_axes(a, &(0..a.dims().len()).collect::<Vec<_>>())?;
+    output.reshape(&[1])?;
+    Ok(output)
+}
+
+/// Multiplies a tensor over `axes`, keeping the reduced axes as dimensions of size 1.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::prod_axes;
+/// let x = Tensor::<i128>::new(Some(&[2, 15, 2, 1, 1, 0]), &[2, 3]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 15, 0]), &[1, 3]).unwrap();
+/// assert_eq!(prod_axes(&x, &[0]).unwrap(), expected);
+/// ```
+pub fn prod_axes<T: TensorType + Mul<Output = T>>(a: &Tensor<T>, axes: &[usize]) -> Result<Tensor<T>, TensorError> {
+    reduce_axes(a, axes, |acc, x| acc * x)
+}
+
+/// Folds a tensor over `axes` with `f`, keeping the reduced axes as dimensions of size 1.
+pub fn reduce_axes<T: TensorType, F: Fn(T, T) -> T>(
+    a: &Tensor<T>,
+    axes: &[usize],
+    f: F,
+) -> Result<Tensor<T>, TensorError> {
+    if let Some(axis) = axes.iter().find(|axis| **axis >= a.dims().len()) {
+        return Err(TensorError::AxisError(format!(
+            "cannot reduce over axis {} of a tensor of dims {:?}",
+            axis,
+            a.dims()
+        )));
+    }
+    let new_dims: Vec<usize> = a
+        .dims()
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if axes.contains(&i) { 1 } else { *d })
+        .collect();
+    let mut acc: Vec<Option<T>> = vec![None; new_dims.iter().product()];
+    let output_shape = Tensor::<usize>::new(None, &new_dims)?;
+    for (coord, x) in index_iter(a.dims()).zip(a.iter()) {
+        let out_coord: Vec<usize> = coord
+            .iter()
+            .enumerate()
+            .map(|(i, c)| if axes.contains(&i) { 0 } else { *c })
+            .collect();
+        let slot = &mut acc[output_shape.get_index(&out_coord)];
+        *slot = Some(match slot.take() {
+            Some(prev) => f(prev, x.clone()),
+            None => x.clone(),
+        });
+    }
+    let values = acc
+        .into_iter()
+        .map(|v| v.ok_or_else(|| TensorError::DimError("cannot reduce over an empty axis".to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut output = Tensor::new(Some(&values), &new_dims)?;
+    if let Some(scale) = a.scale() {
+        output.set_scale(scale);
+    }
+    Ok(output)
+}
+
+/// Concatenates a list of tensors along `axis`. All other dimensions must match.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::concat;
+/// let a = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[5, 6]), &[2, 1]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 5, 3, 4, 6]), &[2, 3]).unwrap();
+/// assert_eq!(concat(&[&a, &b], 1).unwrap(), expected);
+/// ```
+pub fn concat<T: TensorType>(inputs: &[&Tensor<T>], axis: usize) -> Result<Tensor<T>, TensorError> {
+    let first = inputs
+        .first()
+        .ok_or_else(|| TensorError::DimMismatch("concat requires at least one input".to_string()))?;
+    let rank = first.dims().len();
+    if axis >= rank {
+        return Err(TensorError::AxisError(format!(
+            "cannot concat along axis {} tensors of rank {}",
+            axis, rank
+        )));
+    }
+    for t in inputs {
+        let compatible = t.dims().len() == rank
+            && t.dims()
+                .iter()
+                .zip(first.dims())
+                .enumerate()
+                .all(|(i, (a, b))| i == axis || a == b);
+        if !compatible {
+            return Err(TensorError::DimMismatch(format!(
+                "concat of dims {:?} and {:?} along axis {}",
+                first.dims(),
+                t.dims(),
+                axis
+            )));
+        }
+    }
+    let mut new_dims = first.dims().to_vec();
+    new_dims[axis] = inputs.iter().map(|t| t.dims()[axis]).sum();
+
+    // every input contributes a contiguous block of `dims[axis] * inner` elements per outer index
+    let outer: usize = first.dims()[..axis].iter().product();
+    let inner: usize = first.dims()[axis + 1..].iter().product();
+    let mut values = Vec::with_capacity(new_dims.iter().product());
+    for o in 0..outer {
+        for t in inputs {
+            let block = t.dims()[axis] * inner;
+            values.extend_from_slice(&t[o * block..(o + 1) * block]);
+        }
+    }
+    Tensor::new(Some(&values), &new_dims)
+}
+
+/// Slices a tensor along `axis`, keeping the elements in `start..end`.
+pub fn slice<T: TensorType>(t: &Tensor<T>, axis: usize, start: usize, end: usize) -> Result<Tensor<T>, TensorError> {
+    if axis >= t.dims().len() {
+        return Err(TensorError::AxisError(format!(
+            "cannot slice axis {} of a tensor of dims {:?}",
+            axis,
+            t.dims()
+        )));
+    }
+    let ranges: Vec<_> = t
+        .dims()
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i == axis { start..end } else { 0..*d })
+        .collect();
+    t.get_slice(&ranges)
+}
+
+/// Keeps every `stride`-th element along `axis`, starting at `modulo`.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::downsample;
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 5]), &[2, 1]).unwrap();
+/// assert_eq!(downsample(&x, 1, 2, 1).unwrap(), expected);
+/// ```
+pub fn downsample<T: TensorType>(
+    input: &Tensor<T>,
+    axis: usize,
+    stride: usize,
+    modulo: usize,
+) -> Result<Tensor<T>, TensorError> {
+    if axis >= input.dims().len() || stride == 0 {
+        return Err(TensorError::AxisError(format!(
+            "cannot downsample axis {} with stride {} of a tensor of dims {:?}",
+            axis,
+            stride,
+            input.dims()
+        )));
+    }
+    let mut new_dims = input.dims().to_vec();
+    new_dims[axis] = input.dims()[axis].saturating_sub(modulo).div_ceil(stride);
+    let mut values = Vec::with_capacity(new_dims.iter().product());
+    for mut coord in index_iter(&new_dims) {
+        coord[axis] = modulo + coord[axis] * stride;
+        values.push(input.get(&coord));
+    }
+    Tensor::new(Some(&values), &new_dims)
+}
+
+/// Zero-pads the last two dimensions of a tensor by `padding` (`[(top, bottom), (left, right)]`).
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::pad;
+/// let x = Tensor::<i128>::new(Some(&[5, 2, 3, 0]), &[1, 1, 2, 2]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 0, 0, 0, 0, 5, 2, 0, 0, 3, 0, 0, 0, 0, 0, 0]), &[1, 1, 4, 4]).unwrap();
+/// assert_eq!(pad(&x, [(1, 1), (1, 1)]).unwrap(), expected);
+/// ```
+pub fn pad<T: TensorType>(image: &Tensor<T>, padding: [(usize, usize); 2]) -> Result<Tensor<T>, TensorError> {
+    let rank = image.dims().len();
+    if rank < 2 {
+        return Err(TensorError::DimError(format!(
+            "cannot pad a tensor of dims {:?}, expected at least two dimensions",
+            image.dims()
+        )));
+    }
+    let mut new_dims = image.dims().to_vec();
+    new_dims[rank - 2] += padding[0].0 + padding[0].1;
+    new_dims[rank - 1] += padding[1].0 + padding[1].1;
+    let mut output = Tensor::new(None, &new_dims)?;
+    for (coord, x) in index_iter(image.dims()).zip(image.iter()) {
+        let mut dest = coord.clone();
+        dest[rank - 2] += padding[0].0;
+        dest[rank - 1] += padding[1].0;
+        output.set(&dest, x.clone());
+    }
+    if let Some(scale) = image.scale() {
+        output.set_scale(scale);
+    }
+    Ok(output)
+}
+
+/// Nearest-neighbour upsampling of a tensor by an integer factor per dimension.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::resize;
+/// let x = Tensor::<i128>::new(Some(&[1, 2]), &[1, 2]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 1, 2, 2, 1, 1, 2, 2]), &[2, 4]).unwrap();
+/// assert_eq!(resize(&x, &[2, 2]).unwrap(), expected);
+/// ```
+pub fn resize<T: TensorType>(a: &Tensor<T>, scales: &[usize]) -> Result<Tensor<T>, TensorError> {
+    if scales.len() != a.dims().len() || scales.contains(&0) {
+        return Err(TensorError::DimMismatch(format!(
+            "resize of dims {:?} by scale factors {:?}",
+            a.dims(),
+            scales
+        )));
+    }
+    let new_dims: Vec<usize> = a.dims().iter().zip(scales).map(|(d, s)| d * s).collect();
+    let mut values = Vec::with_capacity(new_dims.iter().product());
+    for coord in index_iter(&new_dims) {
+        let src: Vec<usize> = coord.iter().zip(scales).map(|(c, s)| c / s).collect();
+        values.push(a.get(&src));
+    }
+    Tensor::new(Some(&values), &new_dims)
+}
+
+/// Selects elements from `a` where `mask` is one and from `b` where it is zero.
+pub fn iff<T: TensorType + Add<Output = T> + Sub<Output = T> + Mul<Output = T>>(
+    mask: &Tensor<T>,
+    a: &Tensor<T>,
+    b: &Tensor<T>,
+) -> Result<Tensor<T>, TensorError> {
+    // mask * a + (1 - mask) * b
+    let masked_a = mult(&[mask.clone(), a.clone()])?;
+    let inverted = not(mask)?;
+    let masked_b = mult(&[inverted, b.clone()])?;
+    add(&[masked_a, masked_b])
+}
+
+/// Boolean not (`1 - a`) over a tensor of zeros and ones.
+pub fn not<T: TensorType + Sub<Output = T>>(a: &Tensor<T>) -> Result<Tensor<T>, TensorError> {
+    let one = T::one().ok_or_else(|| TensorError::Unsupported("element type has no unit value".to_string()))?;
+    Ok(a.map(|x| one.clone() - x))
+}
+
+/// Boolean and (`a * b`) over tensors of zeros and ones.
+pub fn and<T: TensorType + Mul<Output = T>>(a: &Tensor<T>, b: &Tensor<T>) -> Result<Tensor<T>, TensorError> {
+    a.zip_with(b, |x, y| x * y)
+}
+
+/// Boolean or (`a + b - a * b`) over tensors of zeros and ones.
+pub fn or<T: TensorType + Add<Output = T> + Sub<Output = T> + Mul<Output = T>>(
+    a: &Tensor<T>,
+    b: &Tensor<T>,
+) -> Result<Tensor<T>, TensorError> {
+    a.zip_with(b, |x, y| x.clone() + y.clone() - x * y)
+}
+
+/// Boolean xor (`a + b - 2 * a * b`) over tensors of zeros and ones.
+pub fn xor<T: TensorType + Add<Output = T> + Sub<Output = T> + Mul<Output = T>>(
+    a: &Tensor<T>,
+    b: &Tensor<T>,
+) -> Result<Tensor<T>, TensorError> {
+    a.zip_with(b, |x, y| {
+        let xy = x.clone() * y.clone();
+        x + y - xy.clone() - xy
+    })
+}
+
+/// Packs a tensor into a single element as `sum_i a_i * base^(i * (scale + 1))`.
+pub fn pack<T: TensorType + Add<Output = T> + Mul<Output = T>>(
+    a: &Tensor<T>,
+    base: T,
+    scale: u32,
+) -> Result<Tensor<T>, TensorError> {
+    let zero = T::zero().ok_or_else(|| TensorError::Unsupported("element type has no zero value".to_string()))?;
+    let base_tensor = Tensor::new(Some(&[base]), &[1])?;
+    let mut output = zero;
+    for (i, a_i) in a.iter().enumerate() {
+        let pow_value = pow(&base_tensor, (i as u32) * (scale + 1))?[0].clone();
+        output = output + pow_value * a_i.clone();
+    }
+    Tensor::new(Some(&[output]), &[1])
+}
+
+/// Returns the NCHW output dims of a convolution of an image of dims `image_dims` with a kernel of dims `kernel_dims` (`[C_out, C_in / groups, KH, KW]`).
+/// ```
+/// use core_ezkl::tensorops::conv_output_dims;
+/// let dims = conv_output_dims(&[1, 3, 7, 7], &[4, 3, 3, 3], [(1, 1), (1, 1)], (2, 2)).unwrap();
+/// assert_eq!(dims, vec![1, 4, 4, 4]);
+/// ```
+pub fn conv_output_dims(
+    image_dims: &[usize],
+    kernel_dims: &[usize],
+    padding: [(usize, usize); 2],
+    stride: (usize, usize),
+) -> Result<Vec<usize>, TensorError> {
+    if image_dims.len() != 4 || kernel_dims.len() != 4 {
+        return Err(TensorError::DimMismatch(format!(
+            "conv expects an NCHW image and a 4D kernel, got {:?} and {:?}",
+            image_dims, kernel_dims
+        )));
+    }
+    if stride.0 == 0 || stride.1 == 0 {
+        return Err(TensorError::DimError("conv stride must be non-zero".to_string()));
+    }
+    let (c_in, c_out, cin_per_group) = (image_dims[1], kernel_dims[0], kernel_dims[1]);
+    let groups = c_in.checked_div(cin_per_group).unwrap_or(0);
+    // an image without channels leaves no groups to divide the output channels into
+    if groups == 0 || c_in % cin_per_group != 0 || c_out % groups != 0 {
+        return Err(TensorError::DimMismatch(format!(
+            "conv of an image with {} channels by a kernel of dims {:?}",
+            c_in, kernel_dims
+        )));
+    }
+    let padded_h = image_dims[2] + padding[0].0 + padding[0].1;
+    let padded_w = image_dims[3] + padding[1].0 + padding[1].1;
+    if padded_h < kernel_dims[2] || padded_w < kernel_dims[3] {
+        return Err(TensorError::DimMismatch(format!(
+            "conv kernel {:?} is larger than the padded image ({}, {})",
+            kernel_dims, padded_h, padded_w
+        )));
+    }
+    Ok(vec![
+        image_dims[0],
+        c_out,
+        (padded_h - kernel_dims[2]) / stride.0 + 1,
+        (padded_w - kernel_dims[3]) / stride.1 + 1,
+    ])
+}
+
+/// Returns the NCHW output dims of a transposed convolution of an image of dims `image_dims` with a kernel of dims `kernel_dims` (`[C_in, C_out, KH, KW]`).
+/// ```
+/// use core_ezkl::tensorops::deconv_output_dims;
+/// let dims = deconv_output_dims(&[1, 2, 3, 3], &[2, 1, 3, 3], [(1, 1), (1, 1)], (1, 1), (2, 2)).unwrap();
+/// assert_eq!(dims, vec![1, 1, 6, 6]);
+/// ```
+pub fn deconv_output_dims(
+    image_dims: &[usize],
+    kernel_dims: &[usize],
+    padding: [(usize, usize); 2],
+    output_padding: (usize, usize),
+    stride: (usize, usize),
+) -> Result<Vec<usize>, TensorError> {
+    if image_dims.len() != 4 || kernel_dims.len() != 4 || image_dims[1] != kernel_dims[0] {
+        return Err(TensorError::DimMismatch(format!(
+            "deconv expects an NCHW image and a kernel with matching input channels, got {:?} and {:?}",
+            image_dims, kernel_dims
+        )));
+    }
+    if [stride.0, stride.1, image_dims[2], image_dims[3], kernel_dims[2], kernel_dims[3]].contains(&0) {
+        return Err(TensorError::DimError("deconv stride, image and kernel dims must be non-zero".to_string()));
+    }
+    let full_h = (image_dims[2] - 1) * stride.0 + kernel_dims[2] + output_padding.0;
+    let full_w = (image_dims[3] - 1) * stride.1 + kernel_dims[3] + output_padding.1;
+    if full_h < padding[0].0 + padding[0].1 || full_w < padding[1].0 + padding[1].1 {
+        return Err(TensorError::DimMismatch(format!(
+            "deconv padding {:?} is larger than the output ({}, {})",
+            padding, full_h, full_w
+        )));
+    }
+    Ok(vec![
+        image_dims[0],
+        kernel_dims[1],
+        full_h - padding[0].0 - padding[0].1,
+        full_w - padding[1].0 - padding[1].1,
+    ])
+}
+
+/// Applies a 2D convolution over an NCHW image, with a kernel of dims `[C_out, C_in / groups, KH, KW]` and an optional bias of length `C_out`.
+/// Every output element accumulates `image * kernel` over (channel, kernel row, kernel column), in that order, before the bias is added -- the same order as the circuit's dot products.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::conv;
+/// let x = Tensor::<i128>::new(Some(&[5, 2, 3, 0, 4, -1, 3, 1, 6]), &[1, 1, 3, 3]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[5, 1, 1, 1]), &[1, 1, 2, 2]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[0]), &[1]).unwrap();
+/// let result = conv(&x, &k, Some(&b), [(0, 0); 2], (1, 1)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[31, 16, 8, 26]), &[1, 1, 2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn conv<T: TensorType + Add<Output = T> + Mul<Output = T>>(
+    image: &Tensor<T>,
+    kernel: &Tensor<T>,
+    bias: Option<&Tensor<T>>,
+    padding: [(usize, usize); 2],
+    stride: (usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    let output_dims = conv_output_dims(image.dims(), kernel.dims(), padding, stride)?;
+    let (c_out, cin_per_group, kh, kw) = (kernel.dims()[0], kernel.dims()[1], kernel.dims()[2], kernel.dims()[3]);
+    let groups = image.dims()[1] / cin_per_group;
+    let cout_per_group = c_out / groups;
+
+    if let Some(b) = bias {
+        if b.len() != c_out {
+            return Err(TensorError::DimMismatch(format!(
+                "conv bias of dims {:?} for {} output channels",
+                b.dims(),
+                c_out
+            )));
+        }
+    }
+
+    let padded = pad(image, padding)?;
+    let zero = T::zero().ok_or_else(|| TensorError::Unsupported("element type has no zero value".to_string()))?;
+    let mut values = Vec::with_capacity(output_dims.iter().product());
+    for coord in index_iter(&output_dims) {
+        let (n, oc, i, j) = (coord[0], coord[1], coord[2], coord[3]);
+        let group = oc / cout_per_group;
+        let mut acc = zero.clone();
+        for c in 0..cin_per_group {
+            for ki in 0..kh {
+                for kj in 0..kw {
+                    let x = padded.get(&[n, group * cin_per_group + c, i * stride.0 + ki, j * stride.1 + kj]);
+                    acc = acc + x * kernel.get(&[oc, c, ki, kj]);
+                }
+            }
+        }
+        if let Some(b) = bias {
+            acc = acc + b[oc].clone();
+        }
+        values.push(acc);
+    }
+    let mut output = Tensor::new(Some(&values), &output_dims)?;
+    if let (Some(image_scale), Some(kernel_scale)) = (image.scale(), kernel.scale()) {
+        output.set_scale(image_scale + kernel_scale);
+    }
+    Ok(output)
+}
+
+/// Inserts `stride - 1` zeros between consecutive elements along `axis`.
+pub fn intercalate_values<T: TensorType>(
+    tensor: &Tensor<T>,
+    value: T,
+    stride: usize,
+    axis: usize,
+) -> Result<Tensor<T>, TensorError> {
+    if axis >= tensor.dims().len() || stride == 0 {
+        return Err(TensorError::AxisError(format!(
+            "cannot intercalate axis {} with stride {} of a tensor of dims {:?}",
+            axis,
+            stride,
+            tensor.dims()
+        )));
+    }
+    if stride == 1 || tensor.dims()[axis] == 0 {
+        return Ok(tensor.clone());
+    }
+    let mut new_dims = tensor.dims().to_vec();
+    new_dims[axis] = (tensor.dims()[axis] - 1) * stride + 1;
+    let mut output = Tensor::new(Some(&vec![value; new_dims.iter().product()]), &new_dims)?;
+    for (mut coord, x) in index_iter(tensor.dims()).zip(tensor.iter()) {
+        coord[axis] *= stride;
+        output.set(&coord, x.clone());
+    }
+    Ok(output)
+}
+
+/// Applies a 2D transposed convolution over an NCHW image, with a kernel of dims `[C_in, C_out, KH, KW]` and an optional bias of length `C_out`.
+/// The image is dilated by `stride`, padded by the kernel size (plus `output_padding` at the end), convolved with the spatially flipped kernel, and the output is then cropped by `padding`.
+/// ```
+/// use core_ezkl::tensor::Tensor;
+/// use core_ezkl::tensorops::deconv;
+/// let x = Tensor::<i128>::new(Some(&[2, 4, 0, 1]), &[1, 1, 2, 2]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[3, 1, 1, 5]), &[1, 1, 2, 2]).unwrap();
+/// let result = deconv(&x, &k, None, [(0, 0); 2], (0, 0), (1, 1)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[6, 14, 4, 2, 17, 21, 0, 1, 5]), &[1, 1, 3, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn deconv<T: TensorType + Add<Output = T> + Mul<Output = T>>(
+    image: &Tensor<T>,
+    kernel: &Tensor<T>,
+    bias: Option<&Tensor<T>>,
+    padding: [(usize, usize); 2],
+    output_padding: (usize, usize),
+    stride: (usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    let output_dims = deconv_output_dims(image.dims(), kernel.dims(), padding, output_padding, stride)?;
+    let (c_in, c_out, kh, kw) = (kernel.dims()[0], kernel.dims()[1], kernel.dims()[2], kernel.dims()[3]);
+    let zero = T::zero().ok_or_else(|| TensorError::Unsupported("element type has no zero value".to_string()))?;
+
+    let dilated = intercalate_values(image, zero.clone(), stride.0, 2)?;
+    let dilated = intercalate_values(&dilated, zero, stride.1, 3)?;
+    let expanded = pad(
+        &dilated,
+        [(kh - 1, kh - 1 + output_padding.0), (kw - 1, kw - 1 + output_padding.1)],
+    )?;
+
+    // swap the channel axes and flip the kernel spatially
+    let mut flipped = Vec::with_capacity(kernel.len());
+    for oc in 0..c_out {
+        for ic in 0..c_in {
+            for ki in 0..kh {
+                for kj in 0..kw {
+                    flipped.push(kernel.get(&[ic, oc, kh - 1 - ki, kw - 1 - kj]));
+                }
+            }
+        }
+    }
+    let mut flipped = Tensor::new(Some(&flipped), &[c_out, c_in, kh, kw])?;
+    if let Some(scale) = kernel.scale() {
+        flipped.set_scale(scale);
+    }
+
+    let full = conv(&expanded, &flipped, bias, [(0, 0); 2], (1, 1))?;
+    let full_dims = full.dims().to_vec();
+    let mut output = full.get_slice(&[
+        0..full_dims[0],
+        0..full_dims[1],
+        padding[0].0..full_dims[2] - padding[0].1,
+        padding[1].0..full_dims[3] - padding[1].1,
+    ])?;
+    debug_assert_eq!(output.dims(), &output_dims[..]);
+    if let Some(scale) = full.scale() {
+        output.set_scale(scale);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tensor(values: &[i128], dims: &[usize]) -> Tensor<i128> {
+        Tensor::new(Some(values), dims).unwrap()
+    }
+
+    #[test]
+    fn conv_with_groups() {
+        let x = tensor(&(1..=36).collect::<Vec<_>>(), &[1, 4, 3, 3]);
+        // two groups of two input channels, one output channel per group
+        let k = tensor(&[1, 0, 0, 1, 2, 1, 0, -1, -1, 1, 1, 0, 0, 2, 1, 1], &[2, 2, 2, 2]);
+        let b = tensor(&[1, -1], &[2]);
+        let result = conv(&x, &k, Some(&b), [(0, 0); 2], (1, 1)).unwrap();
+        assert_eq!(result, tensor(&[24, 28, 36, 40, 143, 148, 158, 163], &[1, 2, 2, 2]));
+    }
+
+    #[test]
+    fn conv_with_stride_and_padding() {
+        let x = tensor(&[1, 2, 0, -1, 3, 1, 2, 2, 0, -2, 1, 4, 2, 1, 3, 0], &[1, 1, 4, 4]);
+        let k = tensor(&[1, 0, -1, 2, 1, 0, 0, 1, 1], &[1, 1, 3, 3]);
+        let result = conv(&x, &k, None, [(1, 1), (1, 1)], (2, 2)).unwrap();
+        assert_eq!(result, tensor(&[5, 8, 2, -1], &[1, 1, 2, 2]));
+    }
+
+    #[test]
+    fn conv_with_asymmetric_padding() {
+        let x = tensor(&[1, 2, 3, 4], &[1, 1, 2, 2]);
+        let k = tensor(&[1, 1, 1, 1], &[1, 1, 2, 2]);
+        let result = conv(&x, &k, None, [(1, 0), (0, 1)], (1, 1)).unwrap();
+        assert_eq!(result, tensor(&[3, 2, 10, 6], &[1, 1, 2, 2]));
+    }
+
+    #[test]
+    fn conv_rejects_mismatched_shapes() {
+        let x = tensor(&[1, 2, 3, 4], &[1, 1, 2, 2]);
+        let k = tensor(&[1; 9], &[1, 1, 3, 3]);
+        assert!(conv(&x, &k, None, [(0, 0); 2], (1, 1)).is_err());
+        let k = tensor(&[1; 4], &[1, 1, 2, 2]);
+        let b = tensor(&[1, 2], &[2]);
+        assert!(conv(&x, &k, Some(&b), [(0, 0); 2], (1, 1)).is_err());
+    }
+
+    #[test]
+    fn conv_rejects_images_without_channels() {
+        assert!(matches!(
+            conv_output_dims(&[1, 0, 2, 2], &[2, 3, 1, 1], [(0, 0); 2], (1, 1)),
+            Err(TensorError::DimMismatch(_))
+        ));
+        assert!(matches!(
+            conv_output_dims(&[1, 0, 2, 2], &[0, 0, 1, 1], [(0, 0); 2], (1, 1)),
+            Err(TensorError::DimMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn deconv_with_stride_padding_and_output_padding() {
+        let x = tensor(&[1, 2, 3, 4, -1, 0, 2, 1], &[1, 2, 2, 2]);
+        let k = tensor(&[1, 2, 0, 1, 1, -1, 2, 0], &[2, 1, 2, 2]);
+        let b = tensor(&[3], &[1]);
+        let result = deconv(&x, &k, Some(&b), [(1, 1), (1, 1)], (1, 1), (2, 2)).unwrap();
+        assert_eq!(result, tensor(&[4, 3, 5, 7, 8, 10, 6, 5, 7], &[1, 1, 3, 3]));
+        // the output padding only extends the bottom and right edges
+        let result = deconv(&x, &k, Some(&b), [(1, 1), (1, 1)], (0, 0), (2, 2)).unwrap();
+        assert_eq!(result, tensor(&[4, 3, 7, 8], &[1, 1, 2, 2]));
+    }
+
+    #[test]
+    fn deconv_output_dims_match_deconv() {
+        let dims = deconv_output_dims(&[1, 2, 2, 2], &[2, 1, 2, 2], [(1, 1), (1, 1)], (1, 1), (2, 2)).unwrap();
+        assert_eq!(dims, vec![1, 1, 3, 3]);
+        assert!(deconv_output_dims(&[1, 3, 2, 2], &[2, 1, 2, 2], [(0, 0); 2], (0, 0), (1, 1)).is_err());
+    }
+}
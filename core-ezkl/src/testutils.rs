@@ -0,0 +1,82 @@
+//! Builders for the small models used by the unit tests.
+
+use halo2curves::bn256::Fr as Fp;
+
+use crate::fieldutils::{i128_to_felt, quantize_float};
+use crate::model::{InputMapping, Model, Node, NodeType, Outlet, OutputMapping, ParsedNodes, SupportedOp};
+use crate::supportedop::{Constant, Input, InputType};
+use crate::tensor::Tensor;
+use crate::utils::Scale;
+
+/// A tensor of field elements holding `values`.
+pub fn felts(values: &[i128], dims: &[usize]) -> Tensor<Fp> {
+    let values: Vec<Fp> = values.iter().map(|x| i128_to_felt(*x)).collect();
+    Tensor::new(Some(&values), dims).unwrap()
+}
+
+/// A node used once, applying `opkind` to `inputs`.
+pub fn node(idx: usize, opkind: SupportedOp, inputs: &[Outlet], out_dims: &[usize], out_scale: Scale) -> NodeType {
+    NodeType::Node(Node {
+        opkind,
+        out_scale,
+        inputs: inputs.to_vec(),
+        out_dims: out_dims.to_vec(),
+        idx,
+        num_uses: 1,
+    })
+}
+
+/// A model input.
+pub fn input(idx: usize, dims: &[usize], scale: Scale) -> NodeType {
+    let opkind = SupportedOp::Input(Input {
+        scale,
+        datum_type: InputType::F32,
+    });
+    node(idx, opkind, &[], dims, scale)
+}
+
+/// A constant holding `raw` quantized at `scale`.
+pub fn constant(idx: usize, raw: &[f32], dims: &[usize], scale: Scale) -> NodeType {
+    let quantized: Vec<i128> = raw
+        .iter()
+        .map(|x| quantize_float(&(*x as f64), 0.0, scale).unwrap())
+        .collect();
+    let opkind = SupportedOp::Constant(Constant {
+        quantized_values: felts(&quantized, dims),
+        raw_values: Tensor::new(Some(raw), dims).unwrap(),
+    });
+    node(idx, opkind, &[], dims, scale)
+}
+
+/// A Scan-like subgraph running `body` over `inputs`.
+pub fn subgraph(
+    idx: usize,
+    body: Model,
+    inputs: &[Outlet],
+    input_mappings: Vec<InputMapping>,
+    output_mappings: Vec<Vec<OutputMapping>>,
+    out_dims: Vec<Vec<usize>>,
+    out_scales: Vec<Scale>,
+) -> NodeType {
+    NodeType::SubGraph {
+        model: body,
+        inputs: inputs.to_vec(),
+        idx,
+        output_mappings,
+        input_mappings,
+        out_dims,
+        out_scales,
+    }
+}
+
+/// A model holding `nodes`, keyed by their index.
+pub fn model(nodes: Vec<NodeType>, inputs: &[usize], outputs: &[Outlet]) -> Model {
+    Model {
+        graph: ParsedNodes {
+            nodes: nodes.into_iter().map(|n| (n.idx(), n)).collect(),
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        },
+        visibility: Default::default(),
+    }
+}
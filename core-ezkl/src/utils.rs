@@ -1,3 +1,6 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 // --------------------------------------------------------------------------------------------
@@ -10,7 +13,18 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
-pub type Scale: i32;
+/// The denominator in the fixed point representation
+pub type Scale = i32;
+
+/// Converts a scale (log base 2) to a fixed point multiplier.
+pub fn scale_to_multiplier(scale: Scale) -> f64 {
+    f64::powf(2., scale as f64)
+}
+
+/// Converts a fixed point multiplier to a scale (log base 2), rounding to the nearest integer.
+pub fn multiplier_to_scale(mult: f64) -> Scale {
+    mult.log2().round() as Scale
+}
 
 #[derive(Debug, Default, Clone, Copy)]
 /// f32 wrapper
@@ -35,6 +49,20 @@ impl Serialize for F32 {
     }
 }
 
+impl JsonSchema for F32 {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        f32::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        f32::json_schema(gen)
+    }
+}
+
 /// This works like `PartialEq` on `f32`, except that `NAN == NAN` is true.
 impl PartialEq for F32 {
     fn eq(&self, other: &Self) -> bool {
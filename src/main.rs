@@ -1,23 +1,180 @@
 use core_ezkl::*;
+use core_ezkl::commands::{Cli, Commands};
+use core_ezkl::graphsettings::GraphSettings;
+use core_ezkl::model::Model;
+use clap::Parser;
+use std::error::Error;
+use std::path::Path;
 use halo2curves::bn256::{Fr};
-use halo2curves::CurveAffine;
-use halo2_proofs::poly::commitment::{Params, CommitmentScheme};
-use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
 
-
-// use halo2curves::bn256::fr::Fr;
-// use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
-
-const SETTINGS_JSON: &str = include_str!("../settings.json");
+const SETTINGS: &str = "settings.json";
 const KZG_SRS: &str = "kzg.srs";
 const VK: &str = "test.vk";
 const PROOF: &str = include_str!("../proof.json");
 
+fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let json = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn run(command: Commands) -> Result<(), Box<dyn Error>> {
+    match command {
+        Commands::Stats { model, settings } => {
+            let model: Model = load_json(&model)?;
+            let settings: Option<GraphSettings> = match settings {
+                Some(path) => Some(GraphSettings::load(&path)?),
+                None => None,
+            };
+            print!("{}", stats::model_stats(&model, settings.as_ref()));
+        }
+        Commands::Optimize {
+            model,
+            settings,
+            output,
+        } => {
+            let model: Model = load_json(&model)?;
+            let settings = GraphSettings::load(&settings)?;
+            let (optimized, report) = optimize::optimize(&model, &settings.run_args)?;
+            std::fs::write(output, serde_json::to_string(&optimized)?)?;
+            print!("{}", report);
+        }
+        Commands::Diff {
+            before,
+            after,
+            before_settings,
+            after_settings,
+        } => {
+            let before: Model = load_json(&before)?;
+            let after: Model = load_json(&after)?;
+            let settings: Option<(GraphSettings, GraphSettings)> = match (before_settings, after_settings) {
+                (Some(old), Some(new)) => Some((GraphSettings::load(&old)?, GraphSettings::load(&new)?)),
+                _ => None,
+            };
+            let diff = diff::diff_models(&before, &after, settings.as_ref().map(|(old, new)| (old, new)));
+            print!("{}", diff);
+        }
+        Commands::ValidateWitness {
+            witness,
+            settings,
+            proof,
+        } => {
+            let witness = graphwitness::GraphWitness::load(&witness)?;
+            let settings = GraphSettings::load(&settings)?;
+            let snark = match proof {
+                Some(path) => Some(load_json::<snark::Snark<Fr>>(&path)?),
+                None => None,
+            };
+            let report = witness.validate(&settings, snark.as_ref());
+            print!("{}", report);
+            if !report.is_valid() {
+                return Err("the witness is invalid".into());
+            }
+        }
+        Commands::VerifyInputs {
+            data,
+            settings,
+            proof,
+        } => {
+            let data: serde_json::Value = load_json(&data)?;
+            let data: Vec<Vec<f64>> = serde_json::from_value(match data.get("input_data") {
+                Some(input_data) => input_data.clone(),
+                None => data,
+            })?;
+            let settings = GraphSettings::load(&settings)?;
+            let snark: snark::Snark<Fr> = load_json(&proof)?;
+            poseidon::verify_hashed_inputs(&data, &settings, &snark)?;
+            println!("the input data matches the hashed inputs of the proof");
+        }
+        Commands::Instances { proof, settings } => {
+            let settings = GraphSettings::load(&settings)?;
+            let snark: snark::Snark<Fr> = load_json(&proof)?;
+            let layout = instances::InstanceLayout::from_settings(&settings)?;
+            print!("{}", layout);
+            for (entry, felts) in layout.split(&snark)? {
+                let felts: Vec<String> = felts.iter().map(|x| fieldutils::felt_to_hex(*x)).collect();
+                println!("{}: {:?}", entry.name, felts);
+            }
+        }
+        Commands::CheckSettings { settings, mode } => {
+            let parsed = GraphSettings::parse(&std::fs::read_to_string(settings)?, mode)?;
+            for migration in &parsed.migrations {
+                println!("migrated: {}", migration);
+            }
+            for warning in &parsed.warnings {
+                println!("warning: {}", warning);
+            }
+            println!("settings produced by ezkl {} are valid", parsed.settings.version);
+        }
+        Commands::Schema { target, output } => {
+            let schema = serde_json::to_string_pretty(&schema::schema(target))?;
+            match output {
+                Some(path) => std::fs::write(path, schema)?,
+                None => println!("{}", schema),
+            }
+        }
+        Commands::CheckPolicy {
+            proof,
+            settings,
+            witness,
+            model,
+            vk,
+            forbid_unsafe,
+            allow_partial,
+        } => {
+            let settings = GraphSettings::load(&settings)?;
+            let snark: snark::Snark<Fr> = load_json(&proof)?;
+            let witness = match witness {
+                Some(path) => Some(graphwitness::GraphWitness::load(&path)?),
+                None => None,
+            };
+            let model: Option<Model> = match model {
+                Some(path) => Some(load_json(&path)?),
+                None => None,
+            };
+            let ctx = policy::VerificationContext {
+                settings: &settings,
+                snark: &snark,
+                witness: witness.as_ref(),
+                model: model.as_ref(),
+                vk_k: match vk {
+                    Some(path) => Some(policy::vk_logrows(path)?),
+                    None => None,
+                },
+            };
+            let report = policy::VerificationPolicy {
+                forbid_unsafe,
+                allow_partial,
+            }
+            .apply(&ctx);
+            print!("{}", report);
+            if !report.is_accepted() {
+                return Err("the proof was rejected by the verification policy".into());
+            }
+        }
+    }
+    Ok(())
+}
+
 fn main() {
-    let v_params = get_verifier_params(SETTINGS_JSON, KZG_SRS);
-    let v_params_bytes = v_params_to_bytes(v_params);
-    let ezkl_snark = Snark::<Fr>::load(PROOF);
-    //println!("params bytes: {:?}", v_params_bytes.len());
-    // let _ = ezkl_snark.format_instances();
-    //println!("Proof bytes is: {:?}", &ezkl_snark.proof[..]);
+    if let Some(command) = Cli::parse().command {
+        if let Err(e) = run(command) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    match srs_params::get_verifier_params(SETTINGS, KZG_SRS) {
+        Ok(v_params) => println!("verifier params: {} bytes", srs_params::v_params_to_bytes(v_params).len()),
+        Err(e) => eprintln!("error: {}", e),
+    }
+    match policy::vk_logrows(VK) {
+        Ok(k) => println!("vk k: {}", k),
+        Err(e) => eprintln!("error: {}", e),
+    }
+    let ezkl_snark = snark::Snark::<Fr>::load(PROOF);
+    println!(
+        "proof: {} bytes, {} instance columns",
+        ezkl_snark.proof.len(),
+        ezkl_snark.instances.len()
+    );
 }